@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS audit_logs (
+	id SERIAL PRIMARY KEY,
+	UUID UUID UNIQUE DEFAULT uuid_generate_v4 (),
+	actor_id INT REFERENCES users (id) ON DELETE SET NULL,
+	"action" VARCHAR(50) NOT NULL,
+	target_uuid UUID,
+	details TEXT,
+	created_at TIMESTAMPTZ(0) NOT NULL DEFAULT NOW()
+);
+
+-- Indexes for audit_logs
+CREATE INDEX IF NOT EXISTS idx_audit_logs_actor_id ON audit_logs (actor_id);
+CREATE INDEX IF NOT EXISTS idx_audit_logs_target_uuid ON audit_logs (target_uuid);
+CREATE INDEX IF NOT EXISTS idx_audit_logs_created_at ON audit_logs (created_at);
@@ -0,0 +1,5 @@
+ALTER TABLE user_info ALTER COLUMN hufa_code DROP NOT NULL;
+ALTER TABLE user_info ALTER COLUMN agent_code DROP NOT NULL;
+
+-- Empty codes are treated as missing so they don't collide on the unique constraints
+UPDATE user_info SET hufa_code = NULLIF(TRIM(hufa_code), ''), agent_code = NULLIF(TRIM(agent_code), '');
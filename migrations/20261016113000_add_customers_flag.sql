@@ -0,0 +1,4 @@
+ALTER TABLE customers ADD COLUMN IF NOT EXISTS flag VARCHAR(20);
+ALTER TABLE customers ADD CONSTRAINT customers_flag_check CHECK (flag IN ('Vip', 'DoNotContact', 'Prospect', 'Inactive'));
+
+CREATE INDEX IF NOT EXISTS idx_customers_flag ON customers (flag);
@@ -0,0 +1,3 @@
+ALTER TABLE customer_intervention_tasks ADD COLUMN IF NOT EXISTS contract_id INT REFERENCES customer_contracts (id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_customer_intervention_tasks_contract_id ON customer_intervention_tasks (contract_id);
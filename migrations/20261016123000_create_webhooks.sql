@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS webhooks (
+	id SERIAL PRIMARY KEY,
+	UUID UUID UNIQUE DEFAULT uuid_generate_v4 (),
+	url TEXT NOT NULL,
+	secret VARCHAR(64) NOT NULL,
+	event_types TEXT[] NOT NULL,
+	created_by INT REFERENCES users (id) ON DELETE SET NULL,
+	created_at TIMESTAMPTZ(0) NOT NULL DEFAULT NOW(),
+	CONSTRAINT webhooks_event_types_check CHECK (event_types <@ ARRAY['ContractCreated', 'ContractModified', 'ContractDeleted', 'LeadStatusChanged']::TEXT[])
+);
+
+-- Indexes for webhooks
+CREATE INDEX IF NOT EXISTS idx_webhooks_event_types ON webhooks USING GIN (event_types);
@@ -0,0 +1,30 @@
+ALTER TABLE customer_leads ADD COLUMN IF NOT EXISTS inquiry_type_raw TEXT;
+
+UPDATE customer_leads SET inquiry_type_raw = inquiry_type WHERE inquiry_type_raw IS NULL;
+
+-- Normalize existing values the same way InquiryType::normalize does: whole words after
+-- folding accents, or the word followed by "biztosítás"/"biztosító", first type in order wins
+WITH synonyms(rank, inquiry_type, synonym) AS (
+	VALUES
+		(1, 'Health', 'egeszseg'), (1, 'Health', 'health'), (1, 'Health', 'care'),
+		(2, 'Car', 'auto'), (2, 'Car', 'car'), (2, 'Car', 'kgfb'), (2, 'Car', 'casco'), (2, 'Car', 'gepjarmu'), (2, 'Car', 'jarmu'),
+		(3, 'Home', 'lakas'), (3, 'Home', 'otthon'), (3, 'Home', 'haz'), (3, 'Home', 'home'), (3, 'Home', 'ingatlan'), (3, 'Home', 'tarsashaz'),
+		(4, 'Life', 'elet'), (4, 'Life', 'life'),
+		(5, 'Travel', 'utas'), (5, 'Travel', 'travel'), (5, 'Travel', 'utazas'),
+		(6, 'Savings', 'megtakaritas'), (6, 'Savings', 'nyugdij'), (6, 'Savings', 'befektetes'), (6, 'Savings', 'savings'), (6, 'Savings', 'pension'),
+		(7, 'Corporate', 'vallalat'), (7, 'Corporate', 'vallalati'), (7, 'Corporate', 'ceg'), (7, 'Corporate', 'ceges'), (7, 'Corporate', 'corporate'), (7, 'Corporate', 'mezogazdasag')
+), words AS (
+	SELECT l.id, w.word
+	FROM customer_leads l,
+		REGEXP_SPLIT_TO_TABLE(TRANSLATE(LOWER(l.inquiry_type_raw), 'áéíóöőúüű', 'aeiooouuu'), '[^a-z0-9]+') AS w(word)
+), matched AS (
+	SELECT DISTINCT ON (w.id) w.id, s.inquiry_type
+	FROM words w
+	JOIN synonyms s ON w.word IN (s.synonym, s.synonym || 'biztositas', s.synonym || 'biztosito')
+	ORDER BY w.id, s.rank
+)
+UPDATE customer_leads l
+SET inquiry_type = COALESCE(m.inquiry_type, REGEXP_REPLACE(TRIM(l.inquiry_type_raw), '\s+', ' ', 'g'))
+FROM customer_leads l2
+LEFT JOIN matched m ON m.id = l2.id
+WHERE l2.id = l.id AND l.inquiry_type_raw IS NOT NULL;
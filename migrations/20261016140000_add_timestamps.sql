@@ -0,0 +1,76 @@
+CREATE OR REPLACE FUNCTION set_updated_at() RETURNS TRIGGER AS $$
+BEGIN
+	NEW.updated_at = NOW();
+	RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$
+DECLARE
+	t TEXT;
+BEGIN
+	FOREACH t IN ARRAY ARRAY[
+		'users',
+		'customers',
+		'customer_leads',
+		'user_dates',
+		'customer_contracts',
+		'customer_intervention_tasks',
+		'customer_recommendations',
+		'recruitment'
+	] LOOP
+		EXECUTE format('ALTER TABLE %I ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ(0) NOT NULL DEFAULT NOW()', t);
+		EXECUTE format('ALTER TABLE %I ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ(0) NOT NULL DEFAULT NOW()', t);
+	END LOOP;
+END;
+$$;
+
+-- Existing rows got NOW() above, give them the best timestamp they have instead. Rows with
+-- nothing to go on get the date the first migration shipped, they can't be older.
+UPDATE customer_contracts SET created_at = handle_at, updated_at = handle_at;
+
+UPDATE customer_leads SET created_at = handle_at, updated_at = handle_at;
+
+UPDATE customers c
+SET created_at = COALESCE(
+		LEAST(
+			(SELECT MIN(l.handle_at) FROM customer_leads l WHERE l.customer_id = c.id),
+			(SELECT MIN(cc.handle_at) FROM customer_contracts cc WHERE cc.customer_id = c.id)
+		),
+		'2025-07-21'
+	);
+UPDATE customers SET updated_at = created_at;
+
+UPDATE customer_intervention_tasks t
+SET created_at = COALESCE(
+		(SELECT MIN(cc.handle_at) FROM customer_contracts cc WHERE cc.contract_number = t.contract_number),
+		'2025-07-21'
+	);
+UPDATE customer_intervention_tasks SET updated_at = created_at;
+
+UPDATE customer_recommendations SET created_at = '2025-07-21', updated_at = '2025-07-21';
+
+DO $$
+DECLARE
+	t TEXT;
+BEGIN
+	FOREACH t IN ARRAY ARRAY[
+		'customers',
+		'customer_leads',
+		'user_dates',
+		'customer_contracts',
+		'customer_intervention_tasks',
+		'customer_recommendations',
+		'recruitment'
+	] LOOP
+		EXECUTE format('DROP TRIGGER IF EXISTS %I ON %I', t || '_set_updated_at', t);
+		EXECUTE format('CREATE TRIGGER %I BEFORE UPDATE ON %I FOR EACH ROW EXECUTE FUNCTION set_updated_at()', t || '_set_updated_at', t);
+	END LOOP;
+END;
+$$;
+
+-- Signing in only touches last_login_at, that is not a change to the user
+DROP TRIGGER IF EXISTS users_set_updated_at ON users;
+CREATE TRIGGER users_set_updated_at BEFORE UPDATE ON users FOR EACH ROW
+WHEN ((to_jsonb(OLD) - 'last_login_at' - 'updated_at') IS DISTINCT FROM (to_jsonb(NEW) - 'last_login_at' - 'updated_at'))
+EXECUTE FUNCTION set_updated_at();
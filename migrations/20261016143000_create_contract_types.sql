@@ -0,0 +1,24 @@
+CREATE TABLE IF NOT EXISTS contract_types (
+	name VARCHAR(64) PRIMARY KEY,
+	created_at TIMESTAMP(0) WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO contract_types(name) VALUES
+	('BonusLifeProgram'),
+	('LifeProgram'),
+	('AllianzCareNow'),
+	('HealthProgram'),
+	('MyhomeHomeInsurance'),
+	('MfoHomeInsurance'),
+	('CorporatePropertyInsurance'),
+	('Kgfb'),
+	('Casco'),
+	('TravelInsurance'),
+	('CondominiumInsurance'),
+	('AgriculturalInsurance')
+ON CONFLICT (name) DO NOTHING;
+
+ALTER TABLE customer_contracts DROP CONSTRAINT IF EXISTS contract_type_check;
+ALTER TABLE customer_contracts
+	ADD CONSTRAINT customer_contracts_contract_type_fkey
+	FOREIGN KEY (contract_type) REFERENCES contract_types(name) ON UPDATE CASCADE;
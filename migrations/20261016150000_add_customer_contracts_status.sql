@@ -0,0 +1,7 @@
+ALTER TABLE customer_contracts
+	ADD COLUMN IF NOT EXISTS status VARCHAR(32) NOT NULL DEFAULT 'Active';
+
+ALTER TABLE customer_contracts
+	ADD CONSTRAINT contract_status_check CHECK (status IN ('Active', 'Pending', 'Lapsed', 'Cancelled'));
+
+CREATE INDEX IF NOT EXISTS idx_customer_contracts_status ON customer_contracts (status);
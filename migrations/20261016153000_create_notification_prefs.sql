@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS notification_prefs (
+	user_id INT PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+	digest_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+	reminder_lead_minutes INT NOT NULL DEFAULT 60,
+	channel VARCHAR(32) NOT NULL DEFAULT 'Email',
+	updated_at TIMESTAMPTZ(0) NOT NULL DEFAULT NOW(),
+	CONSTRAINT notification_prefs_channel_check CHECK (channel IN ('Email', 'Sms')),
+	CONSTRAINT notification_prefs_reminder_lead_minutes_check CHECK (reminder_lead_minutes BETWEEN 0 AND 10080)
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS commission_rates (
+	contract_type VARCHAR(64) PRIMARY KEY REFERENCES contract_types (name) ON UPDATE CASCADE ON DELETE CASCADE,
+	rate_percent DOUBLE PRECISION NOT NULL,
+	updated_at TIMESTAMPTZ(0) NOT NULL DEFAULT NOW(),
+	CONSTRAINT commission_rates_rate_percent_check CHECK (rate_percent BETWEEN 0 AND 100)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE customer_contracts
+	ADD COLUMN IF NOT EXISTS is_renewal BOOLEAN NOT NULL DEFAULT FALSE;
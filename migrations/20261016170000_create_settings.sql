@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS settings (
+	key VARCHAR(64) PRIMARY KEY,
+	value TEXT NOT NULL,
+	updated_by INT REFERENCES users (id) ON DELETE SET NULL,
+	updated_at TIMESTAMPTZ(0) NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,12 @@
+ALTER TABLE customers
+	ADD COLUMN IF NOT EXISTS first_name VARCHAR,
+	ADD COLUMN IF NOT EXISTS last_name VARCHAR;
+
+-- Best-effort split of existing names, which are stored family name first:
+-- the first word becomes the last name, the remainder the first name
+UPDATE customers
+SET last_name = SUBSTRING(TRIM(full_name) FROM '^\S+'),
+	first_name = NULLIF(SUBSTRING(TRIM(full_name) FROM '^\S+\s+(.*)$'), '')
+WHERE last_name IS NULL AND first_name IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_customers_user_last_name ON customers (user_id, last_name);
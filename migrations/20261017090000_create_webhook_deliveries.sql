@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+	id SERIAL PRIMARY KEY,
+	webhook_id INT NOT NULL REFERENCES webhooks (id) ON DELETE CASCADE,
+	event VARCHAR(64) NOT NULL,
+	body TEXT NOT NULL,
+	attempts INT NOT NULL DEFAULT 0,
+	-- Truncated, rounding could put a fresh delivery a second into the future
+	next_attempt_at TIMESTAMPTZ(0) NOT NULL DEFAULT DATE_TRUNC('second', NOW()),
+	last_error TEXT,
+	delivered_at TIMESTAMPTZ(0),
+	failed_at TIMESTAMPTZ(0),
+	created_at TIMESTAMPTZ(0) NOT NULL DEFAULT NOW()
+);
+
+-- Indexes for webhook_deliveries, the worker only looks at pending ones
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_pending ON webhook_deliveries (next_attempt_at)
+WHERE delivered_at IS NULL AND failed_at IS NULL;
@@ -0,0 +1,86 @@
+const USAGE: &str =
+    "Usage: niro-backend [migrate | rehash | backfill-created-by | backfill-contract-links] [-log]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Serve,
+    Maintenance(Task),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Task {
+    Migrate,
+    // Recompute the stored hashes after HMAC_SECRET was rotated (old one in HMAC_SECRET_OLD)
+    Rehash,
+    BackfillCreatedBy,
+    BackfillContractLinks,
+}
+
+impl Command {
+    /// Flags such as the legacy `-log` are left to their readers, without a subcommand
+    /// the server is started.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut commands = args.into_iter().skip(1).filter(|arg| !arg.starts_with('-'));
+
+        let command = match commands.next().as_deref() {
+            None => Self::Serve,
+            Some("migrate") => Self::Maintenance(Task::Migrate),
+            Some("rehash") => Self::Maintenance(Task::Rehash),
+            Some("backfill-created-by") => Self::Maintenance(Task::BackfillCreatedBy),
+            Some("backfill-contract-links") => Self::Maintenance(Task::BackfillContractLinks),
+            Some(other) => return Err(format!("Unknown command '{other}'\n{USAGE}")),
+        };
+        if let Some(extra) = commands.next() {
+            return Err(format!("Unexpected argument '{extra}'\n{USAGE}"));
+        }
+
+        Ok(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        Command::parse(
+            std::iter::once("niro-backend")
+                .chain(args.iter().copied())
+                .map(String::from),
+        )
+    }
+
+    #[test]
+    fn no_subcommand_serves() {
+        assert_eq!(parse(&[]), Ok(Command::Serve));
+        assert_eq!(parse(&["-log"]), Ok(Command::Serve));
+    }
+
+    #[test]
+    fn subcommands_map_to_tasks() {
+        assert_eq!(parse(&["migrate"]), Ok(Command::Maintenance(Task::Migrate)));
+        assert_eq!(parse(&["rehash"]), Ok(Command::Maintenance(Task::Rehash)));
+        assert_eq!(
+            parse(&["backfill-created-by"]),
+            Ok(Command::Maintenance(Task::BackfillCreatedBy))
+        );
+        assert_eq!(
+            parse(&["-log", "backfill-contract-links"]),
+            Ok(Command::Maintenance(Task::BackfillContractLinks))
+        );
+    }
+
+    #[test]
+    fn unknown_or_extra_arguments_are_rejected() {
+        assert!(
+            parse(&["drop-everything"])
+                .unwrap_err()
+                .starts_with("Unknown command")
+        );
+        assert!(
+            parse(&["migrate", "rehash"])
+                .unwrap_err()
+                .starts_with("Unexpected argument")
+        );
+    }
+}
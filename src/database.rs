@@ -1,29 +1,232 @@
-use sqlx::{Pool, Postgres, prelude::FromRow};
+use redis::aio::ConnectionManager;
+use sqlx::{Pool, Postgres, postgres::PgDatabaseError, prelude::FromRow};
 use std::{env, time::Duration};
 
-#[derive(FromRow, Debug, Clone)]
+use crate::{
+    models::user::UserRole,
+    utils::{
+        cache::{self, TtlCache},
+        error::{ApiError, INTERNAL_ERROR_MESSAGE},
+        webhook::WebhookDispatcher,
+    },
+};
+
+const DEFAULT_MAX_CONNECTIONS: u32 = 5;
+
+#[derive(FromRow, Clone)]
 pub struct Database {
     pub pool: Pool<Postgres>,
-    // pub redis: redis::Client,
+    pub redis: ConnectionManager,
+    pub role_cache: TtlCache<i32, UserRole>,
+    pub password_change_cache: TtlCache<i32, bool>,
+    pub webhooks: WebhookDispatcher,
 }
 
 impl Database {
     pub async fn create_connection() -> Result<Self, sqlx::error::Error> {
         let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set!");
-        // let redis_url = env::var("REDIS_URL").expect("REDIS_URL must be set!");
+        let redis_url = env::var("REDIS_URL").expect("REDIS_URL must be set!");
+
+        let max_connections = env::var("DATABASE_MAX_CONNECTIONS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_MAX_CONNECTIONS);
 
         let pool = sqlx::postgres::PgPoolOptions::new()
-            .max_connections(5)
+            .max_connections(max_connections)
             .acquire_timeout(Duration::from_secs(30))
             .idle_timeout(Duration::from_secs(600))
             .max_lifetime(Duration::from_secs(1800))
             .connect(&database_url)
             .await?;
 
-        // let redis = redis::Client::open(redis_url).unwrap();
+        let redis = redis::Client::open(redis_url)
+            .unwrap()
+            .get_connection_manager()
+            .await
+            .expect("Failed to connect to Redis");
 
         // sqlx::migrate!("./migrations").run(&pool).await?;
 
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            redis,
+            role_cache: TtlCache::new(cache::role_cache_ttl()),
+            password_change_cache: TtlCache::new(cache::role_cache_ttl()),
+            webhooks: WebhookDispatcher::new(),
+        })
+    }
+}
+
+// Turns constraint violations into client errors instead of a generic 500
+pub fn map_pg_error(err: sqlx::Error) -> ApiError {
+    // Every pooled connection is busy, the client should back off and retry
+    if matches!(err, sqlx::Error::PoolTimedOut) {
+        return ApiError::Unavailable(
+            "A szolgáltatás túlterhelt, kérlek próbáld újra később".to_string(),
+        );
+    }
+
+    let sqlx::Error::Database(db_err) = &err else {
+        return internal(err);
+    };
+
+    let field = constraint_field(db_err.table(), db_err.constraint());
+    match db_err.code().as_deref() {
+        Some("23505") => ApiError::Conflict(format!("Ez az érték már foglalt: {field}")),
+        Some("23503") => ApiError::Validation(format!("A hivatkozott rekord nem létezik: {field}")),
+        Some("23502") => {
+            let column = db_err
+                .try_downcast_ref::<PgDatabaseError>()
+                .and_then(|pg_err| pg_err.column())
+                .unwrap_or(&field);
+            ApiError::Validation(format!("Kötelező mező hiányzik: {column}"))
+        }
+        _ => internal(err),
+    }
+}
+
+// Raw driver errors name tables and columns, they only go to the log
+fn internal(err: sqlx::Error) -> ApiError {
+    log::error!("Unhandled database error: {err}");
+    ApiError::Internal(INTERNAL_ERROR_MESSAGE.to_string())
+}
+
+// Postgres names constraints `<table>_<column>_<suffix>`, strip the table and suffix
+fn constraint_field(table: Option<&str>, constraint: Option<&str>) -> String {
+    let Some(constraint) = constraint else {
+        return String::new();
+    };
+
+    let field = table
+        .and_then(|table| constraint.strip_prefix(table))
+        .map(|rest| rest.trim_start_matches('_'))
+        .unwrap_or(constraint);
+
+    ["_key", "_fkey", "_check"]
+        .iter()
+        .find_map(|suffix| field.strip_suffix(suffix))
+        .unwrap_or(field)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, error::Error as StdError, fmt};
+
+    use sqlx::error::{DatabaseError, ErrorKind};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct FakeDbError {
+        code: &'static str,
+        table: Option<&'static str>,
+        constraint: Option<&'static str>,
+    }
+
+    impl fmt::Display for FakeDbError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "violates constraint on {:?}", self.table)
+        }
+    }
+
+    impl StdError for FakeDbError {}
+
+    impl DatabaseError for FakeDbError {
+        fn message(&self) -> &str {
+            "violates constraint"
+        }
+
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed(self.code))
+        }
+
+        fn as_error(&self) -> &(dyn StdError + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn StdError + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn StdError + Send + Sync + 'static> {
+            self
+        }
+
+        fn constraint(&self) -> Option<&str> {
+            self.constraint
+        }
+
+        fn table(&self) -> Option<&str> {
+            self.table
+        }
+
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Other
+        }
+    }
+
+    fn db_error(code: &'static str, constraint: Option<&'static str>) -> sqlx::Error {
+        sqlx::Error::Database(Box::new(FakeDbError {
+            code,
+            table: Some("user_info"),
+            constraint,
+        }))
+    }
+
+    #[test]
+    fn unique_violation_is_a_conflict() {
+        let err = map_pg_error(db_error("23505", Some("user_info_agent_code_key")));
+
+        assert!(matches!(err, ApiError::Conflict(msg) if msg.ends_with(": agent_code")));
+    }
+
+    #[test]
+    fn foreign_key_violation_is_a_validation_error() {
+        let err = map_pg_error(db_error("23503", Some("user_info_user_id_fkey")));
+
+        assert!(matches!(err, ApiError::Validation(msg) if msg.ends_with(": user_id")));
+    }
+
+    #[test]
+    fn not_null_violation_is_a_validation_error() {
+        let err = map_pg_error(db_error("23502", None));
+
+        assert!(matches!(err, ApiError::Validation(msg) if msg.starts_with("Kötelező mező")));
+    }
+
+    #[test]
+    fn other_errors_are_hidden() {
+        let database = map_pg_error(db_error("42P01", None));
+        let missing_row = map_pg_error(sqlx::Error::RowNotFound);
+
+        assert!(matches!(database, ApiError::Internal(msg) if msg == INTERNAL_ERROR_MESSAGE));
+        assert!(matches!(missing_row, ApiError::Internal(msg) if msg == INTERNAL_ERROR_MESSAGE));
+    }
+
+    #[test]
+    fn pool_timeout_is_unavailable() {
+        assert!(matches!(
+            map_pg_error(sqlx::Error::PoolTimedOut),
+            ApiError::Unavailable(_)
+        ));
+    }
+
+    #[test]
+    fn constraint_field_strips_table_and_suffix() {
+        let field = |table, constraint| constraint_field(table, constraint);
+
+        assert_eq!(field(Some("users"), Some("users_email_key")), "email");
+        assert_eq!(
+            field(Some("users"), Some("users_manager_id_fkey")),
+            "manager_id"
+        );
+        assert_eq!(
+            field(Some("customers"), Some("customers_flag_check")),
+            "flag"
+        );
+        assert_eq!(field(None, Some("custom_name")), "custom_name");
+        assert_eq!(field(Some("users"), None), "");
     }
 }
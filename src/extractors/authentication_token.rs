@@ -1,6 +1,6 @@
 use actix_web::{
     Error as ActixWebError, FromRequest, HttpRequest, dev::Payload, error::ErrorUnauthorized,
-    http::header::HeaderValue,
+    http::header::HeaderValue, web,
 };
 use jsonwebtoken::{
     Algorithm, DecodingKey, TokenData, Validation, decode, errors::Error as JwtError,
@@ -8,23 +8,66 @@ use jsonwebtoken::{
 use serde::{Deserialize, Serialize};
 use std::{
     env,
-    future::{Ready, ready},
+    future::{Future, ready},
+    pin::Pin,
+};
+
+use crate::{
+    models::user::User,
+    utils::error::{ApiError, INTERNAL_ERROR_MESSAGE},
+    web_data::WebData,
 };
 
 #[derive(Serialize, Deserialize)]
 pub struct Claims {
     pub id: usize,
     pub exp: usize,
+    // Tokens issued before `iat` was added don't carry it
+    #[serde(default)]
+    pub iat: Option<usize>,
+    // Session id in Redis, tokens issued before sessions were tracked have none
+    #[serde(default)]
+    pub sid: Option<String>,
+    // Real user behind an impersonation token
+    #[serde(default)]
+    pub act: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AuthenticationToken {
     pub id: usize,
+    pub iat: Option<usize>,
+    pub exp: usize,
+    pub sid: Option<String>,
+    /// The user who actually holds the token, differs from `id` while impersonating
+    pub real_id: usize,
+}
+
+impl AuthenticationToken {
+    pub fn is_impersonating(&self) -> bool {
+        self.real_id != self.id
+    }
 }
 
+// Normal tokens carry no `act`, the holder is then the effective user itself
+impl From<Claims> for AuthenticationToken {
+    fn from(claims: Claims) -> Self {
+        Self {
+            id: claims.id,
+            iat: claims.iat,
+            exp: claims.exp,
+            sid: claims.sid,
+            real_id: claims.act.unwrap_or(claims.id),
+        }
+    }
+}
+
+// Routes a user flagged with `must_change_password` can still reach
+const PASSWORD_CHANGE_EXEMPT_PATHS: [&str; 2] = ["/user/password", "/user/logout"];
+
 impl FromRequest for AuthenticationToken {
     type Error = ActixWebError;
-    type Future = Ready<Result<Self, Self::Error>>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
 
     fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
         let req = req.clone();
@@ -34,7 +77,9 @@ impl FromRequest for AuthenticationToken {
 
         // No Header was sent
         if authorization_header_option.is_none() {
-            return ready(Err(ErrorUnauthorized("No authentication token sent!")));
+            return Box::pin(ready(Err(ErrorUnauthorized(
+                "No authentication token sent!",
+            ))));
         }
 
         let authentication_token: String = authorization_header_option
@@ -45,9 +90,9 @@ impl FromRequest for AuthenticationToken {
 
         // Couldn't convert Header::Authorization to String
         if authentication_token.is_empty() {
-            return ready(Err(ErrorUnauthorized(
+            return Box::pin(ready(Err(ErrorUnauthorized(
                 "Authentication token has foreign chars!",
-            )));
+            ))));
         }
 
         let secret = &env::var("AUTH_SECRET").expect("AUTH_SECRET must be set!");
@@ -58,11 +103,65 @@ impl FromRequest for AuthenticationToken {
             &Validation::new(Algorithm::HS256),
         );
 
-        match token_result {
-            Ok(token) => ready(Ok(AuthenticationToken {
-                id: token.claims.id,
-            })),
-            Err(_e) => ready(Err(ErrorUnauthorized("Invalid authentication token sent!"))),
-        }
+        let claims = match token_result {
+            Ok(token) => token.claims,
+            Err(_e) => {
+                return Box::pin(ready(Err(ErrorUnauthorized(
+                    "Invalid authentication token sent!",
+                ))));
+            }
+        };
+
+        // Without the database the session and password checks can't run, so the token
+        // is refused rather than trusted unchecked
+        let Some(web_data) = req.app_data::<web::Data<WebData>>().cloned() else {
+            log::error!("WebData is not registered, authentication checks can't run");
+            return Box::pin(ready(Err(ApiError::Internal(
+                INTERNAL_ERROR_MESSAGE.to_string(),
+            )
+            .into())));
+        };
+        let password_change_exempt = PASSWORD_CHANGE_EXEMPT_PATHS.contains(&req.path());
+
+        Box::pin(async move {
+            if let Some(sid) = &claims.sid {
+                User::ensure_session_active(&web_data.db, sid)
+                    .await
+                    .map_err(ApiError::from)?;
+            }
+            if !password_change_exempt {
+                User::ensure_password_changed(&web_data.db, claims.id as i32)
+                    .await
+                    .map_err(ApiError::from)?;
+            }
+
+            Ok(AuthenticationToken::from(claims))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test::TestRequest};
+
+    use super::*;
+    use crate::utils::jwt;
+
+    #[actix_web::test]
+    #[ignore = "needs AUTH_SECRET"]
+    async fn tokens_are_refused_without_web_data() {
+        dotenvy::dotenv().ok();
+        let secret = env::var("AUTH_SECRET").unwrap();
+        let token = jwt::generate_jwt_token(1, None, secret).await;
+        let req = TestRequest::default()
+            .insert_header((actix_web::http::header::AUTHORIZATION, token))
+            .to_http_request();
+
+        let result = AuthenticationToken::from_request(&req, &mut Payload::None).await;
+
+        assert_eq!(
+            result.unwrap_err().as_response_error().status_code(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
     }
 }
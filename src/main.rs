@@ -1,7 +1,10 @@
+use std::{env, process};
+
 use dotenvy::dotenv;
 
-use crate::server::Server;
+use crate::{cli::Command, server::Server};
 
+mod cli;
 mod database;
 mod extractors;
 mod models;
@@ -14,5 +17,12 @@ mod web_data;
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
 
-    Server::run(8080).await
+    match Command::parse(env::args()) {
+        Ok(Command::Serve) => Server::run(8080).await,
+        Ok(Command::Maintenance(task)) => Server::run_maintenance(task).await,
+        Err(usage) => {
+            eprintln!("{usage}");
+            process::exit(2);
+        }
+    }
 }
@@ -0,0 +1,184 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use strum::{AsRefStr, Display, EnumString};
+use uuid::Uuid;
+
+use crate::{
+    database::Database,
+    models::{
+        dto::{AccessLogEntryDto, AuditEntryDto},
+        user::{ListingScope, User},
+    },
+    utils::{
+        pagination::{Paginated, PaginationQuery},
+        validation,
+    },
+};
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Default, Clone)]
+pub struct AuditLog {
+    pub uuid: Option<Uuid>,
+    pub actor_id: Option<i32>,
+    pub action: Option<AuditAction>,
+    pub target_uuid: Option<Uuid>,
+    pub details: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, EnumString, Display, AsRefStr)]
+pub enum AuditAction {
+    PasswordReset,
+    TemporaryPasswordGenerated,
+    PasswordChanged,
+    CustomerPiiRead,
+    Impersonation,
+}
+
+impl AuditLog {
+    pub async fn record(
+        db: &Database,
+        actor_id: i32,
+        action: AuditAction,
+        target_uuid: Option<Uuid>,
+        details: Option<String>,
+    ) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO audit_logs(actor_id, action, target_uuid, details)
+             VALUES($1, $2, $3, $4)",
+            actor_id,
+            action.to_string(),
+            target_uuid,
+            details
+        )
+        .execute(&db.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records a `CustomerPiiRead` for every distinct customer a listing or export decrypted,
+    /// with `source` naming the view in the details.
+    pub async fn record_pii_reads(
+        db: &Database,
+        actor_id: i32,
+        customer_uuids: &[Uuid],
+        source: &str,
+    ) -> Result<()> {
+        if customer_uuids.is_empty() {
+            return Ok(());
+        }
+
+        sqlx::query!(
+            "INSERT INTO audit_logs(actor_id, action, target_uuid, details)
+             SELECT DISTINCT $1::INT, $2::TEXT, target_uuid, $4::TEXT
+             FROM UNNEST($3::UUID[]) AS target_uuid",
+            actor_id,
+            AuditAction::CustomerPiiRead.to_string(),
+            customer_uuids,
+            source
+        )
+        .execute(&db.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_access_log(
+        db: &Database,
+        target_uuid: Uuid,
+        action: AuditAction,
+    ) -> Result<Vec<AccessLogEntryDto>> {
+        let rows = sqlx::query!(
+            r#"SELECT u.uuid AS "actor_uuid?", ui.full_name AS "actor_name?", a.created_at
+             FROM audit_logs a
+             LEFT JOIN users u ON u.id = a.actor_id
+             LEFT JOIN user_info ui ON ui.user_id = u.id
+             WHERE a.target_uuid = $1 AND a.action = $2
+             ORDER BY a.created_at DESC"#,
+            target_uuid,
+            action.to_string()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| AccessLogEntryDto {
+                actor_uuid: row.actor_uuid,
+                actor_name: row.actor_name,
+                accessed_at: row.created_at,
+            })
+            .collect())
+    }
+
+    /// Latest entries whose actor or target (a user, or a customer's owner) is within
+    /// the caller's team, so one team never sees another team's activity.
+    pub async fn get_recent_for_team(
+        db: &Database,
+        user_id: i32,
+        pagination: PaginationQuery,
+    ) -> Result<Paginated<AuditEntryDto>> {
+        let user_uuid = User::get_uuid_by_id(db, user_id)
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+        let team_ids = User::get_scoped_ids(db, user_uuid, ListingScope::Team).await?;
+
+        let rows = sqlx::query!(
+            r#"SELECT a.uuid, a.action, u.uuid AS "actor_uuid?", ui.full_name AS "actor_name?", a.target_uuid, a.details, a.created_at
+             FROM audit_logs a
+             LEFT JOIN users u ON u.id = a.actor_id
+             LEFT JOIN user_info ui ON ui.user_id = u.id
+             WHERE a.actor_id = ANY($1)
+                OR EXISTS (SELECT 1 FROM users t WHERE t.uuid = a.target_uuid AND t.id = ANY($1))
+                OR EXISTS (SELECT 1 FROM customers c WHERE c.uuid = a.target_uuid AND c.user_id = ANY($1))
+             ORDER BY a.created_at DESC, a.id DESC
+             LIMIT $2 OFFSET $3"#,
+            &team_ids,
+            pagination.limit() + 1,
+            pagination.offset()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        let items = rows
+            .into_iter()
+            .map(|row| {
+                Ok(AuditEntryDto {
+                    uuid: row.uuid,
+                    action: validation::parse_stored("action", &row.action)?,
+                    actor_uuid: row.actor_uuid,
+                    actor_name: row.actor_name,
+                    target_uuid: row.target_uuid,
+                    details: row.details,
+                    created_at: row.created_at,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let total = if pagination.with_total() {
+            sqlx::query_scalar!(
+                r#"SELECT COUNT(*) AS "count!"
+                 FROM audit_logs a
+                 WHERE a.actor_id = ANY($1)
+                    OR EXISTS (SELECT 1 FROM users t WHERE t.uuid = a.target_uuid AND t.id = ANY($1))
+                    OR EXISTS (SELECT 1 FROM customers c WHERE c.uuid = a.target_uuid AND c.user_id = ANY($1))"#,
+                &team_ids
+            )
+            .fetch_one(&db.pool)
+            .await
+            .map(Some)?
+        } else {
+            None
+        };
+
+        Ok(Paginated::new(
+            items,
+            total,
+            pagination.limit(),
+            pagination.offset(),
+        ))
+    }
+}
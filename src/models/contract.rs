@@ -1,20 +1,39 @@
+use std::collections::{HashMap, HashSet};
+
 use anyhow::{Ok, Result, anyhow};
 use chacha20poly1305::Key;
 use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use serde_with::skip_serializing_none;
-use sqlx::prelude::Type;
-use strum::{AsRefStr, Display, EnumString};
+use sqlx::{Connection, prelude::Type};
+use strum::{AsRefStr, Display, EnumString, VariantNames};
 use uuid::Uuid;
 
 use crate::{
     database::Database,
     models::{
+        audit_log::{AuditAction, AuditLog},
         customer::Customer,
-        dto::{ContractDto, MonthlyProductionChartDto, PortfolioDto, WeeklyProductionChartDto},
-        user::User,
+        dto::{
+            AgentRenewalsDto, BlockedDeleteDto, BookImportContractDto, BookImportCustomerDto,
+            BookImportSummaryDto, CommissionEstimateDto, CommissionTypeDto, ContractDto,
+            ContractSummaryDto, ContractTypeCountsDto, ContractTypeTrendDto, CustomerContractsDto,
+            CustomerSummaryDto, DuplicateContractNumberDto, InterventionTaskDto,
+            MeetingConversionDto, MonthlyCombinedChartDto, MonthlyProductionChartDto,
+            NewVsRenewalDto, PartialDeleteDto, PortfolioDto, RenewalDto, SearchHitDto,
+            WeeklyProductionChartDto,
+        },
+        intervention_task::{InterventionTask, TaskListingRow},
+        user::{ListingScope, User},
+        webhook::WebhookEvent,
+    },
+    utils::{
+        encrypt::{self, DecryptFailurePolicy, HmacSecret, RowDecryptor},
+        error::ApiError,
+        pagination::{Paginated, PaginationQuery},
+        pdf, validation,
     },
-    utils::encrypt::{self, HmacSecret},
 };
 
 #[skip_serializing_none]
@@ -28,13 +47,39 @@ pub struct Contract {
     pub first_payment: Option<bool>,
     pub payment_frequency: Option<PaymentFrequency>,
     pub payment_method: Option<PaymentMethod>,
+    pub status: Option<ContractStatus>,
+    pub is_renewal: Option<bool>,
     pub customer_id: Option<i32>,
     pub user_id: Option<i32>,
     pub created_by: Option<String>,
     pub handle_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, EnumString, Display, Type, AsRefStr)]
+/// Customer and contract columns selected by every contract listing, before decryption.
+struct ContractListingRow {
+    customer_uuid: Option<Uuid>,
+    full_name: String,
+    phone_number_enc: Vec<u8>,
+    phone_number_nonce: Vec<u8>,
+    email_enc: Vec<u8>,
+    email_nonce: Vec<u8>,
+    address_enc: Vec<u8>,
+    address_nonce: Vec<u8>,
+    uuid: Option<Uuid>,
+    contract_number: String,
+    contract_type: String,
+    annual_fee: i32,
+    first_payment: bool,
+    payment_frequency: String,
+    payment_method: String,
+    status: String,
+    handle_at: DateTime<Utc>,
+    created_by: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, EnumString, Display)]
 pub enum ContractType {
     BonusLifeProgram,
     LifeProgram,
@@ -48,6 +93,53 @@ pub enum ContractType {
     TravelInsurance,
     CondominiumInsurance,
     AgriculturalInsurance,
+    // Types added to `contract_types` after deploy, validated against the loaded set
+    #[strum(default)]
+    #[serde(untagged)]
+    Custom(String),
+}
+
+impl ContractType {
+    pub async fn get_allowed(db: &Database) -> Result<HashSet<String>> {
+        let names = sqlx::query_scalar!("SELECT name FROM contract_types")
+            .fetch_all(&db.pool)
+            .await?;
+
+        Ok(names.into_iter().collect())
+    }
+
+    pub fn ensure_allowed(&self, allowed: &HashSet<String>) -> Result<()> {
+        if !allowed.contains(&self.to_string()) {
+            return Err(ApiError::Validation(format!("Ismeretlen szerződés típus: {self}")).into());
+        }
+        Ok(())
+    }
+}
+
+impl From<&ContractTypeCountsDto> for PortfolioDto {
+    fn from(counts: &ContractTypeCountsDto) -> Self {
+        let count = |contract_type: ContractType| {
+            counts
+                .get(&contract_type.to_string())
+                .copied()
+                .unwrap_or_default()
+        };
+
+        PortfolioDto {
+            bonus_life_program: count(ContractType::BonusLifeProgram),
+            life_program: count(ContractType::LifeProgram),
+            allianz_care_now: count(ContractType::AllianzCareNow),
+            health_program: count(ContractType::HealthProgram),
+            myhome_home_insurance: count(ContractType::MyhomeHomeInsurance),
+            mfo_home_insurance: count(ContractType::MfoHomeInsurance),
+            corporate_property_insurance: count(ContractType::CorporatePropertyInsurance),
+            kgfb: count(ContractType::Kgfb),
+            casco: count(ContractType::Casco),
+            travel_insurance: count(ContractType::TravelInsurance),
+            condominium_insurance: count(ContractType::CondominiumInsurance),
+            agricultural_insurance: count(ContractType::AgriculturalInsurance),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, EnumString, Display, Type, AsRefStr)]
@@ -66,6 +158,36 @@ pub enum PaymentMethod {
     Check,
 }
 
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Default, EnumString, Display, Type, AsRefStr, VariantNames,
+)]
+pub enum ContractStatus {
+    #[default]
+    Active,
+    Pending,
+    Lapsed,
+    Cancelled,
+}
+
+impl ContractStatus {
+    /// Statuses a contract may move to this one from. Nothing goes back to `Pending`
+    /// and a cancelled contract stays cancelled.
+    pub fn allowed_from(&self) -> &'static [ContractStatus] {
+        use ContractStatus::*;
+        match self {
+            Active => &[Pending, Active, Lapsed],
+            Pending => &[Pending],
+            Lapsed => &[Active, Lapsed],
+            Cancelled => &[Pending, Active, Lapsed, Cancelled],
+        }
+    }
+}
+
+struct DeleteCandidate {
+    uuid: Uuid,
+    has_open_tasks: bool,
+}
+
 // CONTRACT UTILS //
 impl Contract {
     pub async fn get_id_by_uuid(db: &Database, contract_uuid: Option<Uuid>) -> Result<Option<i32>> {
@@ -121,83 +243,407 @@ impl Contract {
         db: &Database,
         key: &Key,
         hmac_secret: &HmacSecret,
+        actor_id: i32,
         user_uuid: Uuid,
         customer: Customer,
         contract: Contract,
-    ) -> Result<i32> {
+    ) -> Result<(i32, bool)> {
         let user_id = User::get_id_by_uuid(db, Some(user_uuid))
             .await?
             .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+        // A new customer is only kept if its contract is created too
+        let mut tx = db.pool.begin().await?;
+        let (customer_id, customer_created) =
+            Customer::create_or_get_in(&mut tx, key, hmac_secret, user_id, &customer).await?;
+        Customer::ensure_contactable(&mut tx, db, customer_id, Some(actor_id)).await?;
+
+        let created_by =
+            User::normalize_created_by(&mut tx, contract.created_by.as_deref()).await?;
+
         let row = sqlx::query!(
-            "SELECT id FROM customers
-             WHERE email_hash = $1 OR phone_number_hash = $2",
-            encrypt::hash_value(hmac_secret, &customer.email.as_ref().unwrap()),
-            encrypt::hash_value(hmac_secret, &customer.phone_number.as_ref().unwrap()),
+            "INSERT INTO customer_contracts(contract_number, contract_type, annual_fee, payment_frequency, payment_method, status, customer_id, user_id, created_by, is_renewal)
+             VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+             RETURNING id, uuid",
+            contract.contract_number,
+            contract.contract_type.map(|c| c.to_string()),
+            contract.annual_fee,
+            contract.payment_frequency.map(|c| c.to_string()),
+            contract.payment_method.map(|c| c.to_string()),
+            contract.status.unwrap_or_default().to_string(),
+            customer_id,
+            user_id,
+            created_by,
+            contract.is_renewal.unwrap_or(false)
         )
-        .fetch_optional(&db.pool)
+        .fetch_one(&mut *tx)
         .await?;
+        tx.commit().await?;
+        db.webhooks
+            .dispatch(
+                db,
+                WebhookEvent::ContractCreated,
+                json!({ "uuid": row.uuid, "contract_number": contract.contract_number }),
+            )
+            .await;
 
-        // Determine customer_id: create customer if not exists, otherwise use existing id
-        let customer_id = if let Some(existing) = row {
-            existing.id
-        } else {
-            Customer::create(db, key, hmac_secret, user_uuid, customer.clone()).await?
-        };
+        Ok((row.id, customer_created))
+    }
+
+    pub async fn create_for_customer(
+        db: &Database,
+        actor_id: i32,
+        customer_uuid: Uuid,
+        user_uuid: Uuid,
+        contract: Contract,
+    ) -> Result<i32> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+        let customer = sqlx::query!(
+            "SELECT id, user_id FROM customers WHERE uuid = $1",
+            customer_uuid
+        )
+        .fetch_optional(&db.pool)
+        .await?
+        .ok_or_else(|| anyhow!("Ügyfél nem található!"))?;
+
+        if customer.user_id != Some(user_id) {
+            return Err(anyhow!("Ehez a folyamathoz nincs jogosultságod!"));
+        }
+        let mut conn = db.pool.acquire().await?;
+        Customer::ensure_contactable(&mut conn, db, customer.id, Some(actor_id)).await?;
+
+        let created_by =
+            User::normalize_created_by(&mut conn, contract.created_by.as_deref()).await?;
 
         let row = sqlx::query!(
-            "INSERT INTO customer_contracts(contract_number, contract_type, annual_fee, payment_frequency, payment_method, customer_id, user_id, created_by)
-             VALUES($1, $2, $3, $4, $5, $6, $7, $8)
-             RETURNING id",
+            "INSERT INTO customer_contracts(contract_number, contract_type, annual_fee, payment_frequency, payment_method, status, customer_id, user_id, created_by, is_renewal)
+             VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+             RETURNING id, uuid",
             contract.contract_number,
             contract.contract_type.map(|c| c.to_string()),
             contract.annual_fee,
             contract.payment_frequency.map(|c| c.to_string()),
             contract.payment_method.map(|c| c.to_string()),
-            customer_id,
+            contract.status.unwrap_or_default().to_string(),
+            customer.id,
             user_id,
-            contract.created_by
+            created_by,
+            contract.is_renewal.unwrap_or(false)
         )
-        .fetch_one(&db.pool)
+        .fetch_one(&mut *conn)
         .await?;
+        db.webhooks
+            .dispatch(
+                db,
+                WebhookEvent::ContractCreated,
+                json!({ "uuid": row.uuid, "contract_number": contract.contract_number }),
+            )
+            .await;
 
         Ok(row.id)
     }
 
+    async fn insert_in(
+        conn: &mut sqlx::PgConnection,
+        customer_id: i32,
+        user_id: i32,
+        contract: &Contract,
+    ) -> Result<Uuid> {
+        let created_by = User::normalize_created_by(conn, contract.created_by.as_deref()).await?;
+
+        let uuid = sqlx::query_scalar!(
+            "INSERT INTO customer_contracts(contract_number, contract_type, annual_fee, payment_frequency, payment_method, status, customer_id, user_id, created_by, is_renewal)
+             VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+             RETURNING uuid",
+            contract.contract_number,
+            contract.contract_type.as_ref().map(|c| c.to_string()),
+            contract.annual_fee,
+            contract.payment_frequency.as_ref().map(|c| c.to_string()),
+            contract.payment_method.as_ref().map(|c| c.to_string()),
+            contract.status.clone().unwrap_or_default().to_string(),
+            customer_id,
+            user_id,
+            created_by,
+            contract.is_renewal.unwrap_or(false)
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        uuid.ok_or_else(|| anyhow!("A szerződés létrehozása sikertelen!"))
+    }
+
+    /// Imports an agent's existing book in one transaction. Customers are deduplicated by
+    /// their hashes; a failing customer or contract is rolled back on its own and reported,
+    /// the rest of the book is still imported.
+    pub async fn import_book(
+        db: &Database,
+        key: &Key,
+        hmac_secret: &HmacSecret,
+        actor_id: i32,
+        user_uuid: Uuid,
+        book: Vec<(Customer, Vec<Contract>)>,
+    ) -> Result<BookImportSummaryDto> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let mut items = Vec::with_capacity(book.len());
+        let mut created_contracts = Vec::new();
+        let mut tx = db.pool.begin().await?;
+        for (index, (customer, contracts)) in book.into_iter().enumerate() {
+            let mut item = BookImportCustomerDto {
+                index,
+                full_name: customer.full_name.clone().unwrap_or_default(),
+                customer_uuid: None,
+                customer_created: false,
+                error: None,
+                contracts: Vec::with_capacity(contracts.len()),
+            };
+
+            let mut savepoint = tx.begin().await?;
+            let customer_result = async {
+                let (customer_id, created) = Customer::create_or_get_in(
+                    &mut savepoint,
+                    key,
+                    hmac_secret,
+                    user_id,
+                    &customer,
+                )
+                .await?;
+                Customer::ensure_contactable(&mut savepoint, db, customer_id, Some(actor_id))
+                    .await?;
+                Ok((customer_id, created))
+            }
+            .await;
+            let customer_id = match customer_result {
+                Result::Ok((customer_id, created)) => {
+                    savepoint.commit().await?;
+                    item.customer_created = created;
+                    customer_id
+                }
+                Err(e) => {
+                    savepoint.rollback().await?;
+                    item.error = Some(ApiError::from(e).message().to_string());
+                    items.push(item);
+                    continue;
+                }
+            };
+            item.customer_uuid =
+                sqlx::query_scalar!("SELECT uuid FROM customers WHERE id = $1", customer_id)
+                    .fetch_one(&mut *tx)
+                    .await?;
+
+            for contract in contracts {
+                let contract_number = contract.contract_number.clone().unwrap_or_default();
+                let mut savepoint = tx.begin().await?;
+                match Self::insert_in(&mut savepoint, customer_id, user_id, &contract).await {
+                    Result::Ok(uuid) => {
+                        savepoint.commit().await?;
+                        created_contracts.push((uuid, contract_number.clone()));
+                        item.contracts.push(BookImportContractDto {
+                            contract_number,
+                            uuid: Some(uuid),
+                            error: None,
+                        });
+                    }
+                    Err(e) => {
+                        savepoint.rollback().await?;
+                        item.contracts.push(BookImportContractDto {
+                            contract_number,
+                            uuid: None,
+                            error: Some(ApiError::from(e).message().to_string()),
+                        });
+                    }
+                }
+            }
+            items.push(item);
+        }
+        tx.commit().await?;
+
+        for (uuid, contract_number) in created_contracts {
+            db.webhooks
+                .dispatch(
+                    db,
+                    WebhookEvent::ContractCreated,
+                    json!({ "uuid": uuid, "contract_number": contract_number }),
+                )
+                .await;
+        }
+
+        let contracts = items.iter().flat_map(|item| &item.contracts);
+        let contracts_failed = contracts.clone().filter(|c| c.error.is_some()).count();
+        Ok(BookImportSummaryDto {
+            customers_created: items.iter().filter(|item| item.customer_created).count(),
+            customers_reused: items
+                .iter()
+                .filter(|item| item.error.is_none() && !item.customer_created)
+                .count(),
+            customers_failed: items.iter().filter(|item| item.error.is_some()).count(),
+            contracts_created: contracts.count() - contracts_failed,
+            contracts_failed,
+            items,
+        })
+    }
+
     pub async fn modify(
         db: &Database,
         contract_uuid: Uuid,
         updated_contract: Contract,
     ) -> Result<()> {
-        sqlx::query!(
+        let result = sqlx::query!(
             "UPDATE customer_contracts
              SET contract_number = $1,
                  contract_type = $2,
                  annual_fee = $3,
                  payment_frequency = $4,
                  payment_method = $5,
+                 status = COALESCE($6, status),
+                 is_renewal = COALESCE($8, is_renewal),
                  handle_at = NOW()
-             WHERE uuid = $6",
+             WHERE uuid = $7",
             updated_contract.contract_number,
             updated_contract.contract_type.map(|c| c.to_string()),
             updated_contract.annual_fee,
             updated_contract.payment_frequency.map(|c| c.to_string()),
             updated_contract.payment_method.map(|c| c.to_string()),
-            contract_uuid
+            updated_contract.status.map(|c| c.to_string()),
+            contract_uuid,
+            updated_contract.is_renewal
         )
         .execute(&db.pool)
         .await?;
 
+        if result.rows_affected() == 0 {
+            return Err(ApiError::NotFound("Szerződés nem található!".to_string()).into());
+        }
+        db.webhooks.dispatch(
+            db,
+            WebhookEvent::ContractModified,
+            json!({ "uuid": contract_uuid, "contract_number": updated_contract.contract_number }),
+        )
+        .await;
+
         Ok(())
     }
 
-    pub async fn get_all(db: &Database, key: &Key, user_uuid: Uuid) -> Result<Vec<ContractDto>> {
+    /// Decrypts listing rows under the configured failure policy and records the PII read
+    /// of every customer returned, tagged with `source`.
+    async fn decrypt_listing(
+        db: &Database,
+        key: &Key,
+        actor_id: i32,
+        rows: Vec<ContractListingRow>,
+        source: &str,
+    ) -> Result<Vec<ContractDto>> {
+        let customer_uuids: Vec<Uuid> = rows.iter().filter_map(|row| row.customer_uuid).collect();
+
+        let contracts: Vec<ContractDto> = rows
+            .into_iter()
+            .map(|row| {
+                let mut decryptor = RowDecryptor::new(key);
+                Ok(ContractDto {
+                    uuid: row.uuid,
+                    full_name: row.full_name,
+                    phone_number: decryptor
+                        .decrypt(&row.phone_number_enc, &row.phone_number_nonce)
+                        .unwrap_or_default(),
+                    email: decryptor
+                        .decrypt(&row.email_enc, &row.email_nonce)
+                        .unwrap_or_default(),
+                    address: decryptor
+                        .decrypt(&row.address_enc, &row.address_nonce)
+                        .unwrap_or_default(),
+                    contract_number: row.contract_number,
+                    contract_type: validation::parse_stored("contract_type", &row.contract_type)?,
+                    annual_fee: row.annual_fee,
+                    first_payment: row.first_payment,
+                    payment_frequency: validation::parse_stored(
+                        "payment_frequency",
+                        &row.payment_frequency,
+                    )?,
+                    payment_method: validation::parse_stored(
+                        "payment_method",
+                        &row.payment_method,
+                    )?,
+                    status: validation::parse_stored("status", &row.status)?,
+                    created_by: row.created_by,
+                    handle_at: row.handle_at,
+                    decrypt_ok: decryptor.ok,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let contracts = DecryptFailurePolicy::from_env().apply(contracts, |row| row.decrypt_ok)?;
+        AuditLog::record_pii_reads(db, actor_id, &customer_uuids, source).await?;
+
+        Ok(contracts)
+    }
+
+    pub async fn get_all(
+        db: &Database,
+        key: &Key,
+        actor_id: i32,
+        user_uuid: Uuid,
+        scope: ListingScope,
+    ) -> Result<Vec<ContractDto>> {
+        let user_ids = User::get_scoped_ids(db, user_uuid, scope).await?;
+
+        let rows = sqlx::query_as!(
+            ContractListingRow,
+            r#"
+            SELECT
+                c.uuid AS customer_uuid,
+                c.full_name,
+                c.phone_number_enc,
+                c.phone_number_nonce,
+                c.email_enc,
+                c.email_nonce,
+                c.address_enc,
+                c.address_nonce,
+                cc.uuid,
+                cc.contract_number,
+                cc.contract_type,
+                cc.annual_fee,
+                cc.first_payment,
+                cc.payment_frequency,
+                cc.payment_method,
+                cc.status,
+                cc.handle_at,
+                cc.created_by
+            FROM
+                customers c
+                JOIN customer_contracts cc ON cc.customer_id = c.id
+            WHERE
+                cc.user_id = ANY($1)
+            ORDER BY cc.handle_at DESC
+            LIMIT $2
+            "#,
+            &user_ids,
+            validation::decrypt_fetch_limit()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+        validation::ensure_decrypt_limit(rows.len())?;
+        Self::decrypt_listing(db, key, actor_id, rows, "contract_list").await
+    }
+
+    pub async fn get_handled_in_range(
+        db: &Database,
+        key: &Key,
+        actor_id: i32,
+        user_uuid: Uuid,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> Result<Vec<ContractDto>> {
         let user_id = User::get_id_by_uuid(db, Some(user_uuid))
             .await?
             .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
 
-        let rows = sqlx::query!(
+        let rows = sqlx::query_as!(
+            ContractListingRow,
             r#"
             SELECT
+                c.uuid AS customer_uuid,
                 c.full_name,
                 c.phone_number_enc,
                 c.phone_number_nonce,
@@ -212,47 +658,407 @@ impl Contract {
                 cc.first_payment,
                 cc.payment_frequency,
                 cc.payment_method,
+                cc.status,
                 cc.handle_at,
                 cc.created_by
             FROM
                 customers c
                 JOIN customer_contracts cc ON cc.customer_id = c.id
             WHERE
-                cc.user_id = $1
+                cc.user_id = $1 AND cc.handle_at BETWEEN $2 AND $3
             ORDER BY cc.handle_at DESC
+            LIMIT $4
             "#,
-            user_id
+            user_id,
+            from.and_utc(),
+            to.and_utc(),
+            validation::decrypt_fetch_limit()
         )
         .fetch_all(&db.pool)
         .await?;
+        validation::ensure_decrypt_limit(rows.len())?;
+        Self::decrypt_listing(db, key, actor_id, rows, "contracts_in_range").await
+    }
 
-        let contracts: Vec<ContractDto> = rows
-            .into_iter()
-            .map(|row| ContractDto {
+    pub async fn get_grouped_by_customer(
+        db: &Database,
+        key: &Key,
+        actor_id: i32,
+        user_uuid: Uuid,
+    ) -> Result<Vec<CustomerContractsDto>> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                c.id AS customer_id,
+                c.uuid AS customer_uuid,
+                c.full_name,
+                c.phone_number_enc,
+                c.phone_number_nonce,
+                c.email_enc,
+                c.email_nonce,
+                c.address_enc,
+                c.address_nonce,
+                cc.uuid,
+                cc.contract_number,
+                cc.contract_type,
+                cc.annual_fee,
+                cc.first_payment,
+                cc.payment_frequency,
+                cc.payment_method,
+                cc.status,
+                cc.handle_at
+            FROM
+                customers c
+                JOIN customer_contracts cc ON cc.customer_id = c.id
+            WHERE
+                cc.user_id = $1
+            ORDER BY c.full_name, c.id, cc.handle_at DESC
+            LIMIT $2
+            "#,
+            user_id,
+            validation::decrypt_fetch_limit()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+        validation::ensure_decrypt_limit(rows.len())?;
+
+        let mut groups: Vec<(i32, CustomerContractsDto)> = Vec::new();
+        for row in rows {
+            let contract = ContractSummaryDto {
                 uuid: row.uuid,
-                full_name: row.full_name,
-                phone_number: encrypt::decrypt_value(
-                    key,
-                    &row.phone_number_enc,
-                    &row.phone_number_nonce,
-                )
-                .unwrap_or_default(),
-                email: encrypt::decrypt_value(key, &row.email_enc, &row.email_nonce)
-                    .unwrap_or_default(),
-                address: encrypt::decrypt_value(key, &row.address_enc, &row.address_nonce)
-                    .unwrap_or_default(),
                 contract_number: row.contract_number,
-                contract_type: row.contract_type.parse().unwrap(),
+                contract_type: validation::parse_stored("contract_type", &row.contract_type)?,
                 annual_fee: row.annual_fee,
                 first_payment: row.first_payment,
-                payment_frequency: row.payment_frequency.parse().unwrap(),
-                payment_method: row.payment_method.parse().unwrap(),
-                created_by: row.created_by,
+                payment_frequency: validation::parse_stored(
+                    "payment_frequency",
+                    &row.payment_frequency,
+                )?,
+                payment_method: validation::parse_stored("payment_method", &row.payment_method)?,
+                status: validation::parse_stored("status", &row.status)?,
                 handle_at: row.handle_at,
-            })
+            };
+
+            // Rows are ordered by customer, so only the first row of a customer is decrypted
+            match groups.last_mut() {
+                Some((customer_id, group)) if *customer_id == row.customer_id => {
+                    group.contracts.push(contract);
+                }
+                _ => groups.push((
+                    row.customer_id,
+                    CustomerContractsDto {
+                        customer: CustomerSummaryDto {
+                            uuid: row.customer_uuid,
+                            full_name: row.full_name,
+                            phone_number: encrypt::decrypt_value(
+                                key,
+                                &row.phone_number_enc,
+                                &row.phone_number_nonce,
+                            )
+                            .unwrap_or_default(),
+                            email: encrypt::decrypt_value(key, &row.email_enc, &row.email_nonce)
+                                .unwrap_or_default(),
+                            address: encrypt::decrypt_value(
+                                key,
+                                &row.address_enc,
+                                &row.address_nonce,
+                            )
+                            .unwrap_or_default(),
+                        },
+                        contracts: vec![contract],
+                    },
+                )),
+            }
+        }
+
+        let customer_uuids: Vec<Uuid> = groups
+            .iter()
+            .filter_map(|(_, group)| group.customer.uuid)
             .collect();
+        AuditLog::record_pii_reads(db, actor_id, &customer_uuids, "contracts_by_customer").await?;
 
-        Ok(contracts)
+        Ok(groups.into_iter().map(|(_, group)| group).collect())
+    }
+
+    pub async fn get_unpaid_first(
+        db: &Database,
+        key: &Key,
+        actor_id: i32,
+        user_uuid: Uuid,
+    ) -> Result<Vec<ContractDto>> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let rows = sqlx::query_as!(
+            ContractListingRow,
+            r#"
+            SELECT
+                c.uuid AS customer_uuid,
+                c.full_name,
+                c.phone_number_enc,
+                c.phone_number_nonce,
+                c.email_enc,
+                c.email_nonce,
+                c.address_enc,
+                c.address_nonce,
+                cc.uuid,
+                cc.contract_number,
+                cc.contract_type,
+                cc.annual_fee,
+                cc.first_payment,
+                cc.payment_frequency,
+                cc.payment_method,
+                cc.status,
+                cc.handle_at,
+                cc.created_by
+            FROM
+                customers c
+                JOIN customer_contracts cc ON cc.customer_id = c.id
+            WHERE
+                cc.user_id = $1 AND cc.first_payment = FALSE
+            ORDER BY cc.handle_at ASC
+            LIMIT $2
+            "#,
+            user_id,
+            validation::decrypt_fetch_limit()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+        validation::ensure_decrypt_limit(rows.len())?;
+        Self::decrypt_listing(db, key, actor_id, rows, "unpaid_contracts").await
+    }
+
+    /// Same matching as `search` (number prefix or customer name) over several owners,
+    /// without decrypting the customer's contact details.
+    pub async fn search_brief(
+        db: &Database,
+        user_ids: &[i32],
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<SearchHitDto>> {
+        let rows = sqlx::query!(
+            r#"SELECT cc.uuid AS "uuid!", cc.contract_number, c.full_name
+             FROM customer_contracts cc
+             JOIN customers c ON c.id = cc.customer_id
+             WHERE cc.user_id = ANY($1)
+               AND (cc.contract_number ILIKE $2 || '%' OR c.full_name ILIKE '%' || $2 || '%')
+             ORDER BY cc.handle_at DESC
+             LIMIT $3"#,
+            user_ids,
+            validation::escape_like(query),
+            limit
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SearchHitDto {
+                uuid: row.uuid,
+                label: row.contract_number,
+                detail: Some(row.full_name),
+            })
+            .collect())
+    }
+
+    pub async fn search(
+        db: &Database,
+        key: &Key,
+        actor_id: i32,
+        user_uuid: Uuid,
+        query: &str,
+        pagination: PaginationQuery,
+    ) -> Result<Paginated<ContractDto>> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+        let query = validation::escape_like(query.trim());
+
+        let rows = sqlx::query_as!(
+            ContractListingRow,
+            r#"
+            SELECT
+                c.uuid AS customer_uuid,
+                c.full_name,
+                c.phone_number_enc,
+                c.phone_number_nonce,
+                c.email_enc,
+                c.email_nonce,
+                c.address_enc,
+                c.address_nonce,
+                cc.uuid,
+                cc.contract_number,
+                cc.contract_type,
+                cc.annual_fee,
+                cc.first_payment,
+                cc.payment_frequency,
+                cc.payment_method,
+                cc.status,
+                cc.handle_at,
+                cc.created_by
+            FROM
+                customers c
+                JOIN customer_contracts cc ON cc.customer_id = c.id
+            WHERE
+                cc.user_id = $1
+                AND (
+                    cc.contract_number ILIKE $2 || '%'
+                    OR c.full_name ILIKE '%' || $2 || '%'
+                )
+            ORDER BY cc.handle_at DESC
+            LIMIT $3 OFFSET $4
+            "#,
+            user_id,
+            query,
+            pagination.limit() + 1,
+            pagination.offset()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        let total = if pagination.with_total() {
+            sqlx::query_scalar!(
+                r#"
+                SELECT COUNT(*) AS "count!"
+                FROM
+                    customers c
+                    JOIN customer_contracts cc ON cc.customer_id = c.id
+                WHERE
+                    cc.user_id = $1
+                    AND (
+                        cc.contract_number ILIKE $2 || '%'
+                        OR c.full_name ILIKE '%' || $2 || '%'
+                    )
+                "#,
+                user_id,
+                query
+            )
+            .fetch_one(&db.pool)
+            .await
+            .map(Some)?
+        } else {
+            None
+        };
+
+        // Only the rows on the page are decrypted and audited, the extra one just signals `has_more`
+        let page = Paginated::new(rows, total, pagination.limit(), pagination.offset());
+        let contracts =
+            Self::decrypt_listing(db, key, actor_id, page.items, "contract_search").await?;
+
+        Ok(Paginated {
+            items: contracts,
+            total: page.total,
+            limit: page.limit,
+            offset: page.offset,
+            has_more: page.has_more,
+        })
+    }
+
+    /// Numbers are compared trimmed and case-insensitively, oldest contract first.
+    pub async fn find_duplicate_numbers(db: &Database) -> Result<Vec<DuplicateContractNumberDto>> {
+        let rows = sqlx::query!(
+            r#"SELECT
+                UPPER(TRIM(contract_number)) AS "contract_number!",
+                COUNT(*) AS "count!",
+                ARRAY_AGG(uuid ORDER BY created_at, id) AS "uuids!: Vec<Uuid>"
+            FROM customer_contracts
+            GROUP BY 1
+            HAVING COUNT(*) > 1
+            ORDER BY 2 DESC, 1"#
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| DuplicateContractNumberDto {
+                contract_number: row.contract_number,
+                count: row.count,
+                uuids: row.uuids,
+            })
+            .collect())
+    }
+
+    pub async fn get_team_renewals(
+        db: &Database,
+        manager_id: i32,
+        within_days: i32,
+    ) -> Result<Vec<AgentRenewalsDto>> {
+        let rows = sqlx::query!(
+            r#"
+            WITH anniversaries AS (
+                SELECT
+                    cc.*,
+                    cc.handle_at::DATE + (DATE_PART('year', AGE(CURRENT_DATE, cc.handle_at::DATE)) * INTERVAL '1 year') AS last_anniversary
+                FROM customer_contracts cc
+            )
+            SELECT
+                a.uuid,
+                a.contract_number,
+                a.contract_type,
+                a.annual_fee,
+                c.full_name,
+                o.uuid AS agent_uuid,
+                oi.full_name AS agent_name,
+                (CASE
+                    WHEN a.last_anniversary::DATE = CURRENT_DATE THEN a.last_anniversary
+                    ELSE a.last_anniversary + INTERVAL '1 year'
+                END)::DATE AS "renewal_date!"
+            FROM users u
+            JOIN anniversaries a
+            ON (
+                u.user_role = 'Leader' OR (u.user_role = 'Manager' AND a.user_id IN (
+                    SELECT id FROM users WHERE manager_id = u.id OR id = u.id
+                ))
+            )
+            JOIN customers c ON c.id = a.customer_id
+            JOIN users o ON o.id = a.user_id
+            JOIN user_info oi ON oi.user_id = o.id
+            WHERE u.id = $1
+              AND a.handle_at::DATE < CURRENT_DATE
+              AND (CASE
+                    WHEN a.last_anniversary::DATE = CURRENT_DATE THEN a.last_anniversary
+                    ELSE a.last_anniversary + INTERVAL '1 year'
+                END)::DATE <= CURRENT_DATE + $2::INT
+            ORDER BY oi.full_name, "renewal_date!"
+            "#,
+            manager_id,
+            within_days
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        let mut agents: Vec<AgentRenewalsDto> = vec![];
+        for row in rows {
+            let renewal = RenewalDto {
+                uuid: row.uuid,
+                contract_number: row.contract_number,
+                contract_type: validation::parse_stored("contract_type", &row.contract_type)?,
+                annual_fee: row.annual_fee,
+                full_name: row.full_name,
+                renewal_date: row.renewal_date,
+            };
+
+            match agents.last_mut() {
+                Some(agent) if agent.agent_uuid == row.agent_uuid => {
+                    agent.count += 1;
+                    agent.contracts.push(renewal);
+                }
+                _ => agents.push(AgentRenewalsDto {
+                    agent_uuid: row.agent_uuid,
+                    agent_name: row.agent_name,
+                    count: 1,
+                    contracts: vec![renewal],
+                }),
+            }
+        }
+
+        Ok(agents)
     }
 
     pub async fn get_by_customer_uuid(db: &Database, customer_uuid: Uuid) -> Result<Vec<Contract>> {
@@ -269,6 +1075,8 @@ impl Contract {
                 first_payment,
                 payment_frequency,
                 payment_method,
+                status,
+                is_renewal,
                 handle_at,
                 created_by
             FROM
@@ -282,19 +1090,32 @@ impl Contract {
 
         let items: Vec<Contract> = rows
             .into_iter()
-            .map(|row| Contract {
-                uuid: row.uuid,
-                contract_number: Some(row.contract_number),
-                contract_type: Some(row.contract_type.parse().unwrap()),
-                annual_fee: Some(row.annual_fee),
-                first_payment: Some(row.first_payment),
-                payment_frequency: Some(row.payment_frequency.parse().unwrap()),
-                payment_method: Some(row.payment_method.parse().unwrap()),
-                handle_at: Some(row.handle_at),
-                created_by: Some(row.created_by),
-                ..Default::default()
+            .map(|row| {
+                Ok(Contract {
+                    uuid: row.uuid,
+                    contract_number: Some(row.contract_number),
+                    contract_type: Some(validation::parse_stored(
+                        "contract_type",
+                        &row.contract_type,
+                    )?),
+                    annual_fee: Some(row.annual_fee),
+                    first_payment: Some(row.first_payment),
+                    payment_frequency: Some(validation::parse_stored(
+                        "payment_frequency",
+                        &row.payment_frequency,
+                    )?),
+                    payment_method: Some(validation::parse_stored(
+                        "payment_method",
+                        &row.payment_method,
+                    )?),
+                    status: Some(validation::parse_stored("status", &row.status)?),
+                    is_renewal: Some(row.is_renewal),
+                    handle_at: Some(row.handle_at),
+                    created_by: Some(row.created_by),
+                    ..Default::default()
+                })
             })
-            .collect();
+            .collect::<Result<Vec<_>>>()?;
 
         Ok(items)
     }
@@ -309,8 +1130,12 @@ impl Contract {
                 first_payment,
                 payment_frequency,
                 payment_method,
+                status,
+                is_renewal,
                 handle_at,
-                created_by
+                created_by,
+                created_at,
+                updated_at
             FROM
                 customer_contracts
             WHERE
@@ -323,17 +1148,96 @@ impl Contract {
         Ok(Contract {
             uuid: row.uuid,
             contract_number: Some(row.contract_number),
-            contract_type: Some(row.contract_type.parse().unwrap()),
+            contract_type: Some(validation::parse_stored(
+                "contract_type",
+                &row.contract_type,
+            )?),
             annual_fee: Some(row.annual_fee),
             first_payment: Some(row.first_payment),
-            payment_frequency: Some(row.payment_frequency.parse().unwrap()),
-            payment_method: Some(row.payment_method.parse().unwrap()),
+            payment_frequency: Some(validation::parse_stored(
+                "payment_frequency",
+                &row.payment_frequency,
+            )?),
+            payment_method: Some(validation::parse_stored(
+                "payment_method",
+                &row.payment_method,
+            )?),
+            status: Some(validation::parse_stored("status", &row.status)?),
+            is_renewal: Some(row.is_renewal),
             handle_at: Some(row.handle_at),
             created_by: Some(row.created_by),
+            created_at: Some(row.created_at),
+            updated_at: Some(row.updated_at),
             ..Default::default()
         })
     }
 
+    pub async fn get_owner_uuid(db: &Database, contract_uuid: Uuid) -> Result<Uuid> {
+        sqlx::query_scalar!(
+            "SELECT u.uuid
+             FROM customer_contracts cc
+             JOIN users u ON u.id = cc.user_id
+             WHERE cc.uuid = $1",
+            contract_uuid
+        )
+        .fetch_optional(&db.pool)
+        .await?
+        .flatten()
+        .ok_or_else(|| ApiError::NotFound("Szerződés nem található!".to_string()).into())
+    }
+
+    /// One page summary for the customer, returns the contract number for the file name.
+    pub async fn render_pdf(
+        db: &Database,
+        key: &Key,
+        actor_id: i32,
+        contract_uuid: Uuid,
+    ) -> Result<(String, Vec<u8>)> {
+        let row = sqlx::query!(
+            "SELECT c.uuid AS customer_uuid, c.full_name, c.address_enc, c.address_nonce, cc.contract_number, cc.contract_type, cc.annual_fee, cc.first_payment, cc.payment_frequency, cc.payment_method, cc.status, cc.handle_at, cc.created_by
+             FROM customer_contracts cc
+             JOIN customers c ON c.id = cc.customer_id
+             WHERE cc.uuid = $1",
+            contract_uuid
+        )
+        .fetch_optional(&db.pool)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Szerződés nem található!".to_string()))?;
+
+        let address =
+            encrypt::decrypt_value(key, &row.address_enc, &row.address_nonce).unwrap_or_default();
+        AuditLog::record(
+            db,
+            actor_id,
+            AuditAction::CustomerPiiRead,
+            row.customer_uuid,
+            Some("contract_pdf".to_string()),
+        )
+        .await?;
+
+        let fields = [
+            ("Ügyfél neve", row.full_name),
+            ("Cím", address),
+            ("Szerződésszám", row.contract_number.clone()),
+            ("Szerződés típusa", row.contract_type),
+            ("Éves díj", format!("{} Ft", row.annual_fee)),
+            (
+                "Első díj befizetve",
+                if row.first_payment { "Igen" } else { "Nem" }.to_string(),
+            ),
+            ("Díjfizetés gyakorisága", row.payment_frequency),
+            ("Fizetési mód", row.payment_method),
+            ("Státusz", row.status),
+            ("Kötés dátuma", row.handle_at.format("%Y-%m-%d").to_string()),
+            ("Üzletkötő", row.created_by),
+        ];
+
+        Ok((
+            row.contract_number,
+            pdf::render_summary("Szerződés összesítő", &fields),
+        ))
+    }
+
     pub async fn get_customer_uuid(db: &Database, contract_uuid: Uuid) -> Result<Option<Uuid>> {
         let customer = sqlx::query!(
             "SELECT
@@ -351,6 +1255,39 @@ impl Contract {
         Ok(customer.uuid)
     }
 
+    pub async fn get_open_tasks(
+        db: &Database,
+        key: &Key,
+        actor_id: i32,
+        contract_uuid: Uuid,
+    ) -> Result<Vec<InterventionTaskDto>> {
+        if Self::get_id_by_uuid(db, Some(contract_uuid))
+            .await?
+            .is_none()
+        {
+            return Err(ApiError::NotFound("Szerződés nem található!".to_string()).into());
+        }
+
+        let rows = sqlx::query_as!(
+            TaskListingRow,
+            "SELECT c.uuid AS customer_uuid, c.full_name, c.phone_number_enc, c.phone_number_nonce, c.email_enc, c.email_nonce, c.address_enc, c.address_nonce, it.uuid, it.contract_number, it.product_name, it.outstanding_days, it.balance, it.processing_deadline, it.comment, it.status, it.created_by
+             FROM customer_contracts cc
+             JOIN customers c ON c.id = cc.customer_id
+             JOIN customer_intervention_tasks it
+                ON it.contract_id = cc.id
+                OR (it.contract_id IS NULL AND it.customer_id = cc.customer_id AND it.contract_number = cc.contract_number)
+             WHERE cc.uuid = $1 AND it.status NOT IN ('Processed', 'PendingDeletion')
+             ORDER BY it.processing_deadline
+             LIMIT $2",
+            contract_uuid,
+            validation::decrypt_fetch_limit()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+        validation::ensure_decrypt_limit(rows.len())?;
+        InterventionTask::decrypt_listing(db, key, actor_id, rows, "contract_tasks").await
+    }
+
     pub async fn change_first_payment_state(
         db: &Database,
         contract_uuid: Uuid,
@@ -368,6 +1305,54 @@ impl Contract {
         Ok(())
     }
 
+    pub async fn change_status(
+        db: &Database,
+        contract_uuid: Uuid,
+        status: ContractStatus,
+    ) -> Result<()> {
+        let allowed_from: Vec<String> = status
+            .allowed_from()
+            .iter()
+            .map(|from| from.to_string())
+            .collect();
+        // The current status is checked in the UPDATE itself, so a concurrent change can't slip past it
+        let result = sqlx::query!(
+            "UPDATE customer_contracts
+             SET status = $2
+             WHERE uuid = $1 AND status = ANY($3)",
+            contract_uuid,
+            status.to_string(),
+            &allowed_from
+        )
+        .execute(&db.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            let current = sqlx::query_scalar!(
+                "SELECT status FROM customer_contracts WHERE uuid = $1",
+                contract_uuid
+            )
+            .fetch_optional(&db.pool)
+            .await?;
+            return Err(match current {
+                Some(current) => ApiError::Conflict(format!(
+                    "A szerződés státusza nem módosítható: {current} -> {status}"
+                )),
+                None => ApiError::NotFound("Szerződés nem található!".to_string()),
+            }
+            .into());
+        }
+        db.webhooks
+            .dispatch(
+                db,
+                WebhookEvent::ContractModified,
+                json!({ "uuid": contract_uuid, "status": status }),
+            )
+            .await;
+
+        Ok(())
+    }
+
     pub async fn change_handler(
         db: &Database,
         user_full_name: String,
@@ -392,18 +1377,121 @@ impl Contract {
         Ok(())
     }
 
+    /// All or nothing, a contract with open intervention tasks fails the whole batch.
     pub async fn delete(db: &Database, contract_uuids: Vec<Uuid>) -> Result<()> {
-        sqlx::query!(
+        let mut tx = db.pool.begin().await?;
+
+        let rows = Self::lock_for_delete(&mut tx, &contract_uuids).await?;
+        if let Some(row) = rows.iter().find(|row| row.has_open_tasks) {
+            return Err(ApiError::Conflict(format!(
+                "A szerződéshez nyitott intervenciós feladat tartozik: {}",
+                row.uuid
+            ))
+            .into());
+        }
+
+        let deleted = sqlx::query_scalar!(
             "DELETE FROM customer_contracts
-             WHERE uuid = ANY($1)",
+             WHERE uuid = ANY($1)
+             RETURNING uuid",
             &contract_uuids
         )
-        .execute(&db.pool)
+        .fetch_all(&mut *tx)
         .await?;
 
+        tx.commit().await?;
+
+        if !deleted.is_empty() {
+            db.webhooks
+                .dispatch(
+                    db,
+                    WebhookEvent::ContractDeleted,
+                    json!({ "uuids": deleted }),
+                )
+                .await;
+        }
+
         Ok(())
     }
 
+    // Locks the contracts for the rest of the transaction, so no task can be opened on
+    // them between the check and the delete
+    async fn lock_for_delete(
+        conn: &mut sqlx::PgConnection,
+        contract_uuids: &[Uuid],
+    ) -> Result<Vec<DeleteCandidate>> {
+        let rows = sqlx::query_as!(
+            DeleteCandidate,
+            r#"SELECT
+                cc.uuid AS "uuid!",
+                EXISTS(
+                    SELECT 1 FROM customer_intervention_tasks it
+                    WHERE (it.contract_id = cc.id
+                        OR (it.contract_id IS NULL AND it.customer_id = cc.customer_id AND it.contract_number = cc.contract_number))
+                    AND it.status NOT IN ('Processed', 'PendingDeletion')
+                ) AS "has_open_tasks!"
+            FROM customer_contracts cc
+            WHERE cc.uuid = ANY($1)
+            FOR UPDATE OF cc"#,
+            contract_uuids
+        )
+        .fetch_all(conn)
+        .await?;
+
+        Ok(rows)
+    }
+
+    pub async fn delete_partial(
+        db: &Database,
+        contract_uuids: Vec<Uuid>,
+    ) -> Result<PartialDeleteDto> {
+        let mut tx = db.pool.begin().await?;
+
+        let rows = Self::lock_for_delete(&mut tx, &contract_uuids).await?;
+
+        let mut blocked = Vec::new();
+        let mut deletable = Vec::new();
+        for contract_uuid in contract_uuids {
+            match rows.iter().find(|row| row.uuid == contract_uuid) {
+                Some(row) if row.has_open_tasks => blocked.push(BlockedDeleteDto {
+                    uuid: contract_uuid,
+                    reason: "A szerződéshez nyitott intervenciós feladat tartozik!".to_string(),
+                }),
+                Some(_) => deletable.push(contract_uuid),
+                None => blocked.push(BlockedDeleteDto {
+                    uuid: contract_uuid,
+                    reason: "Szerződés nem található!".to_string(),
+                }),
+            }
+        }
+
+        let deleted = sqlx::query_scalar!(
+            "DELETE FROM customer_contracts
+             WHERE uuid = ANY($1)
+             RETURNING uuid",
+            &deletable
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<Uuid>>();
+
+        tx.commit().await?;
+
+        if !deleted.is_empty() {
+            db.webhooks
+                .dispatch(
+                    db,
+                    WebhookEvent::ContractDeleted,
+                    json!({ "uuids": deleted }),
+                )
+                .await;
+        }
+
+        Ok(PartialDeleteDto { deleted, blocked })
+    }
+
     // CHART FUNCTIONS
     pub async fn get_production_value(db: &Database, user_id: i32) -> Result<i64> {
         let chart = sqlx::query!(
@@ -449,6 +1537,31 @@ impl Contract {
         Ok(chart.production_value.unwrap())
     }
 
+    pub async fn get_monthly_production_values_by_user_uuid(
+        db: &Database,
+        user_uuid: Uuid,
+    ) -> Result<(i64, i64)> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let chart = sqlx::query!(
+            r#"SELECT
+                COALESCE(SUM(annual_fee) FILTER (WHERE handle_at >= DATE_TRUNC('month', NOW())), 0) AS "this_month!",
+                COALESCE(SUM(annual_fee) FILTER (
+                    WHERE handle_at >= DATE_TRUNC('month', NOW()) - INTERVAL '1 month'
+                      AND handle_at < DATE_TRUNC('month', NOW())
+                ), 0) AS "last_month!"
+            FROM customer_contracts
+            WHERE user_id = $1"#,
+            user_id
+        )
+        .fetch_one(&db.pool)
+        .await?;
+
+        Ok((chart.this_month, chart.last_month))
+    }
+
     pub async fn get_production_count(db: &Database, user_id: i32) -> Result<i64> {
         let chart = sqlx::query!(
             "SELECT
@@ -493,94 +1606,205 @@ impl Contract {
         Ok(chart.production.unwrap())
     }
 
-    pub async fn get_portfolio_chart(db: &Database, user_id: i32) -> Result<PortfolioDto> {
-        let chart = sqlx::query!(
-            "SELECT
-                COALESCE(SUM(CASE WHEN cc.contract_type = 'BonusLifeProgram' THEN 1 END), 0) AS bonus_life_program,
-                COALESCE(SUM(CASE WHEN cc.contract_type = 'LifeProgram' THEN 1 END), 0) AS life_program,
-                COALESCE(SUM(CASE WHEN cc.contract_type = 'AllianzCareNow' THEN 1 END), 0) AS allianz_care_now,
-                COALESCE(SUM(CASE WHEN cc.contract_type = 'HealthProgram' THEN 1 END), 0) AS health_program,
-                COALESCE(SUM(CASE WHEN cc.contract_type = 'MyhomeHomeInsurance' THEN 1 END), 0) AS myhome_home_insurance,
-                COALESCE(SUM(CASE WHEN cc.contract_type = 'MfoHomeInsurance' THEN 1 END), 0) AS mfo_home_insurance,
-                COALESCE(SUM(CASE WHEN cc.contract_type = 'CorporatePropertyInsurance' THEN 1 END), 0) AS corporate_property_insurance,
-                COALESCE(SUM(CASE WHEN cc.contract_type = 'Kgfb' THEN 1 END), 0) AS kgfb,
-                COALESCE(SUM(CASE WHEN cc.contract_type = 'Casco' THEN 1 END), 0) AS casco,
-                COALESCE(SUM(CASE WHEN cc.contract_type = 'TravelInsurance' THEN 1 END), 0) AS travel_insurance,
-                COALESCE(SUM(CASE WHEN cc.contract_type = 'CondominiumInsurance' THEN 1 END), 0) AS condominium_insurance,
-                COALESCE(SUM(CASE WHEN cc.contract_type = 'AgriculturalInsurance' THEN 1 END), 0) AS agricultural_insurance
-                FROM users u
-                LEFT JOIN customer_contracts cc
-                ON (
+    pub async fn get_new_vs_renewal(
+        db: &Database,
+        user_uuid: Uuid,
+        start_date: NaiveDateTime,
+        end_date: NaiveDateTime,
+    ) -> Result<NewVsRenewalDto> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let row = sqlx::query!(
+            r#"SELECT
+                COUNT(*) FILTER (WHERE NOT is_renewal) AS "new_count!",
+                COALESCE(SUM(annual_fee) FILTER (WHERE NOT is_renewal), 0)::BIGINT AS "new_value!",
+                COUNT(*) FILTER (WHERE is_renewal) AS "renewal_count!",
+                COALESCE(SUM(annual_fee) FILTER (WHERE is_renewal), 0)::BIGINT AS "renewal_value!"
+            FROM customer_contracts
+            WHERE user_id = $1 AND handle_at BETWEEN $2 AND $3"#,
+            user_id,
+            start_date.and_utc(),
+            end_date.and_utc()
+        )
+        .fetch_one(&db.pool)
+        .await?;
+
+        Ok(NewVsRenewalDto {
+            new_count: row.new_count,
+            new_value: row.new_value,
+            renewal_count: row.renewal_count,
+            renewal_value: row.renewal_value,
+        })
+    }
+
+    /// Applies the per type rate to the summed annual fees, types without a configured
+    /// rate count as zero and are flagged with `rate_missing`.
+    pub async fn estimate_commission(
+        db: &Database,
+        user_uuid: Uuid,
+        start_date: NaiveDateTime,
+        end_date: NaiveDateTime,
+    ) -> Result<CommissionEstimateDto> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let rows = sqlx::query!(
+            r#"SELECT
+                cc.contract_type,
+                SUM(cc.annual_fee)::BIGINT AS "value!",
+                cr.rate_percent AS "rate?"
+            FROM customer_contracts cc
+            LEFT JOIN commission_rates cr ON cr.contract_type = cc.contract_type
+            WHERE cc.user_id = $1 AND cc.handle_at BETWEEN $2 AND $3
+            GROUP BY cc.contract_type, cr.rate_percent
+            ORDER BY cc.contract_type"#,
+            user_id,
+            start_date.and_utc(),
+            end_date.and_utc()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        let per_type: Vec<CommissionTypeDto> = rows
+            .into_iter()
+            .map(|row| {
+                let rate = row.rate.unwrap_or(0.0);
+                CommissionTypeDto {
+                    contract_type: row.contract_type,
+                    value: row.value,
+                    rate,
+                    commission: (row.value as f64 * rate / 100.0).round() as i64,
+                    rate_missing: row.rate.is_none(),
+                }
+            })
+            .collect();
+        let total_commission = per_type.iter().map(|item| item.commission).sum();
+
+        Ok(CommissionEstimateDto {
+            per_type,
+            total_commission,
+        })
+    }
+
+    /// Every contract type, including those without contracts.
+    pub async fn get_portfolio_chart(db: &Database, user_id: i32) -> Result<ContractTypeCountsDto> {
+        let rows = sqlx::query!(
+            r#"SELECT
+                ct.name AS contract_type,
+                COUNT(cc.id) AS "count!"
+            FROM contract_types ct
+            LEFT JOIN customer_contracts cc
+            ON cc.contract_type = ct.name AND EXISTS (
+                SELECT 1 FROM users u
+                WHERE u.id = $1 AND (
                     u.user_role = 'Leader'
                     OR (u.user_role = 'Manager' AND cc.user_id IN (
                         SELECT id FROM users WHERE manager_id = u.id OR id = u.id
                     ))
                 )
-                WHERE u.id = $1;",
+            )
+            GROUP BY ct.name"#,
             user_id
         )
-        .fetch_one(&db.pool)
+        .fetch_all(&db.pool)
         .await?;
 
-        Ok(PortfolioDto {
-            bonus_life_program: chart.bonus_life_program.unwrap(),
-            life_program: chart.life_program.unwrap(),
-            allianz_care_now: chart.allianz_care_now.unwrap(),
-            health_program: chart.health_program.unwrap(),
-            myhome_home_insurance: chart.myhome_home_insurance.unwrap(),
-            mfo_home_insurance: chart.mfo_home_insurance.unwrap(),
-            corporate_property_insurance: chart.corporate_property_insurance.unwrap(),
-            kgfb: chart.kgfb.unwrap(),
-            casco: chart.casco.unwrap(),
-            travel_insurance: chart.travel_insurance.unwrap(),
-            condominium_insurance: chart.condominium_insurance.unwrap(),
-            agricultural_insurance: chart.agricultural_insurance.unwrap(),
-        })
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.contract_type, row.count))
+            .collect())
+    }
+
+    pub fn portfolio_csv(chart: &ContractTypeCountsDto) -> Result<Vec<u8>> {
+        let mut writer = csv::Writer::from_writer(vec![]);
+        writer.write_record(["contract_type", "count"])?;
+        for (contract_type, count) in chart {
+            writer.write_record([contract_type.clone(), count.to_string()])?;
+        }
+
+        Ok(writer.into_inner()?)
     }
 
     pub async fn get_portfolio_chart_by_user_uuid(
         db: &Database,
         user_uuid: Uuid,
-    ) -> Result<PortfolioDto> {
+    ) -> Result<ContractTypeCountsDto> {
         let user_id = User::get_id_by_uuid(db, Some(user_uuid))
             .await?
             .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
 
-        let chart = sqlx::query!(
-            "SELECT
-                COUNT(*) FILTER (WHERE contract_type = 'BonusLifeProgram') AS bonus_life_program,
-                COUNT(*) FILTER (WHERE contract_type = 'LifeProgram') AS life_program,
-                COUNT(*) FILTER (WHERE contract_type = 'AllianzCareNow') AS allianz_care_now,
-                COUNT(*) FILTER (WHERE contract_type = 'HealthProgram') AS health_program,
-                COUNT(*) FILTER (WHERE contract_type = 'MyhomeHomeInsurance') AS myhome_home_insurance,
-                COUNT(*) FILTER (WHERE contract_type = 'MfoHomeInsurance') AS mfo_home_insurance,
-                COUNT(*) FILTER (WHERE contract_type = 'CorporatePropertyInsurance') AS corporate_property_insurance,
-                COUNT(*) FILTER (WHERE contract_type = 'Kgfb') AS kgfb,
-                COUNT(*) FILTER (WHERE contract_type = 'Casco') AS casco,
-                COUNT(*) FILTER (WHERE contract_type = 'TravelInsurance') AS travel_insurance,
-                COUNT(*) FILTER (WHERE contract_type = 'CondominiumInsurance') AS condominium_insurance,
-                COUNT(*) FILTER (WHERE contract_type = 'AgriculturalInsurance') AS agricultural_insurance
+        let rows = sqlx::query!(
+            r#"SELECT
+                ct.name AS contract_type,
+                COUNT(cc.id) AS "count!"
+            FROM contract_types ct
+            LEFT JOIN customer_contracts cc
+            ON cc.contract_type = ct.name AND cc.user_id = $1
+            GROUP BY ct.name"#,
+            user_id
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.contract_type, row.count))
+            .collect())
+    }
+
+    /// Only contract types the user actually has contracts of are present.
+    pub async fn get_portfolio_map(db: &Database, user_uuid: Uuid) -> Result<HashMap<String, i64>> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let rows = sqlx::query!(
+            r#"SELECT contract_type, COUNT(*) AS "count!"
             FROM customer_contracts
-            WHERE user_id = $1",
+            WHERE user_id = $1
+            GROUP BY contract_type"#,
             user_id
         )
-        .fetch_one(&db.pool)
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.contract_type, row.count))
+            .collect())
+    }
+
+    pub async fn get_status_summary(
+        db: &Database,
+        manager_id: i32,
+    ) -> Result<HashMap<String, i64>> {
+        let rows = sqlx::query!(
+            r#"SELECT cc.status, COUNT(*) AS "count!"
+            FROM users u
+            JOIN customer_contracts cc
+            ON (
+                u.user_role = 'Leader'
+                OR (u.user_role = 'Manager' AND cc.user_id IN (
+                    SELECT id FROM users WHERE manager_id = u.id OR id = u.id
+                ))
+            )
+            WHERE u.id = $1
+            GROUP BY cc.status"#,
+            manager_id
+        )
+        .fetch_all(&db.pool)
         .await?;
 
-        Ok(PortfolioDto {
-            bonus_life_program: chart.bonus_life_program.unwrap(),
-            life_program: chart.life_program.unwrap(),
-            allianz_care_now: chart.allianz_care_now.unwrap(),
-            health_program: chart.health_program.unwrap(),
-            myhome_home_insurance: chart.myhome_home_insurance.unwrap(),
-            mfo_home_insurance: chart.mfo_home_insurance.unwrap(),
-            corporate_property_insurance: chart.corporate_property_insurance.unwrap(),
-            kgfb: chart.kgfb.unwrap(),
-            casco: chart.casco.unwrap(),
-            travel_insurance: chart.travel_insurance.unwrap(),
-            condominium_insurance: chart.condominium_insurance.unwrap(),
-            agricultural_insurance: chart.agricultural_insurance.unwrap(),
-        })
+        let mut summary: HashMap<String, i64> = ContractStatus::VARIANTS
+            .iter()
+            .map(|status| (status.to_string(), 0))
+            .collect();
+        summary.extend(rows.into_iter().map(|row| (row.status, row.count)));
+
+        Ok(summary)
     }
 
     pub async fn get_weekly_production_chart(
@@ -843,4 +2067,1065 @@ impl Contract {
 
         Ok(dates)
     }
+
+    pub async fn get_type_trend(
+        db: &Database,
+        user_uuid: Uuid,
+        start_date: NaiveDateTime,
+        end_date: NaiveDateTime,
+    ) -> Result<Vec<ContractTypeTrendDto>> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let rows = sqlx::query!(
+            r#"SELECT
+                DATE_TRUNC('month', handle_at)::DATE AS "month!",
+                contract_type,
+                COUNT(*) AS "count!"
+            FROM customer_contracts
+            WHERE handle_at BETWEEN $2 AND $3 AND user_id = $1
+            GROUP BY 1, 2
+            ORDER BY 1"#,
+            user_id,
+            start_date.and_utc(),
+            end_date.and_utc()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        let mut trend: Vec<ContractTypeTrendDto> = Vec::new();
+        for row in rows {
+            match trend.last_mut() {
+                Some(last) if last.month == row.month => {
+                    last.per_type.insert(row.contract_type, row.count);
+                }
+                _ => trend.push(ContractTypeTrendDto {
+                    month: row.month,
+                    per_type: ContractTypeCountsDto::from([(row.contract_type, row.count)]),
+                }),
+            }
+        }
+
+        Ok(trend)
+    }
+
+    pub async fn get_monthly_combined(
+        db: &Database,
+        user_uuid: Uuid,
+        start_date: NaiveDateTime,
+        end_date: NaiveDateTime,
+    ) -> Result<Vec<MonthlyCombinedChartDto>> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let charts = sqlx::query!(
+            "SELECT
+                CAST(EXTRACT(MONTH FROM handle_at) AS SMALLINT) AS month,
+                COALESCE(SUM(annual_fee) FILTER (WHERE EXTRACT(WEEK FROM handle_at) - EXTRACT(WEEK FROM DATE_TRUNC('month', handle_at)) + 1 = 1), 0) AS value_week1,
+                COALESCE(SUM(annual_fee) FILTER (WHERE EXTRACT(WEEK FROM handle_at) - EXTRACT(WEEK FROM DATE_TRUNC('month', handle_at)) + 1 = 2), 0) AS value_week2,
+                COALESCE(SUM(annual_fee) FILTER (WHERE EXTRACT(WEEK FROM handle_at) - EXTRACT(WEEK FROM DATE_TRUNC('month', handle_at)) + 1 = 3), 0) AS value_week3,
+                COALESCE(SUM(annual_fee) FILTER (WHERE EXTRACT(WEEK FROM handle_at) - EXTRACT(WEEK FROM DATE_TRUNC('month', handle_at)) + 1 = 4), 0) AS value_week4,
+                COALESCE(SUM(annual_fee) FILTER (WHERE EXTRACT(WEEK FROM handle_at) - EXTRACT(WEEK FROM DATE_TRUNC('month', handle_at)) + 1 = 5), 0) AS value_week5,
+                COUNT(*) FILTER (WHERE EXTRACT(WEEK FROM handle_at) - EXTRACT(WEEK FROM DATE_TRUNC('month', handle_at)) + 1 = 1) AS count_week1,
+                COUNT(*) FILTER (WHERE EXTRACT(WEEK FROM handle_at) - EXTRACT(WEEK FROM DATE_TRUNC('month', handle_at)) + 1 = 2) AS count_week2,
+                COUNT(*) FILTER (WHERE EXTRACT(WEEK FROM handle_at) - EXTRACT(WEEK FROM DATE_TRUNC('month', handle_at)) + 1 = 3) AS count_week3,
+                COUNT(*) FILTER (WHERE EXTRACT(WEEK FROM handle_at) - EXTRACT(WEEK FROM DATE_TRUNC('month', handle_at)) + 1 = 4) AS count_week4,
+                COUNT(*) FILTER (WHERE EXTRACT(WEEK FROM handle_at) - EXTRACT(WEEK FROM DATE_TRUNC('month', handle_at)) + 1 = 5) AS count_week5
+            FROM customer_contracts
+            WHERE handle_at BETWEEN $2 AND $3 AND user_id = $1
+            GROUP BY month
+            ORDER BY month;",
+            user_id,
+            start_date.and_utc(),
+            end_date.and_utc()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        let dates = charts
+            .into_iter()
+            .map(|chart| MonthlyCombinedChartDto {
+                month: chart.month.unwrap(),
+                value_weeks: [
+                    chart.value_week1.unwrap(),
+                    chart.value_week2.unwrap(),
+                    chart.value_week3.unwrap(),
+                    chart.value_week4.unwrap(),
+                    chart.value_week5.unwrap(),
+                ],
+                count_weeks: [
+                    chart.count_week1.unwrap(),
+                    chart.count_week2.unwrap(),
+                    chart.count_week3.unwrap(),
+                    chart.count_week4.unwrap(),
+                    chart.count_week5.unwrap(),
+                ],
+            })
+            .collect();
+
+        Ok(dates)
+    }
+
+    pub async fn get_meeting_conversion(
+        db: &Database,
+        user_uuid: Uuid,
+        start_date: NaiveDateTime,
+        end_date: NaiveDateTime,
+    ) -> Result<MeetingConversionDto> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let row = sqlx::query!(
+            r#"SELECT
+                (SELECT COUNT(*) FROM user_dates
+                 WHERE user_id = $1 AND meet_date BETWEEN $2 AND $3) AS "meetings!",
+                (SELECT COUNT(*) FROM user_dates
+                 WHERE user_id = $1 AND meet_date BETWEEN $2 AND $3 AND is_completed = TRUE) AS "completed_meetings!",
+                (SELECT COUNT(*) FROM customer_contracts
+                 WHERE user_id = $1 AND handle_at BETWEEN $4 AND $5) AS "contracts!""#,
+            user_id,
+            start_date,
+            end_date,
+            start_date.and_utc(),
+            end_date.and_utc()
+        )
+        .fetch_one(&db.pool)
+        .await?;
+
+        Ok(MeetingConversionDto {
+            meetings: row.meetings,
+            completed_meetings: row.completed_meetings,
+            contracts: row.contracts,
+            meetings_per_contract: (row.contracts > 0)
+                .then(|| row.meetings as f64 / row.contracts as f64),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::redis::Token;
+
+    async fn insert_contract(db: &Database, customer_id: i32) -> (i32, Uuid) {
+        let row = sqlx::query!(
+            "INSERT INTO customer_contracts(contract_number, contract_type, annual_fee, payment_frequency, payment_method, customer_id, created_by)
+             VALUES($1, 'Kgfb', 10000, 'Annual', 'Transfer', $2, 'Teszt')
+             RETURNING id, uuid",
+            Token::generate_token(),
+            customer_id
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        (row.id, row.uuid.unwrap())
+    }
+
+    async fn insert_user(db: &Database, user_role: &str) -> (i32, Uuid, String) {
+        let username = Token::generate_token();
+        let full_name = format!("Teszt Rögzítő {username}");
+        let user = sqlx::query!(
+            "INSERT INTO users(email, username, password, user_role)
+             VALUES($1, $2, '', $3) RETURNING id, uuid",
+            format!("{username}@example.com"),
+            username,
+            user_role
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "INSERT INTO user_info(user_id, full_name, phone_number) VALUES($1, $2, $3)",
+            user.id,
+            full_name,
+            username
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        (user.id, user.uuid.unwrap(), full_name)
+    }
+
+    // Customers outlive their owner, so they go first, taking their records with them
+    async fn delete_user(db: &Database, user_id: i32) {
+        sqlx::query!("DELETE FROM customers WHERE user_id = $1", user_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+    }
+
+    async fn insert_customer(db: &Database, key: &Key, user_id: i32) -> (i32, Uuid) {
+        let encrypted = |value: &str| encrypt::encrypt_value(key, value);
+        let (phone_enc, phone_nonce) = encrypted("+36301234567");
+        let (email_enc, email_nonce) = encrypted("teszt@example.com");
+        let (address_enc, address_nonce) = encrypted("1051 Budapest, Nádor u. 5.");
+        let row = sqlx::query!(
+            "INSERT INTO customers(full_name, phone_number_enc, phone_number_nonce, email_enc, email_nonce, address_enc, address_nonce, user_id, created_by)
+             VALUES('Teszt Ügyfél', $1, $2, $3, $4, $5, $6, $7, 'Teszt')
+             RETURNING id, uuid",
+            phone_enc,
+            phone_nonce,
+            email_enc,
+            email_nonce,
+            address_enc,
+            address_nonce,
+            user_id
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        (row.id, row.uuid.unwrap())
+    }
+
+    async fn insert_handled_contract(
+        db: &Database,
+        customer_id: i32,
+        user_id: i32,
+        annual_fee: i32,
+        handle_at: &str,
+    ) -> Uuid {
+        let (contract_id, contract_uuid) = insert_contract(db, customer_id).await;
+        sqlx::query!(
+            "UPDATE customer_contracts SET user_id = $1, annual_fee = $2, handle_at = $3 WHERE id = $4",
+            user_id,
+            annual_fee,
+            datetime(handle_at).and_utc(),
+            contract_id
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        contract_uuid
+    }
+
+    fn datetime(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn new_contract(created_by: &str) -> Contract {
+        Contract {
+            contract_number: Some(Token::generate_token()),
+            contract_type: Some(ContractType::Kgfb),
+            annual_fee: Some(10000),
+            payment_frequency: Some(PaymentFrequency::Annual),
+            payment_method: Some(PaymentMethod::Transfer),
+            created_by: Some(created_by.to_string()),
+            ..Default::default()
+        }
+    }
+
+    async fn contract_exists(db: &Database, contract_uuid: Uuid) -> bool {
+        sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM customer_contracts WHERE uuid = $1) AS "exists!""#,
+            contract_uuid
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap()
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn contracts_with_open_tasks_are_not_deleted() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let customer_id = sqlx::query_scalar!(
+            "INSERT INTO customers(full_name, phone_number_enc, phone_number_nonce, email_enc, email_nonce, address_enc, address_nonce, created_by)
+             VALUES('Teszt Ügyfél', '', '', '', '', '', '', 'Teszt')
+             RETURNING id"
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        let (blocked_id, blocked_uuid) = insert_contract(&db, customer_id).await;
+        let (_, free_uuid) = insert_contract(&db, customer_id).await;
+        sqlx::query!(
+            "INSERT INTO customer_intervention_tasks(contract_number, product_name, outstanding_days, balance, processing_deadline, status, customer_id, contract_id, created_by)
+             VALUES('', 'Kgfb', 30, 10000, NOW(), 'Pending', $1, $2, 'Teszt')",
+            customer_id,
+            blocked_id
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+        let all_or_nothing = Contract::delete(&db, vec![blocked_uuid, free_uuid]).await;
+        let kept_both =
+            contract_exists(&db, blocked_uuid).await && contract_exists(&db, free_uuid).await;
+        let partial = Contract::delete_partial(&db, vec![blocked_uuid, free_uuid]).await;
+        sqlx::query!("DELETE FROM customers WHERE id = $1", customer_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            all_or_nothing.unwrap_err().downcast_ref::<ApiError>(),
+            Some(ApiError::Conflict(_))
+        ));
+        assert!(kept_both);
+        let partial = partial.unwrap();
+        assert_eq!(partial.deleted, vec![free_uuid]);
+        assert_eq!(partial.blocked.len(), 1);
+        assert_eq!(partial.blocked[0].uuid, blocked_uuid);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn listings_and_pdf_record_pii_reads() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let hmac_secret = Token::generate_token().into_bytes();
+        let username = Token::generate_token();
+        let user = sqlx::query!(
+            "INSERT INTO users(email, username, password, user_role)
+             VALUES($1, $2, '', 'Agent') RETURNING id, uuid",
+            format!("{username}@example.com"),
+            username
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "INSERT INTO user_info(user_id, full_name, phone_number) VALUES($1, 'Teszt Rögzítő', $2)",
+            user.id,
+            username
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let mut conn = db.pool.acquire().await.unwrap();
+        let (customer_id, _) = Customer::create_or_get_in(
+            &mut conn,
+            &key,
+            &hmac_secret,
+            user.id,
+            &Customer {
+                full_name: Some("Teszt Ügyfél".to_string()),
+                phone_number: Some("+36301234567".to_string()),
+                email: Some("teszt@example.com".to_string()),
+                address: Some("Budapest".to_string()),
+                created_by: Some("Teszt Rögzítő".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        drop(conn);
+        let (contract_id, contract_uuid) = insert_contract(&db, customer_id).await;
+        sqlx::query!(
+            "UPDATE customer_contracts SET user_id = $1 WHERE id = $2",
+            user.id,
+            contract_id
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let customer_uuid =
+            sqlx::query_scalar!("SELECT uuid FROM customers WHERE id = $1", customer_id)
+                .fetch_one(&db.pool)
+                .await
+                .unwrap()
+                .unwrap();
+
+        let listed =
+            Contract::get_all(&db, &key, user.id, user.uuid.unwrap(), ListingScope::Own).await;
+        let pdf = Contract::render_pdf(&db, &key, user.id, contract_uuid).await;
+        let unpaid = Contract::get_unpaid_first(&db, &key, user.id, user.uuid.unwrap()).await;
+        let found = Contract::search(
+            &db,
+            &key,
+            user.id,
+            user.uuid.unwrap(),
+            "Teszt",
+            PaginationQuery::default(),
+        )
+        .await;
+        let reads =
+            AuditLog::get_access_log(&db, customer_uuid, AuditAction::CustomerPiiRead).await;
+        sqlx::query!("DELETE FROM audit_logs WHERE actor_id = $1", user.id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM customers WHERE id = $1", customer_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM users WHERE id = $1", user.id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        assert_eq!(listed.unwrap().len(), 1);
+        assert!(pdf.is_ok());
+        assert_eq!(unpaid.unwrap().len(), 1);
+        assert_eq!(found.unwrap().items.len(), 1);
+        let reads = reads.unwrap();
+        assert_eq!(reads.len(), 4);
+        assert!(reads.iter().all(|read| read.actor_uuid == user.uuid));
+    }
+
+    #[test]
+    fn cancelled_contracts_cannot_be_reactivated() {
+        assert!(
+            !ContractStatus::Active
+                .allowed_from()
+                .iter()
+                .any(|from| matches!(from, ContractStatus::Cancelled))
+        );
+        assert!(
+            !ContractStatus::Pending
+                .allowed_from()
+                .iter()
+                .any(|from| matches!(from, ContractStatus::Active))
+        );
+        assert!(
+            ContractStatus::Active
+                .allowed_from()
+                .iter()
+                .any(|from| matches!(from, ContractStatus::Lapsed))
+        );
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn invalid_status_transitions_are_rejected() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let (user_id, _, _) = insert_user(&db, "Agent").await;
+        let (customer_id, _) = insert_customer(&db, &key, user_id).await;
+        let (_, contract_uuid) = insert_contract(&db, customer_id).await;
+
+        let cancelled =
+            Contract::change_status(&db, contract_uuid, ContractStatus::Cancelled).await;
+        let reactivated = Contract::change_status(&db, contract_uuid, ContractStatus::Active).await;
+        let status = Contract::get_by_uuid(&db, contract_uuid)
+            .await
+            .unwrap()
+            .status;
+        let missing = Contract::change_status(&db, Uuid::new_v4(), ContractStatus::Active).await;
+        delete_user(&db, user_id).await;
+
+        cancelled.unwrap();
+        assert!(matches!(
+            ApiError::from(reactivated.unwrap_err()),
+            ApiError::Conflict(_)
+        ));
+        assert!(matches!(status, Some(ContractStatus::Cancelled)));
+        assert!(matches!(
+            ApiError::from(missing.unwrap_err()),
+            ApiError::NotFound(_)
+        ));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn portfolio_map_only_has_types_with_contracts() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let username = Token::generate_token();
+        let user = sqlx::query!(
+            "INSERT INTO users(email, username, password, user_role)
+             VALUES($1, $2, '', 'Agent') RETURNING id, uuid",
+            format!("{username}@example.com"),
+            username
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        let customer_id = sqlx::query_scalar!(
+            "INSERT INTO customers(full_name, phone_number_enc, phone_number_nonce, email_enc, email_nonce, address_enc, address_nonce, created_by)
+             VALUES('Teszt Ügyfél', '', '', '', '', '', '', 'Teszt')
+             RETURNING id"
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        for contract_type in ["Kgfb", "Kgfb", "Casco"] {
+            let (contract_id, _) = insert_contract(&db, customer_id).await;
+            sqlx::query!(
+                "UPDATE customer_contracts SET user_id = $1, contract_type = $2 WHERE id = $3",
+                user.id,
+                contract_type,
+                contract_id
+            )
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        }
+
+        let (manager_id, _, _) = insert_user(&db, "Manager").await;
+
+        let user_uuid = user.uuid.unwrap();
+        let map = Contract::get_portfolio_map(&db, user_uuid).await;
+        let chart = Contract::get_portfolio_chart_by_user_uuid(&db, user_uuid).await;
+        // The agent isn't on this manager's team, so the map route refuses it
+        let outsider = User::require_access(&db, manager_id, user_uuid).await;
+        delete_user(&db, manager_id).await;
+        sqlx::query!("DELETE FROM customers WHERE id = $1", customer_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM users WHERE id = $1", user.id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            ApiError::from(outsider.unwrap_err()),
+            ApiError::Forbidden(_)
+        ));
+        assert_eq!(
+            map.unwrap(),
+            HashMap::from([("Kgfb".to_string(), 2), ("Casco".to_string(), 1)])
+        );
+        let chart = chart.unwrap();
+        assert_eq!(chart.get("LifeProgram"), Some(&0));
+        let portfolio = PortfolioDto::from(&chart);
+        assert_eq!((portfolio.kgfb, portfolio.casco), (2, 1));
+        assert_eq!(portfolio.life_program, 0);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn contracts_are_only_created_for_the_owners_customers() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let (owner_id, owner_uuid, owner_name) = insert_user(&db, "Agent").await;
+        let (other_id, other_uuid, other_name) = insert_user(&db, "Agent").await;
+        let (customer_id, customer_uuid) = insert_customer(&db, &key, owner_id).await;
+
+        let other_access = User::require_access(&db, other_id, owner_uuid).await;
+        let foreign = Contract::create_for_customer(
+            &db,
+            other_id,
+            customer_uuid,
+            other_uuid,
+            new_contract(&other_name),
+        )
+        .await;
+        let own = Contract::create_for_customer(
+            &db,
+            owner_id,
+            customer_uuid,
+            owner_uuid,
+            new_contract(&owner_name),
+        )
+        .await;
+        let linked = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM customer_contracts WHERE customer_id = $1"#,
+            customer_id
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        let customers = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM customers WHERE user_id = $1"#,
+            owner_id
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        delete_user(&db, owner_id).await;
+        delete_user(&db, other_id).await;
+
+        assert!(other_access.is_err());
+        assert!(matches!(
+            ApiError::from(foreign.unwrap_err()),
+            ApiError::Forbidden(_)
+        ));
+        own.unwrap();
+        assert_eq!((linked, customers), (1, 1));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn open_tasks_are_joined_to_their_contract() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let (user_id, _, _) = insert_user(&db, "Agent").await;
+        let (customer_id, _) = insert_customer(&db, &key, user_id).await;
+        let (contract_id, contract_uuid) = insert_contract(&db, customer_id).await;
+        let (other_contract_id, _) = insert_contract(&db, customer_id).await;
+        let contract_number = sqlx::query_scalar!(
+            "SELECT contract_number FROM customer_contracts WHERE id = $1",
+            contract_id
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        // Linked by id, matched by number only, already processed, and another contract's
+        let tasks = [
+            (Some(contract_id), contract_number.clone(), "Pending"),
+            (None, contract_number.clone(), "Pending"),
+            (Some(contract_id), contract_number, "Processed"),
+            (Some(other_contract_id), String::new(), "Pending"),
+        ];
+        let mut task_uuids = Vec::new();
+        for (task_contract_id, task_contract_number, status) in tasks {
+            let task_uuid = sqlx::query_scalar!(
+                "INSERT INTO customer_intervention_tasks(contract_number, product_name, outstanding_days, balance, processing_deadline, status, customer_id, contract_id, created_by)
+                 VALUES($1, 'Kgfb', 30, 10000, NOW(), $2, $3, $4, 'Teszt')
+                 RETURNING uuid",
+                task_contract_number,
+                status,
+                customer_id,
+                task_contract_id
+            )
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+            task_uuids.push(task_uuid);
+        }
+
+        let open = Contract::get_open_tasks(&db, &key, user_id, contract_uuid).await;
+        delete_user(&db, user_id).await;
+
+        let open = open.unwrap();
+        let open_uuids: HashSet<_> = open.iter().map(|task| task.uuid).collect();
+        assert_eq!(open_uuids, HashSet::from([task_uuids[0], task_uuids[1]]));
+        assert!(open.iter().all(|task| task.phone_number == "+36301234567"));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn contracts_take_types_added_to_the_table() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let type_name = format!("Teszt{}", Token::generate_token());
+        sqlx::query!("INSERT INTO contract_types(name) VALUES($1)", type_name)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let (user_id, user_uuid, full_name) = insert_user(&db, "Agent").await;
+        let (_, customer_uuid) = insert_customer(&db, &key, user_id).await;
+
+        let allowed = ContractType::get_allowed(&db).await.unwrap();
+        let contract_type: ContractType = type_name.parse().unwrap();
+        let unknown = ContractType::Custom(format!("Ismeretlen{}", Token::generate_token()));
+        let created = Contract::create_for_customer(
+            &db,
+            user_id,
+            customer_uuid,
+            user_uuid,
+            Contract {
+                contract_type: Some(contract_type.clone()),
+                ..new_contract(&full_name)
+            },
+        )
+        .await;
+        let stored = sqlx::query_scalar!(
+            "SELECT contract_type FROM customer_contracts cc
+             JOIN customers c ON c.id = cc.customer_id
+             WHERE c.uuid = $1",
+            customer_uuid
+        )
+        .fetch_all(&db.pool)
+        .await
+        .unwrap();
+        delete_user(&db, user_id).await;
+        sqlx::query!("DELETE FROM contract_types WHERE name = $1", type_name)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        contract_type.ensure_allowed(&allowed).unwrap();
+        assert!(matches!(
+            ApiError::from(unknown.ensure_allowed(&allowed).unwrap_err()),
+            ApiError::Validation(_)
+        ));
+        created.unwrap();
+        assert_eq!(stored, [type_name]);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn a_failed_contract_rolls_back_its_new_customer() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let hmac_secret = Token::generate_token().into_bytes();
+        let (user_id, user_uuid, full_name) = insert_user(&db, "Agent").await;
+        let customer = Customer {
+            full_name: Some("Teszt Ügyfél".to_string()),
+            phone_number: Some("+36301234567".to_string()),
+            created_by: Some(full_name.clone()),
+            ..Default::default()
+        };
+        let unknown_type = Contract {
+            contract_type: Some(ContractType::Custom(Token::generate_token())),
+            ..new_contract(&full_name)
+        };
+
+        let failed = Contract::create(
+            &db,
+            &key,
+            &hmac_secret,
+            user_id,
+            user_uuid,
+            customer.clone(),
+            unknown_type,
+        )
+        .await;
+        let customers_after_failure = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM customers WHERE user_id = $1"#,
+            user_id
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        let created = Contract::create(
+            &db,
+            &key,
+            &hmac_secret,
+            user_id,
+            user_uuid,
+            customer,
+            new_contract(&full_name),
+        )
+        .await;
+        delete_user(&db, user_id).await;
+
+        assert!(failed.is_err());
+        assert_eq!(customers_after_failure, 0);
+        assert!(created.unwrap().1);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn monthly_combined_buckets_value_and_count_by_week() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let (user_id, user_uuid, _) = insert_user(&db, "Agent").await;
+        let (customer_id, _) = insert_customer(&db, &key, user_id).await;
+        // 2025-03-01 is a Saturday, so the 3rd opens the second week of March
+        for (annual_fee, handle_at) in [
+            (10000, "2025-03-01 10:00"),
+            (20000, "2025-03-02 10:00"),
+            (5000, "2025-03-12 10:00"),
+            (7000, "2025-04-02 10:00"),
+            (9000, "2025-05-02 10:00"),
+        ] {
+            insert_handled_contract(&db, customer_id, user_id, annual_fee, handle_at).await;
+        }
+
+        let chart = Contract::get_monthly_combined(
+            &db,
+            user_uuid,
+            datetime("2025-03-01 00:00"),
+            datetime("2025-04-30 23:59"),
+        )
+        .await;
+        delete_user(&db, user_id).await;
+
+        let chart = chart.unwrap();
+        assert_eq!(
+            chart.iter().map(|month| month.month).collect::<Vec<_>>(),
+            [3, 4]
+        );
+        assert_eq!(chart[0].value_weeks, [30000, 0, 5000, 0, 0]);
+        assert_eq!(chart[0].count_weeks, [2, 0, 1, 0, 0]);
+        assert_eq!(chart[1].value_weeks, [7000, 0, 0, 0, 0]);
+        assert_eq!(chart[1].count_weeks, [1, 0, 0, 0, 0]);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn search_matches_customer_names_and_number_prefixes() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let token = Token::generate_token();
+        let (user_id, user_uuid, _) = insert_user(&db, "Agent").await;
+        let (named_customer, _) = insert_customer(&db, &key, user_id).await;
+        let (numbered_customer, _) = insert_customer(&db, &key, user_id).await;
+        sqlx::query!(
+            "UPDATE customers SET full_name = $1 WHERE id = $2",
+            format!("Kovács Anna {token}"),
+            named_customer
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let mut contract_uuids = Vec::new();
+        for (customer_id, contract_number) in [
+            (named_customer, format!("X-{token}")),
+            (numbered_customer, format!("{token}-42")),
+        ] {
+            let contract_uuid =
+                insert_handled_contract(&db, customer_id, user_id, 10000, "2025-03-01 10:00").await;
+            sqlx::query!(
+                "UPDATE customer_contracts SET contract_number = $1 WHERE uuid = $2",
+                contract_number,
+                contract_uuid
+            )
+            .execute(&db.pool)
+            .await
+            .unwrap();
+            contract_uuids.push(contract_uuid);
+        }
+
+        let mut pages = Vec::new();
+        for query in [
+            format!("anna {token}"),
+            format!("{token}-4"),
+            format!("-{token}"),
+        ] {
+            let page = Contract::search(
+                &db,
+                &key,
+                user_id,
+                user_uuid,
+                &query,
+                PaginationQuery::default(),
+            )
+            .await;
+            pages.push(page.map(|page| {
+                page.items
+                    .into_iter()
+                    .map(|contract| contract.uuid)
+                    .collect::<Vec<_>>()
+            }));
+        }
+        delete_user(&db, user_id).await;
+
+        let [by_name, by_number, by_infix] = pages.try_into().unwrap();
+        assert_eq!(by_name.unwrap(), [Some(contract_uuids[0])]);
+        assert_eq!(by_number.unwrap(), [Some(contract_uuids[1])]);
+        // Numbers only match from their start
+        assert_eq!(by_infix.unwrap(), []);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn modifying_a_missing_contract_is_not_found() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+
+        let modify = Contract::modify(&db, Uuid::new_v4(), new_contract("Teszt")).await;
+
+        assert!(matches!(
+            ApiError::from(modify.unwrap_err()),
+            ApiError::NotFound(_)
+        ));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn meeting_conversion_divides_meetings_by_contracts() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let (user_id, user_uuid, _) = insert_user(&db, "Agent").await;
+        let (customer_id, _) = insert_customer(&db, &key, user_id).await;
+        for (meet_date, is_completed) in [
+            ("2025-03-03 09:00", true),
+            ("2025-03-10 09:00", true),
+            ("2025-03-17 09:00", false),
+            ("2025-04-07 09:00", true),
+        ] {
+            sqlx::query!(
+                "INSERT INTO user_dates(meet_date, full_name, phone_number_enc, phone_number_nonce, meet_location, meet_type, is_completed, created_by, user_id)
+                 VALUES($1, 'Teszt Ügyfél', '', '', 'Iroda', 'Consultation', $2, 'Teszt', $3)",
+                datetime(meet_date),
+                is_completed,
+                user_id
+            )
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        }
+        for handle_at in ["2025-03-05 10:00", "2025-03-20 10:00"] {
+            insert_handled_contract(&db, customer_id, user_id, 10000, handle_at).await;
+        }
+
+        let march = Contract::get_meeting_conversion(
+            &db,
+            user_uuid,
+            datetime("2025-03-01 00:00"),
+            datetime("2025-03-31 23:59"),
+        )
+        .await;
+        let april = Contract::get_meeting_conversion(
+            &db,
+            user_uuid,
+            datetime("2025-04-01 00:00"),
+            datetime("2025-04-30 23:59"),
+        )
+        .await;
+        sqlx::query!("DELETE FROM user_dates WHERE user_id = $1", user_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        delete_user(&db, user_id).await;
+
+        let march = march.unwrap();
+        assert_eq!(
+            (march.meetings, march.completed_meetings, march.contracts),
+            (3, 2, 2)
+        );
+        assert_eq!(march.meetings_per_contract, Some(1.5));
+        // No contracts in April, so there is no ratio rather than a division by zero
+        let april = april.unwrap();
+        assert_eq!((april.meetings, april.contracts), (1, 0));
+        assert_eq!(april.meetings_per_contract, None);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn team_renewals_are_grouped_by_agent() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let (manager_id, _, _) = insert_user(&db, "Manager").await;
+        let (first_id, first_uuid, _) = insert_user(&db, "Agent").await;
+        let (second_id, second_uuid, _) = insert_user(&db, "Agent").await;
+        let (outsider_id, _, _) = insert_user(&db, "Agent").await;
+        sqlx::query!(
+            "UPDATE users SET manager_id = $1 WHERE id = ANY($2)",
+            manager_id,
+            &[first_id, second_id]
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        // Handled a year ago minus `days`, so the anniversary is about `days` away
+        let renewing_in = |days: i64| {
+            (Utc::now() - chrono::Duration::days(365 - days))
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        };
+        for (user_id, days) in [
+            (first_id, 5),
+            (first_id, 10),
+            (second_id, 20),
+            (second_id, 200),
+            (outsider_id, 5),
+        ] {
+            let (customer_id, _) = insert_customer(&db, &key, user_id).await;
+            insert_handled_contract(&db, customer_id, user_id, 10000, &renewing_in(days)).await;
+        }
+
+        let renewals = Contract::get_team_renewals(&db, manager_id, 30).await;
+        for user_id in [first_id, second_id, outsider_id, manager_id] {
+            delete_user(&db, user_id).await;
+        }
+
+        let renewals = renewals.unwrap();
+        let mut counts: Vec<(Option<Uuid>, usize, usize)> = renewals
+            .iter()
+            .map(|agent| (agent.agent_uuid, agent.count, agent.contracts.len()))
+            .collect();
+        counts.sort();
+        let mut expected = vec![(Some(first_uuid), 2, 2), (Some(second_uuid), 1, 1)];
+        expected.sort();
+        assert_eq!(counts, expected);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn contract_pdf_is_a_non_empty_pdf() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let (user_id, _, _) = insert_user(&db, "Agent").await;
+        let (customer_id, _) = insert_customer(&db, &key, user_id).await;
+        let contract_uuid =
+            insert_handled_contract(&db, customer_id, user_id, 10000, "2025-03-01 10:00").await;
+
+        let rendered = Contract::render_pdf(&db, &key, user_id, contract_uuid).await;
+        let missing = Contract::render_pdf(&db, &key, user_id, Uuid::new_v4()).await;
+        let contract_number = Contract::get_by_uuid(&db, contract_uuid)
+            .await
+            .unwrap()
+            .contract_number;
+        delete_user(&db, user_id).await;
+
+        let (file_contract_number, pdf) = rendered.unwrap();
+        assert_eq!(Some(file_contract_number), contract_number);
+        assert!(pdf.len() > 4);
+        assert!(pdf.starts_with(b"%PDF"));
+        assert!(matches!(
+            ApiError::from(missing.unwrap_err()),
+            ApiError::NotFound(_)
+        ));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn book_import_links_contracts_to_their_customers() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let hmac_secret = Token::generate_token().into_bytes();
+        let (user_id, user_uuid, full_name) = insert_user(&db, "Agent").await;
+        let customer = |name: &str, phone_number: &str| Customer {
+            full_name: Some(name.to_string()),
+            phone_number: Some(phone_number.to_string()),
+            created_by: Some(full_name.clone()),
+            ..Default::default()
+        };
+        let book = vec![
+            (
+                customer("Első Ügyfél", "+36301111111"),
+                vec![new_contract(&full_name), new_contract(&full_name)],
+            ),
+            (
+                customer("Második Ügyfél", "+36302222222"),
+                vec![new_contract(&full_name)],
+            ),
+        ];
+
+        let summary =
+            Contract::import_book(&db, &key, &hmac_secret, user_id, user_uuid, book).await;
+        let links = sqlx::query!(
+            "SELECT cc.uuid AS contract_uuid, c.uuid AS customer_uuid
+             FROM customer_contracts cc
+             JOIN customers c ON c.id = cc.customer_id
+             WHERE cc.user_id = $1",
+            user_id
+        )
+        .fetch_all(&db.pool)
+        .await
+        .unwrap();
+        delete_user(&db, user_id).await;
+
+        let summary = summary.unwrap();
+        assert_eq!(
+            (
+                summary.customers_created,
+                summary.customers_reused,
+                summary.customers_failed
+            ),
+            (2, 0, 0)
+        );
+        assert_eq!(
+            (summary.contracts_created, summary.contracts_failed),
+            (3, 0)
+        );
+        assert_eq!(links.len(), 3);
+        for (item, contract_count) in summary.items.iter().zip([2, 1]) {
+            assert!(item.customer_created && item.error.is_none());
+            assert_eq!(item.contracts.len(), contract_count);
+            for contract in &item.contracts {
+                let link = links
+                    .iter()
+                    .find(|link| link.contract_uuid == contract.uuid)
+                    .unwrap();
+                assert_eq!(link.customer_uuid, item.customer_uuid);
+            }
+        }
+    }
 }
@@ -1,27 +1,140 @@
 use anyhow::{Ok, Result, anyhow};
 use chacha20poly1305::Key;
-use serde::Serialize;
+use chrono::{DateTime, Utc};
+use futures_util::try_join;
+use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
-use sqlx::types::Uuid;
+use sqlx::{Connection, prelude::Type, types::Uuid};
+use strum::{AsRefStr, Display, EnumString};
 
 use crate::{
     database::Database,
-    models::user::User,
-    utils::encrypt::{self, HmacSecret},
+    models::{
+        audit_log::{AuditAction, AuditLog},
+        contract::Contract,
+        dto::{
+            AccessLogEntryDto, CustomerLookupDto, DedupCandidateDto, DedupCheckDto,
+            DormantCustomerDto, SearchHitDto,
+        },
+        intervention_task::{InterventionTask, InterventionTaskStatus},
+        lead::Lead,
+        user::{ListingScope, User, UserRole},
+    },
+    utils::{
+        contact,
+        encrypt::{self, DecryptFailurePolicy, HmacSecret, RowDecryptor},
+        error::ApiError,
+        pagination::PaginationQuery,
+        settings, validation,
+    },
 };
 
+const RECENT_LEADS_LIMIT: i64 = 5;
+
 #[skip_serializing_none]
 #[derive(Debug, Serialize, Default, Clone)]
 pub struct Customer {
     pub id: Option<i32>,
     pub uuid: Option<Uuid>,
     pub full_name: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
     pub phone_number: Option<String>,
     pub email: Option<String>,
     pub address: Option<String>,
     pub comment: Option<String>,
     pub user_id: Option<i32>,
     pub created_by: Option<String>,
+    pub flag: Option<CustomerFlag>,
+    pub decrypt_ok: Option<bool>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, EnumString, Display, Type, AsRefStr)]
+pub enum CustomerFlag {
+    Vip,
+    DoNotContact,
+    Prospect,
+    Inactive,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DedupStrategy {
+    #[default]
+    Strict,
+    Fuzzy,
+}
+
+impl DedupStrategy {
+    pub fn from_env() -> Self {
+        if settings::get("CUSTOMER_DEDUP_STRATEGY")
+            .is_some_and(|value| value.eq_ignore_ascii_case("fuzzy"))
+        {
+            DedupStrategy::Fuzzy
+        } else {
+            DedupStrategy::Strict
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CustomerSort {
+    #[default]
+    FullName,
+    LastName,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct CustomerFilter {
+    pub flag: Option<CustomerFlag>,
+    #[serde(default)]
+    pub scope: ListingScope,
+    #[serde(default)]
+    pub sort: CustomerSort,
+    pub last_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub enum DedupMatch {
+    Email,
+    PhoneNumber,
+    NameAndCity,
+}
+
+/// Takes the locality part of a "1051 Budapest, Nádor u. 5." style address.
+fn city_of(address: &str) -> Option<String> {
+    let locality = address.split(',').next()?;
+    let city = locality
+        .trim()
+        .trim_matches(|c: char| c.is_ascii_digit() || c.is_whitespace());
+
+    (!city.is_empty()).then(|| city.to_string())
+}
+
+/// Names are written family name first, so the first word is taken as the last name.
+/// Matches the split the migration did for existing rows.
+fn split_full_name(full_name: &str) -> (Option<String>, Option<String>) {
+    match full_name.trim().split_once(char::is_whitespace) {
+        Some((last_name, first_name)) => (
+            Some(first_name.trim().to_string()),
+            Some(last_name.to_string()),
+        ),
+        None => (None, Some(full_name.trim().to_string())),
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// Blank contact values get no hash, so they never match each other.
+fn hash_present(hmac_secret: &HmacSecret, value: &Option<String>) -> Option<Vec<u8>> {
+    non_empty(value).map(|value| encrypt::hash_value(hmac_secret, &value))
 }
 
 impl Customer {
@@ -41,6 +154,35 @@ impl Customer {
         Ok(user.uuid)
     }
 
+    /// Only a Leader may override DoNotContact, an anonymous intake (`actor_id` of `None`)
+    /// never can.
+    pub(super) async fn ensure_contactable(
+        conn: &mut sqlx::PgConnection,
+        db: &Database,
+        customer_id: i32,
+        actor_id: Option<i32>,
+    ) -> Result<()> {
+        let flag = sqlx::query_scalar!("SELECT flag FROM customers WHERE id = $1", customer_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .flatten();
+
+        if flag.as_deref() != Some(CustomerFlag::DoNotContact.as_ref()) {
+            return Ok(());
+        }
+        let is_leader = match actor_id {
+            Some(actor_id) => User::get_role(db, actor_id).await? >= UserRole::Leader,
+            None => false,
+        };
+        if !is_leader {
+            return Err(
+                ApiError::Forbidden("Az ügyfél letiltotta a megkeresést".to_string()).into(),
+            );
+        }
+
+        Ok(())
+    }
+
     pub(super) async fn is_exists(
         db: &Database,
         hmac_secret: &HmacSecret,
@@ -49,8 +191,8 @@ impl Customer {
         let is_exists = sqlx::query!(
             "SELECT id FROM customers
              WHERE email_hash = $1 OR phone_number_hash = $2",
-            encrypt::hash_value(hmac_secret, &customer.email.as_ref().unwrap()),
-            encrypt::hash_value(hmac_secret, &customer.phone_number.as_ref().unwrap()),
+            hash_present(hmac_secret, &customer.email),
+            hash_present(hmac_secret, &customer.phone_number),
         )
         .fetch_optional(&db.pool)
         .await?;
@@ -72,6 +214,38 @@ impl Customer {
 }
 
 impl Customer {
+    /// Returns the display name with its first and last name parts. Given parts win, the
+    /// display name is composed from them and a missing part is taken from `full_name`.
+    /// Without parts they are derived from `full_name`.
+    fn structured_name(&self) -> Result<(String, Option<String>, Option<String>)> {
+        let full_name = non_empty(&self.full_name);
+        let (derived_first_name, derived_last_name) = full_name
+            .as_deref()
+            .map(split_full_name)
+            .unwrap_or_default();
+        let first_name = non_empty(&self.first_name);
+        let last_name = non_empty(&self.last_name);
+
+        if first_name.is_none() && last_name.is_none() {
+            return match full_name {
+                Some(full_name) => Ok((full_name, derived_first_name, derived_last_name)),
+                None => Err(ApiError::Validation(
+                    "Az ügyfél nevének megadása kötelező!".to_string(),
+                )
+                .into()),
+            };
+        }
+
+        let first_name = first_name.or(derived_first_name);
+        let last_name = last_name.or(derived_last_name);
+        let full_name = [last_name.as_deref(), first_name.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok((full_name, first_name, last_name))
+    }
+
     pub async fn create(
         db: &Database,
         key: &Key,
@@ -87,22 +261,37 @@ impl Customer {
             .await?
             .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
 
-        let email = new_customer.email.as_deref().unwrap();
-        let phone = new_customer.phone_number.as_deref().unwrap();
-        let address = new_customer.address.as_deref().unwrap();
+        let mut conn = db.pool.acquire().await?;
+        Self::insert_in(&mut conn, key, hmac_secret, user_id, &new_customer).await
+    }
+
+    async fn insert_in(
+        conn: &mut sqlx::PgConnection,
+        key: &Key,
+        hmac_secret: &HmacSecret,
+        user_id: i32,
+        new_customer: &Customer,
+    ) -> Result<i32> {
+        let email = new_customer.email.as_deref().unwrap_or_default();
+        let phone = new_customer.phone_number.as_deref().unwrap_or_default();
+        let address = new_customer.address.as_deref().unwrap_or_default();
 
-        let email_hash = encrypt::hash_value(&hmac_secret, email);
-        let phone_hash = encrypt::hash_value(&hmac_secret, phone);
+        let email_hash = hash_present(hmac_secret, &new_customer.email);
+        let phone_hash = hash_present(hmac_secret, &new_customer.phone_number);
 
-        let (email_enc, email_nonce) = encrypt::encrypt_value(&key, email);
-        let (phone_enc, phone_nonce) = encrypt::encrypt_value(&key, phone);
-        let (address_enc, address_nonce) = encrypt::encrypt_value(&key, address);
+        let (email_enc, email_nonce) = encrypt::encrypt_value(key, email);
+        let (phone_enc, phone_nonce) = encrypt::encrypt_value(key, phone);
+        let (address_enc, address_nonce) = encrypt::encrypt_value(key, address);
+
+        let (full_name, first_name, last_name) = new_customer.structured_name()?;
+        let created_by =
+            User::normalize_created_by(conn, new_customer.created_by.as_deref()).await?;
 
         let row = sqlx::query!(
-            "INSERT INTO customers(full_name, phone_number_enc, phone_number_nonce, phone_number_hash, email_enc, email_nonce, email_hash, address_enc, address_nonce, user_id, created_by)
-             VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            "INSERT INTO customers(full_name, phone_number_enc, phone_number_nonce, phone_number_hash, email_enc, email_nonce, email_hash, address_enc, address_nonce, user_id, created_by, first_name, last_name)
+             VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
              RETURNING id",
-            new_customer.full_name,
+            full_name,
             phone_enc,
             phone_nonce,
             phone_hash,
@@ -112,14 +301,88 @@ impl Customer {
             address_enc,
             address_nonce,
             user_id,
-            new_customer.created_by
+            created_by,
+            first_name,
+            last_name
         )
-        .fetch_one(&db.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         Ok(row.id)
     }
 
+    async fn find_id_by_hash(
+        conn: &mut sqlx::PgConnection,
+        hmac_secret: &HmacSecret,
+        customer: &Customer,
+    ) -> Result<Option<i32>> {
+        let email_hash = hash_present(hmac_secret, &customer.email);
+        let phone_hash = hash_present(hmac_secret, &customer.phone_number);
+        if email_hash.is_none() && phone_hash.is_none() {
+            return Ok(None);
+        }
+
+        // A missing value binds NULL, which leaves its predicate out of the match
+        let id = sqlx::query_scalar!(
+            "SELECT id FROM customers
+             WHERE email_hash = $1 OR phone_number_hash = $2
+             LIMIT 1",
+            email_hash,
+            phone_hash
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// `create_or_get` on a caller provided connection, so it can be part of a transaction.
+    pub(super) async fn create_or_get_in(
+        conn: &mut sqlx::PgConnection,
+        key: &Key,
+        hmac_secret: &HmacSecret,
+        user_id: i32,
+        customer: &Customer,
+    ) -> Result<(i32, bool)> {
+        if let Some(id) = Self::find_id_by_hash(conn, hmac_secret, customer).await? {
+            return Ok((id, false));
+        }
+
+        // The insert gets its own savepoint, so a lost race doesn't abort the caller's transaction
+        let mut savepoint = conn.begin().await?;
+        match Self::insert_in(&mut savepoint, key, hmac_secret, user_id, customer).await {
+            Result::Ok(id) => {
+                savepoint.commit().await?;
+                Ok((id, true))
+            }
+            // A concurrent request may have inserted the same customer in the meantime
+            Err(e) => {
+                savepoint.rollback().await?;
+                match Self::find_id_by_hash(conn, hmac_secret, customer).await? {
+                    Some(id) => Ok((id, false)),
+                    None => Err(e),
+                }
+            }
+        }
+    }
+
+    /// Reuses the customer matching the email or phone number, creating it otherwise.
+    /// The flag tells whether a new customer row was inserted.
+    pub async fn create_or_get(
+        db: &Database,
+        key: &Key,
+        hmac_secret: &HmacSecret,
+        user_uuid: Uuid,
+        customer: Customer,
+    ) -> Result<(i32, bool)> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let mut conn = db.pool.acquire().await?;
+        Self::create_or_get_in(&mut conn, key, hmac_secret, user_id, &customer).await
+    }
+
     pub async fn modify(
         db: &Database,
         key: &Key,
@@ -131,14 +394,15 @@ impl Customer {
         let phone = updated_customer.phone_number.as_deref().unwrap_or_default();
         let address = updated_customer.address.as_deref().unwrap_or_default();
 
-        let email_hash = encrypt::hash_value(hmac_secret, email);
-        let phone_hash = encrypt::hash_value(hmac_secret, phone);
+        let email_hash = hash_present(hmac_secret, &updated_customer.email);
+        let phone_hash = hash_present(hmac_secret, &updated_customer.phone_number);
 
         let (email_enc, email_nonce) = encrypt::encrypt_value(key, email);
         let (phone_enc, phone_nonce) = encrypt::encrypt_value(key, phone);
         let (address_enc, address_nonce) = encrypt::encrypt_value(key, address);
+        let (full_name, first_name, last_name) = updated_customer.structured_name()?;
 
-        sqlx::query!(
+        let result = sqlx::query!(
             "UPDATE customers
              SET full_name = $1,
                  phone_number_enc = $2,
@@ -148,9 +412,11 @@ impl Customer {
                  email_nonce = $6,
                  email_hash = $7,
                  address_enc = $8,
-                 address_nonce = $9
+                 address_nonce = $9,
+                 first_name = $11,
+                 last_name = $12
              WHERE uuid = $10",
-            updated_customer.full_name,
+            full_name,
             phone_enc,
             phone_nonce,
             phone_hash,
@@ -159,16 +425,22 @@ impl Customer {
             email_hash,
             address_enc,
             address_nonce,
-            customer_uuid
+            customer_uuid,
+            first_name,
+            last_name
         )
         .execute(&db.pool)
         .await?;
 
+        if result.rows_affected() == 0 {
+            return Err(ApiError::NotFound("Ügyfél nem található!".to_string()).into());
+        }
+
         Ok(())
     }
 
     pub async fn save_comment(db: &Database, customer_uuid: Uuid, comment: String) -> Result<()> {
-        sqlx::query!(
+        let result = sqlx::query!(
             "UPDATE customers
              SET comment = $1
              WHERE uuid = $2",
@@ -178,21 +450,72 @@ impl Customer {
         .execute(&db.pool)
         .await?;
 
+        if result.rows_affected() == 0 {
+            return Err(ApiError::NotFound("Ügyfél nem található!".to_string()).into());
+        }
+
         Ok(())
     }
 
-    pub async fn get_by_uuid(db: &Database, key: &Key, customer_uuid: Uuid) -> Result<Self> {
+    pub async fn get_owner_uuid(db: &Database, customer_uuid: Uuid) -> Result<Uuid> {
+        sqlx::query_scalar!(
+            "SELECT u.uuid
+             FROM customers c
+             JOIN users u ON u.id = c.user_id
+             WHERE c.uuid = $1",
+            customer_uuid
+        )
+        .fetch_optional(&db.pool)
+        .await?
+        .flatten()
+        .ok_or_else(|| ApiError::NotFound("Ügyfél nem található!".to_string()).into())
+    }
+
+    pub async fn set_flag(
+        db: &Database,
+        customer_uuid: Uuid,
+        flag: Option<CustomerFlag>,
+    ) -> Result<()> {
+        let result = sqlx::query!(
+            "UPDATE customers
+             SET flag = $1
+             WHERE uuid = $2",
+            flag.map(|f| f.to_string()),
+            customer_uuid
+        )
+        .execute(&db.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(ApiError::NotFound("Ügyfél nem található!".to_string()).into());
+        }
+
+        Ok(())
+    }
+
+    /// Every successful read of the decrypted customer is recorded in the audit log.
+    pub async fn get_by_uuid(
+        db: &Database,
+        key: &Key,
+        actor_id: i32,
+        customer_uuid: Uuid,
+    ) -> Result<Self> {
         let row = sqlx::query!(
-            "SELECT uuid, full_name, phone_number_enc, phone_number_nonce, email_enc, email_nonce, address_enc, address_nonce, comment, user_id
+            "SELECT uuid, full_name, first_name, last_name, phone_number_enc, phone_number_nonce, email_enc, email_nonce, address_enc, address_nonce, comment, user_id, flag, created_at, updated_at
              FROM customers
              WHERE uuid = $1",
              customer_uuid
         )
         .fetch_one(&db.pool)
         .await?;
+
+        AuditLog::record(db, actor_id, AuditAction::CustomerPiiRead, row.uuid, None).await?;
+
         Ok(Customer {
             uuid: row.uuid,
             full_name: Some(row.full_name),
+            first_name: row.first_name,
+            last_name: row.last_name,
             phone_number: encrypt::decrypt_value(
                 key,
                 &row.phone_number_enc,
@@ -202,44 +525,340 @@ impl Customer {
             address: encrypt::decrypt_value(key, &row.address_enc, &row.address_nonce),
             comment: Some(row.comment),
             user_id: row.user_id,
+            flag: row.flag.and_then(|f| f.parse().ok()),
+            created_at: Some(row.created_at),
+            updated_at: Some(row.updated_at),
             ..Default::default()
         })
     }
 
-    pub async fn get_all(db: &Database, key: &Key, user_uuid: Uuid) -> Result<Vec<Self>> {
+    /// Strict matching only reports exact email/phone hits; fuzzy matching also reports
+    /// customers with the same normalized name in the same city for the agent to confirm.
+    /// Only customers owned by `user_ids` are listed, a match outside them is just flagged.
+    pub async fn find_dedup_candidates(
+        db: &Database,
+        key: &Key,
+        hmac_secret: &HmacSecret,
+        user_ids: &[i32],
+        customer: &Customer,
+        strategy: DedupStrategy,
+    ) -> Result<DedupCheckDto> {
+        let hash = |value: &Option<String>| hash_present(hmac_secret, value);
+        let fuzzy = strategy == DedupStrategy::Fuzzy;
+
+        let rows = sqlx::query!(
+            r#"WITH input AS (
+                SELECT TRANSLATE(LOWER(REGEXP_REPLACE(TRIM($4::TEXT), '\s+', ' ', 'g')), 'áéíóöőúüű', 'aeiooouuu') AS name
+            ), normalized AS (
+                SELECT
+                    uuid,
+                    user_id,
+                    full_name,
+                    address_enc,
+                    address_nonce,
+                    email_hash,
+                    phone_number_hash,
+                    TRANSLATE(LOWER(REGEXP_REPLACE(TRIM(full_name), '\s+', ' ', 'g')), 'áéíóöőúüű', 'aeiooouuu') AS name
+                FROM customers
+            )
+            SELECT
+                n.uuid,
+                n.full_name,
+                n.address_enc,
+                n.address_nonce,
+                COALESCE(n.email_hash = $1, FALSE) AS "email_match!",
+                COALESCE(n.phone_number_hash = $2, FALSE) AS "phone_match!",
+                ($3 AND n.name = i.name) AS "name_match!",
+                n.user_id = ANY($5) AS "visible!"
+            FROM normalized n, input i
+            WHERE n.email_hash = $1 OR n.phone_number_hash = $2 OR ($3 AND n.name = i.name)
+            ORDER BY n.full_name
+            LIMIT $6"#,
+            hash(&customer.email),
+            hash(&customer.phone_number),
+            fuzzy,
+            customer.full_name.as_deref().unwrap_or_default(),
+            user_ids,
+            validation::decrypt_fetch_limit()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+        validation::ensure_decrypt_limit(rows.len())?;
+
+        let wanted_city = customer
+            .address
+            .as_deref()
+            .and_then(city_of)
+            .map(|city| validation::fold_accents(&city));
+
+        let mut exists_elsewhere = false;
+        let candidates = rows
+            .into_iter()
+            .filter_map(|row| {
+                let city = encrypt::decrypt_value(key, &row.address_enc, &row.address_nonce)
+                    .as_deref()
+                    .and_then(city_of);
+
+                let mut matched_on = Vec::new();
+                if row.email_match {
+                    matched_on.push(DedupMatch::Email);
+                }
+                if row.phone_match {
+                    matched_on.push(DedupMatch::PhoneNumber);
+                }
+                if row.name_match
+                    && wanted_city.is_some()
+                    && city.as_deref().map(validation::fold_accents) == wanted_city
+                {
+                    matched_on.push(DedupMatch::NameAndCity);
+                }
+
+                if matched_on.is_empty() {
+                    return None;
+                }
+                if !row.visible {
+                    exists_elsewhere = true;
+                    return None;
+                }
+
+                Some(DedupCandidateDto {
+                    uuid: row.uuid,
+                    full_name: row.full_name,
+                    city,
+                    matched_on,
+                })
+            })
+            .collect();
+
+        Ok(DedupCheckDto {
+            candidates,
+            exists_elsewhere,
+        })
+    }
+
+    /// Exact phone number match through its hash, restricted to the given owners.
+    pub async fn find_uuid_by_phone(
+        db: &Database,
+        hmac_secret: &HmacSecret,
+        user_ids: &[i32],
+        phone_number: &str,
+    ) -> Result<Option<Uuid>> {
+        let row = sqlx::query!(
+            r#"SELECT uuid AS "uuid!"
+             FROM customers
+             WHERE user_id = ANY($1) AND phone_number_hash = $2
+             ORDER BY updated_at DESC
+             LIMIT 1"#,
+            user_ids,
+            encrypt::hash_value(hmac_secret, phone_number)
+        )
+        .fetch_optional(&db.pool)
+        .await?;
+
+        Ok(row.map(|row| row.uuid))
+    }
+
+    /// The customer behind a phone number together with its contracts, open tasks
+    /// and latest leads. Only customers owned by someone the caller has authority
+    /// over can be found.
+    pub async fn lookup_by_phone(
+        db: &Database,
+        key: &Key,
+        hmac_secret: &HmacSecret,
+        user_id: i32,
+        actor_id: i32,
+        phone_number: &str,
+    ) -> Result<CustomerLookupDto> {
+        let user_ids = User::get_authority_ids(db, user_id).await?;
+        let customer_uuid = Self::find_uuid_by_phone(db, hmac_secret, &user_ids, phone_number)
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Ügyfél nem található!".to_string()))?;
+
+        let recent_leads = PaginationQuery {
+            limit: Some(RECENT_LEADS_LIMIT),
+            ..Default::default()
+        };
+        let (customer, contracts, tasks, leads) = try_join!(
+            Self::get_by_uuid(db, key, actor_id, customer_uuid),
+            Contract::get_by_customer_uuid(db, customer_uuid),
+            InterventionTask::get_by_customer_uuid(db, customer_uuid),
+            Lead::get_by_customer_uuid_paginated(db, customer_uuid, recent_leads),
+        )?;
+
+        Ok(CustomerLookupDto {
+            customer,
+            contracts,
+            open_tasks: tasks
+                .into_iter()
+                .filter(|task| {
+                    !matches!(
+                        task.status,
+                        Some(InterventionTaskStatus::Processed)
+                            | Some(InterventionTaskStatus::PendingDeletion)
+                    )
+                })
+                .collect(),
+            recent_leads: leads.items,
+        })
+    }
+
+    /// Name fragment, or an exact phone number / email matched through its hash.
+    pub async fn search_brief(
+        db: &Database,
+        hmac_secret: &HmacSecret,
+        user_ids: &[i32],
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<SearchHitDto>> {
+        let rows = sqlx::query!(
+            r#"SELECT uuid AS "uuid!", full_name
+             FROM customers
+             WHERE user_id = ANY($1)
+               AND (full_name ILIKE '%' || $2 || '%' OR phone_number_hash = $3 OR email_hash = $3)
+             ORDER BY full_name
+             LIMIT $4"#,
+            user_ids,
+            validation::escape_like(query),
+            encrypt::hash_value(hmac_secret, &contact::canonical(query)),
+            limit
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SearchHitDto {
+                uuid: row.uuid,
+                label: row.full_name,
+                detail: None,
+            })
+            .collect())
+    }
+
+    pub async fn get_access_log(
+        db: &Database,
+        customer_uuid: Uuid,
+    ) -> Result<Vec<AccessLogEntryDto>> {
+        if Self::get_id_by_uuid(db, Some(customer_uuid))
+            .await?
+            .is_none()
+        {
+            return Err(ApiError::NotFound("Ügyfél nem található!".to_string()).into());
+        }
+
+        AuditLog::get_access_log(db, customer_uuid, AuditAction::CustomerPiiRead).await
+    }
+
+    /// Last activity is the newest lead, contract, intervention task update or meeting
+    /// (matched by phone number); customers without any activity count as dormant too.
+    pub async fn get_dormant(
+        db: &Database,
+        key: &Key,
+        actor_id: i32,
+        user_uuid: Uuid,
+        inactive_days: i32,
+    ) -> Result<Vec<DormantCustomerDto>> {
         let user_id = User::get_id_by_uuid(db, Some(user_uuid))
             .await?
             .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let rows = sqlx::query!(
+            r#"SELECT uuid, full_name, phone_number_enc, phone_number_nonce, email_enc, email_nonce, last_activity_at
+             FROM (
+                SELECT c.uuid, c.full_name, c.phone_number_enc, c.phone_number_nonce, c.email_enc, c.email_nonce,
+                    GREATEST(
+                        (SELECT MAX(l.handle_at) FROM customer_leads l WHERE l.customer_id = c.id),
+                        (SELECT MAX(cc.handle_at) FROM customer_contracts cc WHERE cc.customer_id = c.id),
+                        (SELECT MAX(it.updated_at) FROM customer_intervention_tasks it WHERE it.customer_id = c.id),
+                        (SELECT MAX(ud.meet_date) AT TIME ZONE 'UTC' FROM user_dates ud
+                            WHERE ud.user_id = c.user_id AND ud.phone_number_hash = c.phone_number_hash)
+                    ) AS last_activity_at
+                FROM customers c
+                WHERE c.user_id = $1
+             ) activity
+             WHERE last_activity_at IS NULL OR last_activity_at < NOW() - make_interval(days => $2)
+             ORDER BY last_activity_at NULLS FIRST, full_name
+             LIMIT $3"#,
+            user_id,
+            inactive_days,
+            validation::decrypt_fetch_limit()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+        validation::ensure_decrypt_limit(rows.len())?;
+        let customer_uuids: Vec<Uuid> = rows.iter().filter_map(|row| row.uuid).collect();
+        AuditLog::record_pii_reads(db, actor_id, &customer_uuids, "dormant_customers").await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| DormantCustomerDto {
+                uuid: row.uuid,
+                full_name: row.full_name,
+                phone_number: encrypt::decrypt_value(
+                    key,
+                    &row.phone_number_enc,
+                    &row.phone_number_nonce,
+                )
+                .unwrap_or_default(),
+                email: encrypt::decrypt_value(key, &row.email_enc, &row.email_nonce)
+                    .unwrap_or_default(),
+                last_activity_at: row.last_activity_at,
+            })
+            .collect())
+    }
+
+    pub async fn get_all(
+        db: &Database,
+        key: &Key,
+        actor_id: i32,
+        user_uuid: Uuid,
+        filter: &CustomerFilter,
+    ) -> Result<Vec<Self>> {
+        let user_ids = User::get_scoped_ids(db, user_uuid, filter.scope).await?;
         let row = sqlx::query!(
-            "SELECT uuid, full_name, phone_number_enc, phone_number_nonce, email_enc, email_nonce, address_enc, address_nonce, user_id, created_by
+            "SELECT uuid, full_name, first_name, last_name, phone_number_enc, phone_number_nonce, email_enc, email_nonce, address_enc, address_nonce, user_id, created_by, flag
              FROM customers
-             WHERE user_id = $1",
-            user_id
+             WHERE user_id = ANY($1)
+               AND ($2::TEXT IS NULL OR flag = $2)
+               AND ($3::TEXT IS NULL OR last_name ILIKE $3 || '%')
+             ORDER BY CASE WHEN $4 THEN COALESCE(last_name, full_name) END, full_name
+             LIMIT $5",
+            &user_ids,
+            filter.flag.as_ref().map(|f| f.to_string()),
+            filter.last_name.as_deref().map(validation::escape_like),
+            filter.sort == CustomerSort::LastName,
+            validation::decrypt_fetch_limit()
         )
         .fetch_all(&db.pool)
         .await?;
+        validation::ensure_decrypt_limit(row.len())?;
 
         let customers: Vec<Customer> = row
             .into_iter()
-            .map(|customer| Customer {
-                uuid: customer.uuid,
-                full_name: Some(customer.full_name),
-                phone_number: encrypt::decrypt_value(
-                    key,
-                    &customer.phone_number_enc,
-                    &customer.phone_number_nonce,
-                ),
-                email: encrypt::decrypt_value(key, &customer.email_enc, &customer.email_nonce),
-                address: encrypt::decrypt_value(
-                    key,
-                    &customer.address_enc,
-                    &customer.address_nonce,
-                ),
-                user_id: customer.user_id,
-                created_by: Some(customer.created_by),
-                ..Default::default()
+            .map(|customer| {
+                let mut decryptor = RowDecryptor::new(key);
+                Customer {
+                    uuid: customer.uuid,
+                    full_name: Some(customer.full_name),
+                    first_name: customer.first_name,
+                    last_name: customer.last_name,
+                    phone_number: decryptor
+                        .decrypt(&customer.phone_number_enc, &customer.phone_number_nonce),
+                    email: decryptor.decrypt(&customer.email_enc, &customer.email_nonce),
+                    address: decryptor.decrypt(&customer.address_enc, &customer.address_nonce),
+                    user_id: customer.user_id,
+                    created_by: Some(customer.created_by),
+                    flag: customer.flag.and_then(|f| f.parse().ok()),
+                    decrypt_ok: Some(decryptor.ok),
+                    ..Default::default()
+                }
             })
             .collect();
+        let customers = DecryptFailurePolicy::from_env()
+            .apply(customers, |customer| customer.decrypt_ok == Some(true))?;
+
+        let customer_uuids: Vec<Uuid> = customers.iter().filter_map(|c| c.uuid).collect();
+        AuditLog::record_pii_reads(db, actor_id, &customer_uuids, "customer_list").await?;
         Ok(customers)
     }
 
@@ -267,6 +886,87 @@ impl Customer {
         Ok(())
     }
 
+    pub async fn reassign_with_related(
+        db: &Database,
+        customer_uuids: Vec<Uuid>,
+        target_user_uuid: Uuid,
+    ) -> Result<()> {
+        let user_id = User::get_id_by_uuid(db, Some(target_user_uuid))
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Felhasználó nem található!".to_string()))?;
+
+        let mut tx = db.pool.begin().await?;
+        Self::move_with_related(&mut tx, &customer_uuids, user_id).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Moves the customers together with their contracts, leads, tasks and meetings, so
+    /// a customer and everything about them always share one owner. Returns the number
+    /// of customers moved.
+    pub(super) async fn move_with_related(
+        conn: &mut sqlx::PgConnection,
+        customer_uuids: &[Uuid],
+        user_id: i32,
+    ) -> Result<u64> {
+        // Meetings are linked by phone number only, so move the ones booked by the current handler
+        sqlx::query!(
+            "UPDATE user_dates d
+             SET user_id = $2
+             FROM customers c
+             WHERE c.uuid = ANY($1)
+               AND d.phone_number_hash = c.phone_number_hash
+               AND d.user_id = c.user_id",
+            customer_uuids,
+            user_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE customer_contracts
+             SET user_id = $2
+             WHERE customer_id IN (SELECT id FROM customers WHERE uuid = ANY($1))",
+            customer_uuids,
+            user_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE customer_leads
+             SET user_id = $2
+             WHERE customer_id IN (SELECT id FROM customers WHERE uuid = ANY($1))",
+            customer_uuids,
+            user_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE customer_intervention_tasks
+             SET user_id = $2
+             WHERE customer_id IN (SELECT id FROM customers WHERE uuid = ANY($1))",
+            customer_uuids,
+            user_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        let moved = sqlx::query!(
+            "UPDATE customers
+             SET user_id = $2
+             WHERE uuid = ANY($1)",
+            customer_uuids,
+            user_id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+
+        Ok(moved)
+    }
+
     pub async fn delete(db: &Database, customer_ids: Vec<Uuid>) -> Result<()> {
         for customer_uuid in customer_ids {
             let customer_id = Self::get_id_by_uuid(db, Some(customer_uuid))
@@ -288,3 +988,305 @@ impl Customer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::redis::Token;
+
+    fn named(full_name: &str, first_name: Option<&str>, last_name: Option<&str>) -> Customer {
+        Customer {
+            full_name: Some(full_name.to_string()),
+            first_name: first_name.map(str::to_string),
+            last_name: last_name.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn some(first_name: &str, last_name: &str) -> (Option<String>, Option<String>) {
+        (Some(first_name.to_string()), Some(last_name.to_string()))
+    }
+
+    async fn insert_user(db: &Database) -> (i32, String) {
+        let full_name = format!("Teszt Rögzítő {}", Token::generate_token());
+        let username = Token::generate_token();
+        let user_id = sqlx::query_scalar!(
+            "INSERT INTO users(email, username, password, user_role)
+             VALUES($1, $2, '', 'Agent') RETURNING id",
+            format!("{username}@example.com"),
+            username
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "INSERT INTO user_info(user_id, full_name, phone_number) VALUES($1, $2, $3)",
+            user_id,
+            full_name,
+            username
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        (user_id, full_name)
+    }
+
+    async fn delete_user(db: &Database, user_id: i32) {
+        sqlx::query!("DELETE FROM customers WHERE user_id = $1", user_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn full_names_split_at_the_family_name() {
+        assert_eq!(split_full_name("Kiss Anna"), some("Anna", "Kiss"));
+        assert_eq!(
+            split_full_name("  Kiss  Anna Mária "),
+            some("Anna Mária", "Kiss")
+        );
+        assert_eq!(split_full_name("Kiss"), (None, Some("Kiss".to_string())));
+    }
+
+    #[test]
+    fn name_parts_win_over_the_full_name() {
+        let name = |customer: Customer| customer.structured_name().unwrap();
+
+        assert_eq!(
+            name(named("Kiss Anna", None, None)),
+            (
+                "Kiss Anna".to_string(),
+                Some("Anna".to_string()),
+                Some("Kiss".to_string())
+            )
+        );
+        assert_eq!(
+            name(named("Kiss Anna", Some("Éva"), Some("Nagy"))),
+            (
+                "Nagy Éva".to_string(),
+                Some("Éva".to_string()),
+                Some("Nagy".to_string())
+            )
+        );
+        assert_eq!(
+            name(named("Kiss Anna", None, Some("Nagy"))),
+            (
+                "Nagy Anna".to_string(),
+                Some("Anna".to_string()),
+                Some("Nagy".to_string())
+            )
+        );
+        assert_eq!(
+            name(named(" ", Some("Éva"), Some(" "))),
+            ("Éva".to_string(), Some("Éva".to_string()), None)
+        );
+        assert!(matches!(
+            named(" ", None, Some(" "))
+                .structured_name()
+                .unwrap_err()
+                .downcast_ref::<ApiError>(),
+            Some(ApiError::Validation(_))
+        ));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn modify_recomposes_the_full_name() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let hmac_secret = Token::generate_token().into_bytes();
+        let customer = sqlx::query!(
+            "INSERT INTO customers(full_name, first_name, last_name, phone_number_enc, phone_number_nonce, email_enc, email_nonce, address_enc, address_nonce, created_by)
+             VALUES('Kiss Anna', 'Anna', 'Kiss', '', '', '', '', '', '', 'Teszt')
+             RETURNING id, uuid"
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+
+        let modified = Customer::modify(
+            &db,
+            &key,
+            &hmac_secret,
+            customer.uuid.unwrap(),
+            named("Kiss Anna", None, Some("Nagy")),
+        )
+        .await;
+        let stored = sqlx::query!(
+            "SELECT full_name, first_name, last_name FROM customers WHERE id = $1",
+            customer.id
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query!("DELETE FROM customers WHERE id = $1", customer.id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        modified.unwrap();
+        assert_eq!(stored.full_name, "Nagy Anna");
+        assert_eq!(stored.first_name.as_deref(), Some("Anna"));
+        assert_eq!(stored.last_name.as_deref(), Some("Nagy"));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn blank_contacts_do_not_match_each_other() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        // A fresh secret keeps the hashes clear of existing rows
+        let hmac_secret = Token::generate_token().into_bytes();
+        let (user_id, full_name) = insert_user(&db).await;
+        let customer = |phone_number: &str| Customer {
+            full_name: Some("Teszt Ügyfél".to_string()),
+            phone_number: Some(phone_number.to_string()),
+            email: Some(String::new()),
+            created_by: Some(full_name.clone()),
+            ..Default::default()
+        };
+
+        let mut conn = db.pool.acquire().await.unwrap();
+        let first = Customer::create_or_get_in(
+            &mut conn,
+            &key,
+            &hmac_secret,
+            user_id,
+            &customer("+36301111111"),
+        )
+        .await
+        .unwrap();
+        let second = Customer::create_or_get_in(
+            &mut conn,
+            &key,
+            &hmac_secret,
+            user_id,
+            &customer("+36302222222"),
+        )
+        .await
+        .unwrap();
+        let again = Customer::create_or_get_in(
+            &mut conn,
+            &key,
+            &hmac_secret,
+            user_id,
+            &customer("+36301111111"),
+        )
+        .await
+        .unwrap();
+        delete_user(&db, user_id).await;
+
+        assert!(first.1 && second.1);
+        assert_ne!(first.0, second.0);
+        assert_eq!(again, (first.0, false));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn changes_to_a_missing_customer_are_not_found() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let hmac_secret = Token::generate_token().into_bytes();
+        let missing = Uuid::new_v4();
+
+        let comment = Customer::save_comment(&db, missing, "Visszahívni".to_string()).await;
+        let modify = Customer::modify(
+            &db,
+            &key,
+            &hmac_secret,
+            missing,
+            named("Teszt Ügyfél", None, None),
+        )
+        .await;
+
+        assert!(matches!(
+            ApiError::from(comment.unwrap_err()),
+            ApiError::NotFound(_)
+        ));
+        assert!(matches!(
+            ApiError::from(modify.unwrap_err()),
+            ApiError::NotFound(_)
+        ));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn phone_lookup_gathers_the_related_records() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let hmac_secret = Token::generate_token().into_bytes();
+        let (user_id, full_name) = insert_user(&db).await;
+        let customer = Customer {
+            full_name: Some("Teszt Ügyfél".to_string()),
+            phone_number: Some("+36301234567".to_string()),
+            created_by: Some(full_name),
+            ..Default::default()
+        };
+        let mut conn = db.pool.acquire().await.unwrap();
+        let (customer_id, _) =
+            Customer::create_or_get_in(&mut conn, &key, &hmac_secret, user_id, &customer)
+                .await
+                .unwrap();
+        sqlx::query!(
+            "INSERT INTO customer_contracts(contract_number, contract_type, annual_fee, payment_frequency, payment_method, customer_id, user_id, created_by)
+             VALUES($1, 'Kgfb', 10000, 'Annual', 'Transfer', $2, $3, 'Teszt')",
+            Token::generate_token(),
+            customer_id,
+            user_id
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        for status in ["Pending", "Processed"] {
+            sqlx::query!(
+                "INSERT INTO customer_intervention_tasks(contract_number, product_name, outstanding_days, balance, processing_deadline, status, customer_id, user_id, created_by)
+                 VALUES('T-1', 'Kgfb', 30, 10000, NOW(), $1, $2, $3, 'Teszt')",
+                status,
+                customer_id,
+                user_id
+            )
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        }
+        sqlx::query!(
+            "INSERT INTO customer_leads(lead_type, inquiry_type, lead_status, customer_id, user_id, created_by)
+             VALUES('Personal', 'Kgfb', 'Opened', $1, $2, 'Teszt')",
+            customer_id,
+            user_id
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+        let lookup =
+            Customer::lookup_by_phone(&db, &key, &hmac_secret, user_id, user_id, "+36301234567")
+                .await;
+        let unknown =
+            Customer::lookup_by_phone(&db, &key, &hmac_secret, user_id, user_id, "+36309999999")
+                .await;
+        delete_user(&db, user_id).await;
+
+        let lookup = lookup.unwrap();
+        assert_eq!(lookup.customer.full_name.as_deref(), Some("Teszt Ügyfél"));
+        assert_eq!(lookup.contracts.len(), 1);
+        assert_eq!(lookup.open_tasks.len(), 1);
+        assert!(matches!(
+            lookup.open_tasks[0].status,
+            Some(InterventionTaskStatus::Pending)
+        ));
+        assert_eq!(lookup.recent_leads.len(), 1);
+        assert!(matches!(
+            ApiError::from(unknown.err().unwrap()),
+            ApiError::NotFound(_)
+        ));
+    }
+}
@@ -1,9 +1,18 @@
-use chrono::{DateTime, NaiveDateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use serde::Serialize;
 use uuid::Uuid;
 
-use crate::models::contract::{ContractType, PaymentFrequency, PaymentMethod};
-use crate::models::intervention_task::InterventionTaskStatus;
+use crate::models::audit_log::AuditAction;
+use crate::models::contract::{
+    Contract, ContractStatus, ContractType, PaymentFrequency, PaymentMethod,
+};
+use crate::models::customer::{Customer, DedupMatch};
+use crate::models::intervention_task::{InterventionTask, InterventionTaskStatus};
+use crate::models::lead::Lead;
+use crate::models::user::UserRole;
+use crate::models::user_date::MeetType;
 
 #[derive(Serialize)]
 pub struct ManagerNameDto {
@@ -12,6 +21,29 @@ pub struct ManagerNameDto {
     pub user_role: String,
 }
 
+#[derive(Serialize)]
+pub struct AssignableUserDto {
+    pub uuid: Option<Uuid>,
+    pub full_name: String,
+    pub user_role: UserRole,
+}
+
+#[derive(Serialize)]
+pub struct SessionDto {
+    pub session_id: String,
+    pub device: Option<String>,
+    pub issued_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub current: bool,
+}
+
+#[derive(Serialize)]
+pub struct SessionTokensDto {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
 #[derive(Serialize)]
 pub struct LeadListItemDto {
     pub uuid: Option<Uuid>,
@@ -24,6 +56,8 @@ pub struct LeadListItemDto {
     pub lead_status: String,
     pub handle_at: DateTime<Utc>,
     pub created_by: String,
+    // False when an encrypted field couldn't be decrypted and was left blank
+    pub decrypt_ok: bool,
 }
 
 #[derive(Serialize)]
@@ -41,6 +75,8 @@ pub struct InterventionTaskDto {
     pub comment: Option<String>,
     pub status: InterventionTaskStatus,
     pub created_by: String,
+    // False when an encrypted field couldn't be decrypted and was left blank
+    pub decrypt_ok: bool,
 }
 
 #[derive(Serialize)]
@@ -56,8 +92,171 @@ pub struct ContractDto {
     pub first_payment: bool,
     pub payment_frequency: PaymentFrequency,
     pub payment_method: PaymentMethod,
+    pub status: ContractStatus,
     pub created_by: String,
     pub handle_at: DateTime<Utc>,
+    // False when an encrypted field couldn't be decrypted and was left blank
+    pub decrypt_ok: bool,
+}
+
+#[derive(Serialize)]
+pub struct CustomerSummaryDto {
+    pub uuid: Option<Uuid>,
+    pub full_name: String,
+    pub phone_number: String,
+    pub email: String,
+    pub address: String,
+}
+
+#[derive(Serialize)]
+pub struct ContractSummaryDto {
+    pub uuid: Option<Uuid>,
+    pub contract_number: String,
+    pub contract_type: ContractType,
+    pub annual_fee: i32,
+    pub first_payment: bool,
+    pub payment_frequency: PaymentFrequency,
+    pub payment_method: PaymentMethod,
+    pub status: ContractStatus,
+    pub handle_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct CustomerContractsDto {
+    pub customer: CustomerSummaryDto,
+    pub contracts: Vec<ContractSummaryDto>,
+}
+
+#[derive(Serialize)]
+pub struct DuplicateContractNumberDto {
+    pub contract_number: String,
+    pub count: i64,
+    pub uuids: Vec<Uuid>,
+}
+
+#[derive(Serialize)]
+pub struct BlockedDeleteDto {
+    pub uuid: Uuid,
+    pub reason: String,
+}
+
+#[derive(Serialize)]
+pub struct PartialDeleteDto {
+    pub deleted: Vec<Uuid>,
+    pub blocked: Vec<BlockedDeleteDto>,
+}
+
+#[derive(Serialize)]
+pub struct TeamMeetDateDto {
+    pub uuid: Option<Uuid>,
+    pub meet_date: NaiveDateTime,
+    pub full_name: String,
+    pub phone_number: String,
+    pub meet_location: String,
+    pub meet_type: MeetType,
+    pub is_completed: bool,
+    pub agent_uuid: Option<Uuid>,
+    pub agent_name: String,
+}
+
+#[derive(Serialize)]
+pub struct BulkMeetDateFailureDto {
+    pub index: usize,
+    pub meet_date: Option<NaiveDateTime>,
+    pub reason: String,
+}
+
+#[derive(Serialize)]
+pub struct BulkMeetDateSummaryDto {
+    pub created: Vec<Uuid>,
+    pub failed: Vec<BulkMeetDateFailureDto>,
+}
+
+#[derive(Serialize)]
+pub struct BookImportContractDto {
+    pub contract_number: String,
+    pub uuid: Option<Uuid>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BookImportCustomerDto {
+    pub index: usize,
+    pub full_name: String,
+    pub customer_uuid: Option<Uuid>,
+    pub customer_created: bool,
+    pub error: Option<String>,
+    pub contracts: Vec<BookImportContractDto>,
+}
+
+#[derive(Serialize)]
+pub struct BookImportSummaryDto {
+    pub customers_created: usize,
+    pub customers_reused: usize,
+    pub customers_failed: usize,
+    pub contracts_created: usize,
+    pub contracts_failed: usize,
+    pub items: Vec<BookImportCustomerDto>,
+}
+
+#[derive(Serialize)]
+pub struct DormantCustomerDto {
+    pub uuid: Option<Uuid>,
+    pub full_name: String,
+    pub phone_number: String,
+    pub email: String,
+    pub last_activity_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct DedupCandidateDto {
+    pub uuid: Option<Uuid>,
+    pub full_name: String,
+    pub city: Option<String>,
+    pub matched_on: Vec<DedupMatch>,
+}
+
+#[derive(Serialize)]
+pub struct DedupCheckDto {
+    pub candidates: Vec<DedupCandidateDto>,
+    // A match exists among customers the caller can't see, no details are given
+    pub exists_elsewhere: bool,
+}
+
+#[derive(Serialize)]
+pub struct AccessLogEntryDto {
+    pub actor_uuid: Option<Uuid>,
+    pub actor_name: Option<String>,
+    pub accessed_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct AuditEntryDto {
+    pub uuid: Option<Uuid>,
+    pub action: AuditAction,
+    pub actor_uuid: Option<Uuid>,
+    pub actor_name: Option<String>,
+    pub target_uuid: Option<Uuid>,
+    pub details: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct RenewalDto {
+    pub uuid: Option<Uuid>,
+    pub contract_number: String,
+    pub contract_type: ContractType,
+    pub annual_fee: i32,
+    pub full_name: String,
+    pub renewal_date: NaiveDate,
+}
+
+#[derive(Serialize)]
+pub struct AgentRenewalsDto {
+    pub agent_uuid: Option<Uuid>,
+    pub agent_name: String,
+    pub count: usize,
+    pub contracts: Vec<RenewalDto>,
 }
 
 // USER DATE CHART
@@ -86,6 +285,27 @@ pub struct DatesWeeklyChartDto {
     pub sunday: i64,
 }
 
+#[derive(Serialize)]
+pub struct DailyCountDto {
+    pub date: NaiveDate,
+    pub count: i64,
+}
+
+#[derive(Serialize)]
+pub struct ActivityDayDto {
+    pub date: NaiveDate,
+    pub customers: i64,
+    pub contracts: i64,
+    pub leads: i64,
+    pub meetings: i64,
+}
+
+#[derive(Serialize)]
+pub struct LocationCountDto {
+    pub location: String,
+    pub count: i64,
+}
+
 #[derive(Serialize)]
 pub struct DatesMonthlyChartDto {
     pub month: i16,
@@ -96,7 +316,41 @@ pub struct DatesMonthlyChartDto {
     pub week5: i64,
 }
 
+// LEADS CHART
+#[derive(Serialize)]
+pub struct InquiryBreakdownDto {
+    pub inquiry_type: String,
+    pub count: i64,
+}
+
+// INTERVENTION TASKS CHART
+#[derive(Serialize)]
+pub struct BalanceSummaryDto {
+    pub total_balance: i64,
+    pub by_status: HashMap<String, i64>,
+}
+
+// RECOMMENDATIONS
+#[derive(Serialize)]
+pub struct RecommendationCheckDto {
+    pub exists: bool,
+    pub recommendation: bool,
+    pub customer: bool,
+    pub lead: bool,
+}
+
+#[derive(Serialize)]
+pub struct ReferrerCountDto {
+    pub referral_name: String,
+    pub count: i64,
+}
+
 // CONTRACTS CHART
+// Contract count keyed by contract type
+pub type ContractTypeCountsDto = BTreeMap<String, i64>;
+
+// The shape the portfolio chart had before contract types became configurable, kept for
+// existing clients, types added since are only in `ContractTypeCountsDto`
 #[derive(Serialize)]
 pub struct PortfolioDto {
     pub bonus_life_program: i64,
@@ -132,4 +386,83 @@ pub struct MonthlyProductionChartDto {
     pub week3: i64,
     pub week4: i64,
     pub week5: i64,
-}
\ No newline at end of file
+}
+#[derive(Serialize)]
+pub struct ContractTypeTrendDto {
+    pub month: NaiveDate,
+    pub per_type: ContractTypeCountsDto,
+}
+#[derive(Serialize)]
+pub struct MonthlyCombinedChartDto {
+    pub month: i16,
+    pub value_weeks: [i64; 5],
+    pub count_weeks: [i64; 5],
+}
+
+#[derive(Serialize)]
+pub struct NewVsRenewalDto {
+    pub new_count: i64,
+    pub new_value: i64,
+    pub renewal_count: i64,
+    pub renewal_value: i64,
+}
+
+#[derive(Serialize)]
+pub struct CommissionTypeDto {
+    pub contract_type: String,
+    pub value: i64,
+    pub rate: f64,
+    pub commission: i64,
+    pub rate_missing: bool,
+}
+
+#[derive(Serialize)]
+pub struct CommissionEstimateDto {
+    pub per_type: Vec<CommissionTypeDto>,
+    pub total_commission: i64,
+}
+
+// DASHBOARD
+#[derive(Serialize)]
+pub struct MeetingConversionDto {
+    pub meetings: i64,
+    pub completed_meetings: i64,
+    pub contracts: i64,
+    pub meetings_per_contract: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct KpiDto {
+    pub production_value: i64,
+    pub production_count: i64,
+    pub this_month_value: i64,
+    pub last_month_value: i64,
+    pub month_over_month_delta: i64,
+    pub meeting_completion_rate: Option<f64>,
+    pub open_task_count: i64,
+}
+
+// SEARCH
+#[derive(Serialize)]
+pub struct SearchHitDto {
+    pub uuid: Uuid,
+    pub label: String,
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct GlobalSearchDto {
+    pub customers: Vec<SearchHitDto>,
+    pub contracts: Vec<SearchHitDto>,
+    pub leads: Vec<SearchHitDto>,
+    pub recommendations: Vec<SearchHitDto>,
+}
+
+// LOOKUP
+#[derive(Serialize)]
+pub struct CustomerLookupDto {
+    pub customer: Customer,
+    pub contracts: Vec<Contract>,
+    pub open_tasks: Vec<InterventionTask>,
+    pub recent_leads: Vec<Lead>,
+}
@@ -0,0 +1,372 @@
+use anyhow::Result;
+use chacha20poly1305::Key;
+use uuid::Uuid;
+
+use crate::{
+    database::Database,
+    utils::{
+        contact,
+        encrypt::{self, HmacSecret},
+    },
+};
+
+const REHASH_BATCH_SIZE: i64 = 500;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RehashCount {
+    pub updated: u64,
+    // Undecryptable rows and hashes not made with the old secret (e.g. already rotated)
+    pub skipped: u64,
+}
+
+struct Rotation<'a> {
+    key: &'a Key,
+    old_secret: &'a HmacSecret,
+    new_secret: &'a HmacSecret,
+}
+
+impl Rotation<'_> {
+    /// The new hash of a field, if its stored hash was made from the decrypted value
+    /// with the old secret, either as entered or in its canonical form. The new hash is
+    /// always of the canonical form.
+    fn rehash(&self, stored: Option<&[u8]>, ciphertext: &[u8], nonce: &[u8]) -> Option<Vec<u8>> {
+        let value = encrypt::decrypt_value(self.key, ciphertext, nonce)?;
+        let canonical = contact::canonical(&value);
+        let stored = stored?;
+        (stored == encrypt::hash_value(self.old_secret, &value)
+            || stored == encrypt::hash_value(self.old_secret, &canonical))
+        .then(|| encrypt::hash_value(self.new_secret, &canonical))
+    }
+}
+
+/// Recomputes every deterministic hash after `HMAC_SECRET` was rotated. Each batch is
+/// committed separately and already rotated rows are skipped, so an interrupted run can
+/// simply be started again. Run with an unchanged secret, it only brings contacts hashed
+/// as entered in line with their canonical form.
+pub async fn rehash_all(
+    db: &Database,
+    key: &Key,
+    old_secret: &HmacSecret,
+    new_secret: &HmacSecret,
+) -> Result<Vec<(&'static str, RehashCount)>> {
+    let rotation = Rotation {
+        key,
+        old_secret,
+        new_secret,
+    };
+
+    Ok(vec![
+        ("customers", rehash_customers(db, &rotation).await?),
+        ("user_dates", rehash_dates(db, &rotation).await?),
+        (
+            "customer_recommendations",
+            rehash_recommendations(db, &rotation).await?,
+        ),
+        ("recruitment", rehash_recruitment(db, &rotation).await?),
+    ])
+}
+
+async fn rehash_customers(db: &Database, rotation: &Rotation<'_>) -> Result<RehashCount> {
+    let mut count = RehashCount::default();
+    let mut last_id = 0;
+    loop {
+        let rows = sqlx::query!(
+            "SELECT id, phone_number_enc, phone_number_nonce, phone_number_hash, email_enc, email_nonce, email_hash
+             FROM customers
+             WHERE id > $1
+             ORDER BY id
+             LIMIT $2",
+            last_id,
+            REHASH_BATCH_SIZE
+        )
+        .fetch_all(&db.pool)
+        .await?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        last_id = last.id;
+
+        let mut tx = db.pool.begin().await?;
+        for row in rows {
+            let phone_hash = rotation.rehash(
+                row.phone_number_hash.as_deref(),
+                &row.phone_number_enc,
+                &row.phone_number_nonce,
+            );
+            let email_hash =
+                rotation.rehash(row.email_hash.as_deref(), &row.email_enc, &row.email_nonce);
+            if phone_hash.is_none() && email_hash.is_none() {
+                count.skipped += 1;
+                continue;
+            }
+
+            sqlx::query!(
+                "UPDATE customers
+                 SET phone_number_hash = COALESCE($1, phone_number_hash),
+                     email_hash = COALESCE($2, email_hash)
+                 WHERE id = $3",
+                phone_hash,
+                email_hash,
+                row.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            count.updated += 1;
+        }
+        tx.commit().await?;
+    }
+
+    Ok(count)
+}
+
+async fn rehash_dates(db: &Database, rotation: &Rotation<'_>) -> Result<RehashCount> {
+    let mut count = RehashCount::default();
+    let mut last_id = 0;
+    loop {
+        let rows = sqlx::query!(
+            "SELECT id, phone_number_enc, phone_number_nonce, phone_number_hash
+             FROM user_dates
+             WHERE id > $1
+             ORDER BY id
+             LIMIT $2",
+            last_id,
+            REHASH_BATCH_SIZE
+        )
+        .fetch_all(&db.pool)
+        .await?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        last_id = last.id;
+
+        let mut tx = db.pool.begin().await?;
+        for row in rows {
+            let Some(phone_hash) = rotation.rehash(
+                row.phone_number_hash.as_deref(),
+                &row.phone_number_enc,
+                &row.phone_number_nonce,
+            ) else {
+                count.skipped += 1;
+                continue;
+            };
+
+            sqlx::query!(
+                "UPDATE user_dates SET phone_number_hash = $1 WHERE id = $2",
+                phone_hash,
+                row.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            count.updated += 1;
+        }
+        tx.commit().await?;
+    }
+
+    Ok(count)
+}
+
+// No serial id on this table, batches are keyed by uuid instead
+async fn rehash_recommendations(db: &Database, rotation: &Rotation<'_>) -> Result<RehashCount> {
+    let mut count = RehashCount::default();
+    let mut last_uuid: Option<Uuid> = None;
+    loop {
+        let rows = sqlx::query!(
+            r#"SELECT uuid AS "uuid!", phone_number_enc, phone_number_nonce, phone_number_hash
+             FROM customer_recommendations
+             WHERE uuid IS NOT NULL AND ($1::UUID IS NULL OR uuid > $1)
+             ORDER BY uuid
+             LIMIT $2"#,
+            last_uuid,
+            REHASH_BATCH_SIZE
+        )
+        .fetch_all(&db.pool)
+        .await?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        last_uuid = Some(last.uuid);
+
+        let mut tx = db.pool.begin().await?;
+        for row in rows {
+            let Some(phone_hash) = rotation.rehash(
+                row.phone_number_hash.as_deref(),
+                &row.phone_number_enc,
+                &row.phone_number_nonce,
+            ) else {
+                count.skipped += 1;
+                continue;
+            };
+
+            sqlx::query!(
+                "UPDATE customer_recommendations SET phone_number_hash = $1 WHERE uuid = $2",
+                phone_hash,
+                row.uuid
+            )
+            .execute(&mut *tx)
+            .await?;
+            count.updated += 1;
+        }
+        tx.commit().await?;
+    }
+
+    Ok(count)
+}
+
+async fn rehash_recruitment(db: &Database, rotation: &Rotation<'_>) -> Result<RehashCount> {
+    let mut count = RehashCount::default();
+    let mut last_uuid: Option<Uuid> = None;
+    loop {
+        let rows = sqlx::query!(
+            r#"SELECT uuid AS "uuid!", phone_number_enc, phone_number_nonce, phone_number_hash, email_enc, email_nonce, email_hash
+             FROM recruitment
+             WHERE uuid IS NOT NULL AND ($1::UUID IS NULL OR uuid > $1)
+             ORDER BY uuid
+             LIMIT $2"#,
+            last_uuid,
+            REHASH_BATCH_SIZE
+        )
+        .fetch_all(&db.pool)
+        .await?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        last_uuid = Some(last.uuid);
+
+        let mut tx = db.pool.begin().await?;
+        for row in rows {
+            let phone_hash = rotation.rehash(
+                Some(&row.phone_number_hash),
+                &row.phone_number_enc,
+                &row.phone_number_nonce,
+            );
+            let email_hash =
+                rotation.rehash(Some(&row.email_hash), &row.email_enc, &row.email_nonce);
+            if phone_hash.is_none() && email_hash.is_none() {
+                count.skipped += 1;
+                continue;
+            }
+
+            sqlx::query!(
+                "UPDATE recruitment
+                 SET phone_number_hash = COALESCE($1, phone_number_hash),
+                     email_hash = COALESCE($2, email_hash)
+                 WHERE uuid = $3",
+                phone_hash,
+                email_hash,
+                row.uuid
+            )
+            .execute(&mut *tx)
+            .await?;
+            count.updated += 1;
+        }
+        tx.commit().await?;
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{models::customer::Customer, utils::redis::Token};
+
+    #[test]
+    fn only_hashes_made_with_the_old_secret_are_rotated() {
+        let key = Key::from([7u8; 32]);
+        let (old_secret, new_secret) = (b"old-secret".to_vec(), b"new-secret".to_vec());
+        let rotation = Rotation {
+            key: &key,
+            old_secret: &old_secret,
+            new_secret: &new_secret,
+        };
+        let (ciphertext, nonce) = encrypt::encrypt_value(&key, "+36 30 123 4567");
+        let rotated = Some(encrypt::hash_value(&new_secret, "+36301234567"));
+
+        let as_entered = encrypt::hash_value(&old_secret, "+36 30 123 4567");
+        assert_eq!(
+            rotation.rehash(Some(&as_entered), &ciphertext, &nonce),
+            rotated
+        );
+        let canonical = encrypt::hash_value(&old_secret, "+36301234567");
+        assert_eq!(
+            rotation.rehash(Some(&canonical), &ciphertext, &nonce),
+            rotated
+        );
+
+        let foreign = encrypt::hash_value(b"other-secret", "+36301234567");
+        assert_eq!(rotation.rehash(Some(&foreign), &ciphertext, &nonce), None);
+        assert_eq!(rotation.rehash(None, &ciphertext, &nonce), None);
+        assert_eq!(
+            rotation.rehash(Some(&canonical), &ciphertext, &[0u8; 12]),
+            None
+        );
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn phone_lookup_works_under_the_rotated_secret() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let old_secret = Token::generate_token().into_bytes();
+        let new_secret = Token::generate_token().into_bytes();
+        let username = Token::generate_token();
+        let user_id = sqlx::query_scalar!(
+            "INSERT INTO users(email, username, password, user_role)
+             VALUES($1, $2, '', 'Agent') RETURNING id",
+            format!("{username}@example.com"),
+            username
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "INSERT INTO user_info(user_id, full_name, phone_number) VALUES($1, 'Teszt Rögzítő', $2)",
+            user_id,
+            username
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let phone_number = "+36301234567";
+        let (phone_enc, phone_nonce) = encrypt::encrypt_value(&key, phone_number);
+        let (blank_enc, blank_nonce) = encrypt::encrypt_value(&key, "");
+        let customer_uuid = sqlx::query_scalar!(
+            "INSERT INTO customers(full_name, phone_number_enc, phone_number_nonce, phone_number_hash, email_enc, email_nonce, address_enc, address_nonce, user_id, created_by)
+             VALUES('Teszt Ügyfél', $1, $2, $3, $4, $5, $4, $5, $6, 'Teszt')
+             RETURNING uuid",
+            phone_enc,
+            phone_nonce,
+            encrypt::hash_value(&old_secret, phone_number),
+            blank_enc,
+            blank_nonce,
+            user_id
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+
+        let before = Customer::find_uuid_by_phone(&db, &new_secret, &[user_id], phone_number).await;
+        let counts = rehash_all(&db, &key, &old_secret, &new_secret).await;
+        let after = Customer::find_uuid_by_phone(&db, &new_secret, &[user_id], phone_number).await;
+        let stale = Customer::find_uuid_by_phone(&db, &old_secret, &[user_id], phone_number).await;
+        sqlx::query!("DELETE FROM customers WHERE user_id = $1", user_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        assert_eq!(before.unwrap(), None);
+        let counts = counts.unwrap();
+        assert!(
+            counts
+                .iter()
+                .any(|(table, count)| *table == "customers" && count.updated >= 1)
+        );
+        assert_eq!(after.unwrap(), customer_uuid);
+        assert_eq!(stale.unwrap(), None);
+    }
+}
@@ -1,17 +1,21 @@
 use anyhow::{Result, anyhow};
 use chacha20poly1305::Key;
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use sqlx::prelude::Type;
 use strum::{AsRefStr, Display, EnumString};
 use uuid::Uuid;
 
-use crate::models::dto::InterventionTaskDto;
+use crate::models::dto::{BalanceSummaryDto, InterventionTaskDto};
 use crate::{
     database::Database,
-    models::{customer::Customer, user::User},
-    utils::encrypt::{self, HmacSecret},
+    models::{audit_log::AuditLog, customer::Customer, user::User},
+    utils::{
+        encrypt::{DecryptFailurePolicy, HmacSecret, RowDecryptor},
+        error::ApiError,
+        validation,
+    },
 };
 
 #[skip_serializing_none]
@@ -29,6 +33,29 @@ pub struct InterventionTask {
     pub customer_id: Option<i32>,
     pub user_id: Option<i32>,
     pub created_by: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Customer and task columns selected by every task listing, before decryption.
+pub(crate) struct TaskListingRow {
+    pub(crate) customer_uuid: Option<Uuid>,
+    pub(crate) full_name: String,
+    pub(crate) phone_number_enc: Vec<u8>,
+    pub(crate) phone_number_nonce: Vec<u8>,
+    pub(crate) email_enc: Vec<u8>,
+    pub(crate) email_nonce: Vec<u8>,
+    pub(crate) address_enc: Vec<u8>,
+    pub(crate) address_nonce: Vec<u8>,
+    pub(crate) uuid: Option<Uuid>,
+    pub(crate) contract_number: String,
+    pub(crate) product_name: String,
+    pub(crate) outstanding_days: i32,
+    pub(crate) balance: i32,
+    pub(crate) processing_deadline: NaiveDateTime,
+    pub(crate) comment: Option<String>,
+    pub(crate) status: String,
+    pub(crate) created_by: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, EnumString, Display, Type, AsRefStr)]
@@ -50,29 +77,21 @@ impl InterventionTask {
         user_uuid: Uuid,
         customer: Customer,
         intervention_task: InterventionTask,
-    ) -> Result<i32> {
+    ) -> Result<(i32, bool)> {
         let user_id = User::get_id_by_uuid(db, Some(user_uuid))
             .await?
             .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
-        let row = sqlx::query!(
-            "SELECT id FROM customers
-             WHERE email_hash = $1 OR phone_number_hash = $2",
-            encrypt::hash_value(hmac_secret, &customer.email.as_ref().unwrap()),
-            encrypt::hash_value(hmac_secret, &customer.phone_number.as_ref().unwrap()),
-        )
-        .fetch_optional(&db.pool)
-        .await?;
+        let (customer_id, customer_created) =
+            Customer::create_or_get(db, key, hmac_secret, user_uuid, customer).await?;
 
-        // Determine customer_id: create customer if not exists, otherwise use existing id
-        let customer_id = if let Some(existing) = row {
-            existing.id
-        } else {
-            Customer::create(db, key, hmac_secret, user_uuid, customer.clone()).await?
-        };
+        let mut conn = db.pool.acquire().await?;
+        let created_by =
+            User::normalize_created_by(&mut conn, intervention_task.created_by.as_deref()).await?;
 
         let intervention_task_row = sqlx::query!(
-            "INSERT INTO customer_intervention_tasks(contract_number, product_name, outstanding_days, balance, processing_deadline, comment, status, customer_id, user_id, created_by)
-             VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "INSERT INTO customer_intervention_tasks(contract_number, product_name, outstanding_days, balance, processing_deadline, comment, status, customer_id, user_id, created_by, contract_id)
+             VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
+                (SELECT id FROM customer_contracts WHERE customer_id = $8 AND contract_number = $1::VARCHAR LIMIT 1))
              RETURNING id",
             intervention_task.contract_number,
             intervention_task.product_name,
@@ -83,12 +102,12 @@ impl InterventionTask {
             intervention_task.status.map(|s| s.to_string()),
             customer_id,
             user_id,
-            intervention_task.created_by
+            created_by
         )
-        .fetch_one(&db.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
-        Ok(intervention_task_row.id)
+        Ok((intervention_task_row.id, customer_created))
     }
 
     pub async fn modify(
@@ -96,7 +115,7 @@ impl InterventionTask {
         intervention_task_uuid: Uuid,
         updated_intervention_task: InterventionTask,
     ) -> Result<()> {
-        sqlx::query!(
+        let result = sqlx::query!(
             "UPDATE customer_intervention_tasks
              SET contract_number = $1,
                  product_name = $2,
@@ -104,7 +123,12 @@ impl InterventionTask {
                  balance = $4,
                  processing_deadline = $5,
                  comment = $6,
-                 status = $7
+                 status = $7,
+                 contract_id = (
+                    SELECT id FROM customer_contracts cc
+                    WHERE cc.customer_id = customer_intervention_tasks.customer_id AND cc.contract_number = $1::VARCHAR
+                    LIMIT 1
+                 )
              WHERE uuid = $8",
             updated_intervention_task.contract_number,
             updated_intervention_task.product_name,
@@ -118,54 +142,153 @@ impl InterventionTask {
         .execute(&db.pool)
         .await?;
 
+        if result.rows_affected() == 0 {
+            return Err(
+                ApiError::NotFound("Intervenciós feladat nem található!".to_string()).into(),
+            );
+        }
+
         Ok(())
     }
 
+    pub async fn backfill_contract_links(db: &Database) -> Result<u64> {
+        let result = sqlx::query!(
+            "UPDATE customer_intervention_tasks it
+             SET contract_id = cc.id
+             FROM customer_contracts cc
+             WHERE it.contract_id IS NULL
+               AND cc.customer_id = it.customer_id
+               AND cc.contract_number = it.contract_number"
+        )
+        .execute(&db.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // Shared with `Contract::get_open_tasks`, so both task listings decrypt and audit alike
+    pub(crate) async fn decrypt_listing(
+        db: &Database,
+        key: &Key,
+        actor_id: i32,
+        rows: Vec<TaskListingRow>,
+        source: &str,
+    ) -> Result<Vec<InterventionTaskDto>> {
+        let customer_uuids: Vec<Uuid> = rows.iter().filter_map(|row| row.customer_uuid).collect();
+
+        let items: Vec<InterventionTaskDto> = rows
+            .into_iter()
+            .map(|row| {
+                let mut decryptor = RowDecryptor::new(key);
+                Ok(InterventionTaskDto {
+                    full_name: row.full_name,
+                    phone_number: decryptor
+                        .decrypt(&row.phone_number_enc, &row.phone_number_nonce)
+                        .unwrap_or_default(),
+                    email: decryptor
+                        .decrypt(&row.email_enc, &row.email_nonce)
+                        .unwrap_or_default(),
+                    address: decryptor
+                        .decrypt(&row.address_enc, &row.address_nonce)
+                        .unwrap_or_default(),
+                    uuid: row.uuid,
+                    contract_number: row.contract_number,
+                    product_name: row.product_name,
+                    outstanding_days: row.outstanding_days,
+                    balance: row.balance,
+                    processing_deadline: row.processing_deadline,
+                    comment: row.comment,
+                    status: validation::parse_stored("status", &row.status)?,
+                    created_by: row.created_by,
+                    decrypt_ok: decryptor.ok,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let items = DecryptFailurePolicy::from_env().apply(items, |row| row.decrypt_ok)?;
+        AuditLog::record_pii_reads(db, actor_id, &customer_uuids, source).await?;
+
+        Ok(items)
+    }
+
     pub async fn get_all(
         db: &Database,
         key: &Key,
+        actor_id: i32,
         user_uuid: Uuid,
     ) -> Result<Vec<InterventionTaskDto>> {
         let user_id = User::get_id_by_uuid(db, Some(user_uuid))
             .await?
             .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
-        let rows = sqlx::query!(
-            "SELECT c.full_name, c.phone_number_enc, c.phone_number_nonce, c.email_enc, c.email_nonce, c.address_enc, c.address_nonce, it.uuid, it.contract_number, it.product_name, it.outstanding_days, it.balance, it.processing_deadline, it.comment, it.status, it.created_by
+        let rows = sqlx::query_as!(
+            TaskListingRow,
+            "SELECT c.uuid AS customer_uuid, c.full_name, c.phone_number_enc, c.phone_number_nonce, c.email_enc, c.email_nonce, c.address_enc, c.address_nonce, it.uuid, it.contract_number, it.product_name, it.outstanding_days, it.balance, it.processing_deadline, it.comment, it.status, it.created_by
              FROM customers c
              JOIN customer_intervention_tasks it ON it.customer_id = c.id
-             WHERE it.user_id = $1",
-            user_id
+             WHERE it.user_id = $1
+             LIMIT $2",
+            user_id,
+            validation::decrypt_fetch_limit()
         )
             .fetch_all(&db.pool)
             .await?;
+        validation::ensure_decrypt_limit(rows.len())?;
+        Self::decrypt_listing(db, key, actor_id, rows, "task_list").await
+    }
 
-        let items: Vec<InterventionTaskDto> = rows
-            .into_iter()
-            .map(|row| InterventionTaskDto {
-                full_name: row.full_name,
-                phone_number: encrypt::decrypt_value(
-                    key,
-                    &row.phone_number_enc,
-                    &row.phone_number_nonce,
-                )
-                .unwrap_or_default(),
-                email: encrypt::decrypt_value(key, &row.email_enc, &row.email_nonce)
-                    .unwrap_or_default(),
-                address: encrypt::decrypt_value(key, &row.address_enc, &row.address_nonce)
-                    .unwrap_or_default(),
-                uuid: row.uuid,
-                contract_number: row.contract_number,
-                product_name: row.product_name,
-                outstanding_days: row.outstanding_days,
-                balance: row.balance,
-                processing_deadline: row.processing_deadline,
-                comment: row.comment,
-                status: row.status.parse().unwrap(),
-                created_by: row.created_by,
-            })
-            .collect();
+    pub async fn export_csv(
+        db: &Database,
+        key: &Key,
+        actor_id: i32,
+        user_uuid: Uuid,
+    ) -> Result<Vec<u8>> {
+        let items = Self::get_all(db, key, actor_id, user_uuid).await?;
 
-        Ok(items)
+        let mut writer = csv::Writer::from_writer(vec![]);
+        writer.write_record([
+            "contract_number",
+            "product_name",
+            "outstanding_days",
+            "balance",
+            "processing_deadline",
+            "status",
+            "comment",
+            "full_name",
+            "phone_number",
+        ])?;
+        for item in items {
+            writer.write_record([
+                item.contract_number,
+                item.product_name,
+                item.outstanding_days.to_string(),
+                item.balance.to_string(),
+                item.processing_deadline
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+                item.status.to_string(),
+                item.comment.unwrap_or_default(),
+                item.full_name,
+                item.phone_number,
+            ])?;
+        }
+
+        Ok(writer.into_inner()?)
+    }
+
+    pub async fn count_open_by_user_uuid(db: &Database, user_uuid: Uuid) -> Result<i64> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!"
+             FROM customer_intervention_tasks
+             WHERE user_id = $1 AND status NOT IN ('Processed', 'PendingDeletion')"#,
+            user_id
+        )
+        .fetch_one(&db.pool)
+        .await?;
+
+        Ok(count)
     }
 
     pub async fn get_by_customer_uuid(
@@ -198,19 +321,21 @@ impl InterventionTask {
 
         let items: Vec<InterventionTask> = rows
             .into_iter()
-            .map(|row| InterventionTask {
-                uuid: row.uuid,
-                contract_number: Some(row.contract_number),
-                product_name: Some(row.product_name),
-                outstanding_days: Some(row.outstanding_days),
-                balance: Some(row.balance),
-                processing_deadline: Some(row.processing_deadline),
-                comment: row.comment,
-                status: Some(row.status.parse().unwrap()),
-                created_by: Some(row.created_by),
-                ..Default::default()
+            .map(|row| {
+                Ok(InterventionTask {
+                    uuid: row.uuid,
+                    contract_number: Some(row.contract_number),
+                    product_name: Some(row.product_name),
+                    outstanding_days: Some(row.outstanding_days),
+                    balance: Some(row.balance),
+                    processing_deadline: Some(row.processing_deadline),
+                    comment: row.comment,
+                    status: Some(validation::parse_stored("status", &row.status)?),
+                    created_by: Some(row.created_by),
+                    ..Default::default()
+                })
             })
-            .collect();
+            .collect::<Result<Vec<_>>>()?;
 
         Ok(items)
     }
@@ -229,7 +354,9 @@ impl InterventionTask {
                 processing_deadline,
                 comment,
                 status,
-                created_by
+                created_by,
+                created_at,
+                updated_at
             FROM
                 customer_intervention_tasks
             WHERE
@@ -249,10 +376,96 @@ impl InterventionTask {
             comment: row.comment,
             status: Some(row.status.parse()?),
             created_by: Some(row.created_by),
+            created_at: Some(row.created_at),
+            updated_at: Some(row.updated_at),
             ..Default::default()
         })
     }
 
+    pub async fn distinct_products(
+        db: &Database,
+        user_uuid: Uuid,
+        prefix: &str,
+        limit: i64,
+    ) -> Result<Vec<String>> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+        let prefix = validation::escape_like(prefix.trim());
+
+        let products = sqlx::query_scalar!(
+            "SELECT DISTINCT product_name
+             FROM customer_intervention_tasks
+             WHERE user_id = $1 AND product_name ILIKE $2 || '%'
+             ORDER BY product_name
+             LIMIT $3",
+            user_id,
+            prefix,
+            limit
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(products)
+    }
+
+    pub async fn get_balance_summary(db: &Database, user_uuid: Uuid) -> Result<BalanceSummaryDto> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let row = sqlx::query!(
+            r#"SELECT
+                COALESCE(SUM(balance), 0) AS "total!",
+                COALESCE(SUM(balance) FILTER (WHERE status = 'Pending'), 0) AS "pending!",
+                COALESCE(SUM(balance) FILTER (WHERE status = 'PaymentPromise'), 0) AS "payment_promise!",
+                COALESCE(SUM(balance) FILTER (WHERE status = 'Processed'), 0) AS "processed!",
+                COALESCE(SUM(balance) FILTER (WHERE status = 'Nonpayment'), 0) AS "nonpayment!",
+                COALESCE(SUM(balance) FILTER (WHERE status = 'PendingDeletion'), 0) AS "pending_deletion!"
+            FROM customer_intervention_tasks
+            WHERE user_id = $1"#,
+            user_id
+        )
+        .fetch_one(&db.pool)
+        .await?;
+
+        let by_status = [
+            (InterventionTaskStatus::Pending, row.pending),
+            (InterventionTaskStatus::PaymentPromise, row.payment_promise),
+            (InterventionTaskStatus::Processed, row.processed),
+            (InterventionTaskStatus::Nonpayment, row.nonpayment),
+            (
+                InterventionTaskStatus::PendingDeletion,
+                row.pending_deletion,
+            ),
+        ]
+        .into_iter()
+        .map(|(status, balance)| (status.to_string(), balance))
+        .collect();
+
+        Ok(BalanceSummaryDto {
+            total_balance: row.total,
+            by_status,
+        })
+    }
+
+    pub async fn change_status_bulk(
+        db: &Database,
+        intervention_task_uuids: Vec<Uuid>,
+        status: InterventionTaskStatus,
+    ) -> Result<u64> {
+        let result = sqlx::query!(
+            "UPDATE customer_intervention_tasks
+             SET status = $2
+             WHERE uuid = ANY($1)",
+            &intervention_task_uuids,
+            status.to_string()
+        )
+        .execute(&db.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
     pub async fn get_customer_uuid(
         db: &Database,
         intervention_task_uuid: Uuid,
@@ -309,3 +522,23 @@ impl InterventionTask {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn modifying_a_missing_task_is_not_found() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+
+        let modify =
+            InterventionTask::modify(&db, Uuid::new_v4(), InterventionTask::default()).await;
+
+        assert!(matches!(
+            ApiError::from(modify.unwrap_err()),
+            ApiError::NotFound(_)
+        ));
+    }
+}
@@ -2,8 +2,9 @@ use std::str::FromStr;
 
 use anyhow::{Result, anyhow};
 use chacha20poly1305::Key;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use serde_with::skip_serializing_none;
 use sqlx::prelude::Type;
 use strum::{AsRefStr, Display, EnumString};
@@ -11,8 +12,20 @@ use uuid::Uuid;
 
 use crate::{
     database::Database,
-    models::{customer::Customer, dto::LeadListItemDto, user::User},
-    utils::encrypt::{self, HmacSecret},
+    models::{
+        audit_log::AuditLog,
+        customer::Customer,
+        dto::{InquiryBreakdownDto, LeadListItemDto, SearchHitDto},
+        user::{ListingScope, User},
+        webhook::WebhookEvent,
+    },
+    utils::{
+        contact,
+        encrypt::{self, DecryptFailurePolicy, HmacSecret, RowDecryptor},
+        error::ApiError,
+        pagination::{Paginated, PaginationQuery},
+        validation,
+    },
 };
 
 #[skip_serializing_none]
@@ -22,9 +35,12 @@ pub struct Lead {
     pub uuid: Option<Uuid>,
     pub lead_type: Option<LeadType>,
     pub inquiry_type: Option<String>,
+    pub inquiry_type_raw: Option<String>,
     pub lead_status: Option<LeadStatus>,
     pub handle_at: Option<DateTime<Utc>>,
     pub created_by: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Type, Clone, AsRefStr, EnumString, Display)]
@@ -43,6 +59,97 @@ pub enum LeadStatus {
     Closed,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum InquiryType {
+    Car,
+    Home,
+    Life,
+    Health,
+    Travel,
+    Savings,
+    Corporate,
+    Other(String),
+}
+
+impl InquiryType {
+    // Synonyms match whole words, checked in order. The migration backfilling
+    // `inquiry_type` mirrors this list, keep the two in sync.
+    const SYNONYMS: [(InquiryType, &[&str]); 7] = [
+        (InquiryType::Health, &["egeszseg", "health", "care"]),
+        (
+            InquiryType::Car,
+            &["auto", "car", "kgfb", "casco", "gepjarmu", "jarmu"],
+        ),
+        (
+            InquiryType::Home,
+            &["lakas", "otthon", "haz", "home", "ingatlan", "tarsashaz"],
+        ),
+        (InquiryType::Life, &["elet", "life"]),
+        (InquiryType::Travel, &["utas", "travel", "utazas"]),
+        (
+            InquiryType::Savings,
+            &[
+                "megtakaritas",
+                "nyugdij",
+                "befektetes",
+                "savings",
+                "pension",
+            ],
+        ),
+        (
+            InquiryType::Corporate,
+            &[
+                "vallalat",
+                "vallalati",
+                "ceg",
+                "ceges",
+                "corporate",
+                "mezogazdasag",
+            ],
+        ),
+    ];
+
+    // Compounds like "lakásbiztosítás" still count as a whole-word match
+    const COMPOUND_SUFFIXES: [&str; 2] = ["biztositas", "biztosito"];
+
+    fn matches(word: &str, synonym: &str) -> bool {
+        word == synonym
+            || word
+                .strip_prefix(synonym)
+                .is_some_and(|suffix| Self::COMPOUND_SUFFIXES.contains(&suffix))
+    }
+
+    pub fn normalize(raw: &str) -> InquiryType {
+        let cleaned = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+        let folded = validation::fold_accents(&cleaned);
+
+        Self::SYNONYMS
+            .into_iter()
+            .find(|(_, synonyms)| {
+                folded
+                    .split(|c: char| !c.is_ascii_alphanumeric())
+                    .any(|word| synonyms.iter().any(|synonym| Self::matches(word, synonym)))
+            })
+            .map(|(inquiry_type, _)| inquiry_type)
+            .unwrap_or(InquiryType::Other(cleaned))
+    }
+}
+
+impl std::fmt::Display for InquiryType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InquiryType::Car => write!(f, "Car"),
+            InquiryType::Home => write!(f, "Home"),
+            InquiryType::Life => write!(f, "Life"),
+            InquiryType::Health => write!(f, "Health"),
+            InquiryType::Travel => write!(f, "Travel"),
+            InquiryType::Savings => write!(f, "Savings"),
+            InquiryType::Corporate => write!(f, "Corporate"),
+            InquiryType::Other(value) => write!(f, "{value}"),
+        }
+    }
+}
+
 impl Lead {
     async fn is_exists(db: &Database, lead: &Lead) -> Result<bool> {
         let is_exists = sqlx::query!(
@@ -74,61 +181,86 @@ impl Lead {
         db: &Database,
         key: &Key,
         hmac_secret: &HmacSecret,
+        actor_id: Option<i32>,
         user_uuid: Uuid,
         customer: Customer,
         lead: Lead,
-    ) -> Result<()> {
+    ) -> Result<bool> {
         let user_id = User::get_id_by_uuid(db, Some(user_uuid))
             .await?
             .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
-        let row = sqlx::query!(
-            "SELECT id FROM customers
-             WHERE email_hash = $1 OR phone_number_hash = $2",
-            encrypt::hash_value(hmac_secret, &customer.email.as_ref().unwrap()),
-            encrypt::hash_value(hmac_secret, &customer.phone_number.as_ref().unwrap()),
-        )
-        .fetch_optional(&db.pool)
-        .await?;
+        let (customer_id, customer_created) =
+            Customer::create_or_get(db, key, hmac_secret, user_uuid, customer).await?;
+        let mut conn = db.pool.acquire().await?;
+        Customer::ensure_contactable(&mut conn, db, customer_id, actor_id).await?;
 
-        // Determine customer_id: create customer if not exists, otherwise use existing id
-        let customer_id = if let Some(existing) = row {
-            existing.id
-        } else {
-            Customer::create(db, key, hmac_secret, user_uuid, customer.clone()).await?
-        };
+        let created_by = User::normalize_created_by(&mut conn, lead.created_by.as_deref()).await?;
+
+        let inquiry_type = lead
+            .inquiry_type
+            .as_deref()
+            .map(|raw| InquiryType::normalize(raw).to_string());
 
         let _row = sqlx::query!(
-            "INSERT INTO customer_leads(lead_type, inquiry_type, lead_status, customer_id, user_id, created_by)
-             VALUES($1,$2, $3, $4, $5, $6)
+            "INSERT INTO customer_leads(lead_type, inquiry_type, inquiry_type_raw, lead_status, customer_id, user_id, created_by)
+             VALUES($1, $2, $3, $4, $5, $6, $7)
              RETURNING id",
             lead.lead_type.map(|t| t.to_string()),
+            inquiry_type,
             lead.inquiry_type,
             lead.lead_status.map(|l| l.to_string()),
             customer_id,
             user_id,
-            lead.created_by
+            created_by
         )
-        .fetch_one(&db.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
-        Ok(())
+        Ok(customer_created)
     }
 
     pub async fn modify(db: &Database, lead_uuid: Uuid, updated_lead: Lead) -> Result<()> {
-        sqlx::query!(
-            "UPDATE customer_leads
+        let inquiry_type = updated_lead
+            .inquiry_type
+            .as_deref()
+            .map(|raw| InquiryType::normalize(raw).to_string());
+        let lead_status = updated_lead.lead_status.map(|s| s.to_string());
+        let row = sqlx::query!(
+            "WITH previous AS (
+                SELECT id, lead_status FROM customer_leads WHERE uuid = $4 FOR UPDATE
+             )
+             UPDATE customer_leads l
              SET lead_type = $1,
                  inquiry_type = $2,
                  lead_status = $3,
+                 inquiry_type_raw = $5,
                  handle_at = NOW()
-             WHERE uuid = $4",
+             FROM previous
+             WHERE l.id = previous.id
+             RETURNING previous.lead_status AS previous_status",
             updated_lead.lead_type.map(|t| t.to_string()),
-            updated_lead.inquiry_type,
-            updated_lead.lead_status.map(|s| s.to_string()),
-            lead_uuid
+            inquiry_type,
+            lead_status,
+            lead_uuid,
+            updated_lead.inquiry_type
         )
-        .execute(&db.pool)
-        .await?;
+        .fetch_optional(&db.pool)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Tevékenység nem található!".to_string()))?;
+
+        if lead_status.as_ref() != Some(&row.previous_status) {
+            db.webhooks
+                .dispatch(
+                    db,
+                    WebhookEvent::LeadStatusChanged,
+                    json!({
+                        "uuid": lead_uuid,
+                        "previous_status": row.previous_status,
+                        "lead_status": lead_status,
+                    }),
+                )
+                .await;
+        }
 
         Ok(())
     }
@@ -136,43 +268,140 @@ impl Lead {
     pub async fn get_all(
         db: &Database,
         key: &Key,
+        actor_id: i32,
         user_uuid: Uuid,
+        scope: ListingScope,
+    ) -> Result<Vec<LeadListItemDto>> {
+        let user_ids = User::get_scoped_ids(db, user_uuid, scope).await?;
+        let rows = sqlx::query!(
+            "SELECT c.uuid AS customer_uuid, c.full_name, c.phone_number_enc, c.phone_number_nonce, c.email_enc, c.email_nonce, c.address_enc, c.address_nonce, l.uuid, l.lead_type, l.inquiry_type, l.lead_status, l.handle_at, l.created_by
+             FROM customers c
+             JOIN customer_leads l ON l.customer_id = c.id
+             WHERE l.user_id = ANY($1)
+             LIMIT $2",
+            &user_ids,
+            validation::decrypt_fetch_limit()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+        validation::ensure_decrypt_limit(rows.len())?;
+        let customer_uuids: Vec<Uuid> = rows.iter().filter_map(|row| row.customer_uuid).collect();
+
+        let items: Vec<LeadListItemDto> = rows
+            .into_iter()
+            .map(|row| {
+                let mut decryptor = RowDecryptor::new(key);
+                LeadListItemDto {
+                    uuid: row.uuid,
+                    full_name: row.full_name,
+                    phone_number: decryptor
+                        .decrypt(&row.phone_number_enc, &row.phone_number_nonce)
+                        .unwrap_or_default(),
+                    email: decryptor
+                        .decrypt(&row.email_enc, &row.email_nonce)
+                        .unwrap_or_default(),
+                    address: decryptor
+                        .decrypt(&row.address_enc, &row.address_nonce)
+                        .unwrap_or_default(),
+                    lead_type: row.lead_type,
+                    inquiry_type: row.inquiry_type,
+                    lead_status: row.lead_status,
+                    handle_at: row.handle_at,
+                    created_by: row.created_by,
+                    decrypt_ok: decryptor.ok,
+                }
+            })
+            .collect();
+        let items = DecryptFailurePolicy::from_env().apply(items, |row| row.decrypt_ok)?;
+        AuditLog::record_pii_reads(db, actor_id, &customer_uuids, "lead_list").await?;
+
+        Ok(items)
+    }
+
+    pub async fn search_brief(
+        db: &Database,
+        hmac_secret: &HmacSecret,
+        user_ids: &[i32],
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<SearchHitDto>> {
+        let rows = sqlx::query!(
+            r#"SELECT l.uuid AS "uuid!", c.full_name, l.lead_type
+             FROM customer_leads l
+             JOIN customers c ON c.id = l.customer_id
+             WHERE l.user_id = ANY($1)
+               AND (c.full_name ILIKE '%' || $2 || '%' OR c.phone_number_hash = $3 OR c.email_hash = $3)
+             ORDER BY l.handle_at DESC
+             LIMIT $4"#,
+            user_ids,
+            validation::escape_like(query),
+            encrypt::hash_value(hmac_secret, &contact::canonical(query)),
+            limit
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SearchHitDto {
+                uuid: row.uuid,
+                label: row.full_name,
+                detail: Some(row.lead_type),
+            })
+            .collect())
+    }
+
+    pub async fn get_by_creator(
+        db: &Database,
+        key: &Key,
+        user_uuid: Uuid,
+        created_by: &str,
     ) -> Result<Vec<LeadListItemDto>> {
         let user_id = User::get_id_by_uuid(db, Some(user_uuid))
             .await?
             .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
         let rows = sqlx::query!(
             "SELECT c.full_name, c.phone_number_enc, c.phone_number_nonce, c.email_enc, c.email_nonce, c.address_enc, c.address_nonce, l.uuid, l.lead_type, l.inquiry_type, l.lead_status, l.handle_at, l.created_by
              FROM customers c
              JOIN customer_leads l ON l.customer_id = c.id
-             WHERE l.user_id = $1",
-            user_id
+             WHERE l.user_id = $1 AND LOWER(TRIM(l.created_by)) = LOWER(TRIM($2))
+             ORDER BY l.handle_at DESC
+             LIMIT $3",
+            user_id,
+            created_by,
+            validation::decrypt_fetch_limit()
         )
         .fetch_all(&db.pool)
         .await?;
+        validation::ensure_decrypt_limit(rows.len())?;
 
         let items: Vec<LeadListItemDto> = rows
             .into_iter()
-            .map(|row| LeadListItemDto {
-                uuid: row.uuid,
-                full_name: row.full_name,
-                phone_number: encrypt::decrypt_value(
-                    key,
-                    &row.phone_number_enc,
-                    &row.phone_number_nonce,
-                )
-                .unwrap_or_default(),
-                email: encrypt::decrypt_value(key, &row.email_enc, &row.email_nonce)
-                    .unwrap_or_default(),
-                address: encrypt::decrypt_value(key, &row.address_enc, &row.address_nonce)
-                    .unwrap_or_default(),
-                lead_type: row.lead_type,
-                inquiry_type: row.inquiry_type,
-                lead_status: row.lead_status,
-                handle_at: row.handle_at,
-                created_by: row.created_by,
+            .map(|row| {
+                let mut decryptor = RowDecryptor::new(key);
+                LeadListItemDto {
+                    uuid: row.uuid,
+                    full_name: row.full_name,
+                    phone_number: decryptor
+                        .decrypt(&row.phone_number_enc, &row.phone_number_nonce)
+                        .unwrap_or_default(),
+                    email: decryptor
+                        .decrypt(&row.email_enc, &row.email_nonce)
+                        .unwrap_or_default(),
+                    address: decryptor
+                        .decrypt(&row.address_enc, &row.address_nonce)
+                        .unwrap_or_default(),
+                    lead_type: row.lead_type,
+                    inquiry_type: row.inquiry_type,
+                    lead_status: row.lead_status,
+                    handle_at: row.handle_at,
+                    created_by: row.created_by,
+                    decrypt_ok: decryptor.ok,
+                }
             })
             .collect();
+        let items = DecryptFailurePolicy::from_env().apply(items, |row| row.decrypt_ok)?;
 
         Ok(items)
     }
@@ -181,12 +410,13 @@ impl Lead {
         let customer_id = Customer::get_id_by_uuid(db, Some(customer_uuid))
             .await?
             .unwrap();
-        
+
         let rows = sqlx::query!(
             "SELECT
                 uuid,
                 lead_type,
                 inquiry_type,
+                inquiry_type_raw,
                 lead_status,
                 handle_at,
                 created_by
@@ -201,29 +431,93 @@ impl Lead {
 
         let items: Vec<Lead> = rows
             .into_iter()
-            .map(|row| Lead {
-                uuid: row.uuid,
-                lead_type: Some(row.lead_type.parse().unwrap()),
-                inquiry_type: Some(row.inquiry_type),
-                lead_status: LeadStatus::from_str(&row.lead_status).ok(),
-                handle_at: Some(row.handle_at),
-                created_by: Some(row.created_by),
-                ..Default::default()
+            .map(|row| {
+                Ok(Lead {
+                    uuid: row.uuid,
+                    lead_type: Some(validation::parse_stored("lead_type", &row.lead_type)?),
+                    inquiry_type: Some(row.inquiry_type),
+                    inquiry_type_raw: row.inquiry_type_raw,
+                    lead_status: LeadStatus::from_str(&row.lead_status).ok(),
+                    handle_at: Some(row.handle_at),
+                    created_by: Some(row.created_by),
+                    ..Default::default()
+                })
             })
-            .collect();
+            .collect::<Result<Vec<_>>>()?;
 
         Ok(items)
     }
 
+    pub async fn get_by_customer_uuid_paginated(
+        db: &Database,
+        customer_uuid: Uuid,
+        pagination: PaginationQuery,
+    ) -> Result<Paginated<Lead>> {
+        let customer_id = Customer::get_id_by_uuid(db, Some(customer_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Ügyfél nem található!"))?;
+
+        let rows = sqlx::query!(
+            "SELECT uuid, lead_type, inquiry_type, inquiry_type_raw, lead_status, handle_at, created_by
+             FROM customer_leads
+             WHERE customer_id = $1
+             ORDER BY handle_at DESC, id DESC
+             LIMIT $2 OFFSET $3",
+            customer_id,
+            pagination.limit() + 1,
+            pagination.offset()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        let items: Vec<Lead> = rows
+            .into_iter()
+            .map(|row| {
+                Ok(Lead {
+                    uuid: row.uuid,
+                    lead_type: Some(validation::parse_stored("lead_type", &row.lead_type)?),
+                    inquiry_type: Some(row.inquiry_type),
+                    inquiry_type_raw: row.inquiry_type_raw,
+                    lead_status: LeadStatus::from_str(&row.lead_status).ok(),
+                    handle_at: Some(row.handle_at),
+                    created_by: Some(row.created_by),
+                    ..Default::default()
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let total = if pagination.with_total() {
+            sqlx::query_scalar!(
+                r#"SELECT COUNT(*) AS "count!" FROM customer_leads WHERE customer_id = $1"#,
+                customer_id
+            )
+            .fetch_one(&db.pool)
+            .await
+            .map(Some)?
+        } else {
+            None
+        };
+
+        Ok(Paginated::new(
+            items,
+            total,
+            pagination.limit(),
+            pagination.offset(),
+        ))
+    }
+
     pub async fn get_by_uuid(db: &Database, lead_uuid: Uuid) -> Result<Lead> {
         let row = sqlx::query!(
             "SELECT
                 uuid,
                 lead_type,
                 inquiry_type,
+                inquiry_type_raw,
                 lead_status,
                 handle_at,
-                created_by
+                created_by,
+                created_at,
+                updated_at
             FROM
                 customer_leads
             WHERE
@@ -237,9 +531,12 @@ impl Lead {
             uuid: row.uuid,
             lead_type: Some(row.lead_type.parse()?),
             inquiry_type: Some(row.inquiry_type),
+            inquiry_type_raw: row.inquiry_type_raw,
             lead_status: LeadStatus::from_str(&row.lead_status).ok(),
             handle_at: Some(row.handle_at),
             created_by: Some(row.created_by),
+            created_at: Some(row.created_at),
+            updated_at: Some(row.updated_at),
             ..Default::default()
         })
     }
@@ -296,4 +593,89 @@ impl Lead {
 
         Ok(())
     }
+
+    // CHART FUNCTIONS
+    pub async fn get_inquiry_breakdown(
+        db: &Database,
+        user_uuid: Uuid,
+        start_date: NaiveDateTime,
+        end_date: NaiveDateTime,
+    ) -> Result<Vec<InquiryBreakdownDto>> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let rows = sqlx::query!(
+            r#"SELECT
+                LOWER(REGEXP_REPLACE(TRIM(inquiry_type), '\s+', ' ', 'g')) AS "inquiry_type!",
+                COUNT(*) AS "count!"
+            FROM customer_leads
+            WHERE user_id = $1 AND handle_at BETWEEN $2 AND $3
+            GROUP BY 1
+            ORDER BY 2 DESC, 1"#,
+            user_id,
+            start_date.and_utc(),
+            end_date.and_utc()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        let items = rows
+            .into_iter()
+            .map(|row| InquiryBreakdownDto {
+                inquiry_type: row.inquiry_type,
+                count: row.count,
+            })
+            .collect();
+
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inquiry_types_match_whole_words() {
+        assert_eq!(InquiryType::normalize("care"), InquiryType::Health);
+        assert_eq!(InquiryType::normalize("Auto"), InquiryType::Car);
+        assert_eq!(InquiryType::normalize("KGFB ajánlat"), InquiryType::Car);
+        assert_eq!(InquiryType::normalize("Autóbiztosítás"), InquiryType::Car);
+        assert_eq!(
+            InquiryType::normalize("lakás-biztosítás"),
+            InquiryType::Home
+        );
+        assert_eq!(InquiryType::normalize("Életbiztosító"), InquiryType::Life);
+        assert_eq!(
+            InquiryType::normalize("céges flotta"),
+            InquiryType::Corporate
+        );
+        assert_eq!(
+            InquiryType::normalize("career  change"),
+            InquiryType::Other("career change".to_string())
+        );
+        assert_eq!(
+            InquiryType::normalize("automatikus"),
+            InquiryType::Other("automatikus".to_string())
+        );
+        assert_eq!(
+            InquiryType::normalize("házhoz szállítás"),
+            InquiryType::Other("házhoz szállítás".to_string())
+        );
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn modifying_a_missing_lead_is_not_found() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+
+        let modify = Lead::modify(&db, Uuid::new_v4(), Lead::default()).await;
+
+        assert!(matches!(
+            ApiError::from(modify.unwrap_err()),
+            ApiError::NotFound(_)
+        ));
+    }
 }
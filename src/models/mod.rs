@@ -1,10 +1,15 @@
+pub mod audit_log;
 pub mod contract;
 pub mod customer;
 pub mod dto;
 pub mod lead;
+pub mod notification_prefs;
 pub mod user;
 pub mod user_date;
 pub mod user_info;
 pub mod intervention_task;
 pub mod recommendation;
 pub mod recruitment;
+pub mod settings;
+pub mod webhook;
+pub mod hash_rotation;
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use strum::{AsRefStr, Display, EnumString};
+
+use crate::{database::Database, utils::error::ApiError};
+
+const DEFAULT_REMINDER_LEAD_MINUTES: i32 = 60;
+const MAX_REMINDER_LEAD_MINUTES: i32 = 7 * 24 * 60;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotificationPrefs {
+    pub digest_enabled: bool,
+    pub reminder_lead_minutes: i32,
+    pub channel: NotificationChannel,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, EnumString, Display, AsRefStr)]
+pub enum NotificationChannel {
+    #[default]
+    Email,
+    Sms,
+}
+
+impl Default for NotificationPrefs {
+    fn default() -> Self {
+        Self {
+            digest_enabled: true,
+            reminder_lead_minutes: DEFAULT_REMINDER_LEAD_MINUTES,
+            channel: NotificationChannel::default(),
+        }
+    }
+}
+
+impl NotificationPrefs {
+    /// Users without a stored row get the defaults, so jobs can always rely on a value.
+    pub async fn get(db: &Database, user_id: i32) -> Result<Self> {
+        let row = sqlx::query!(
+            "SELECT digest_enabled, reminder_lead_minutes, channel
+             FROM notification_prefs
+             WHERE user_id = $1",
+            user_id
+        )
+        .fetch_optional(&db.pool)
+        .await?;
+
+        Ok(row
+            .map(|row| Self {
+                digest_enabled: row.digest_enabled,
+                reminder_lead_minutes: row.reminder_lead_minutes,
+                channel: row.channel.parse().unwrap_or_default(),
+            })
+            .unwrap_or_default())
+    }
+
+    pub async fn save(db: &Database, user_id: i32, prefs: &Self) -> Result<()> {
+        if !(0..=MAX_REMINDER_LEAD_MINUTES).contains(&prefs.reminder_lead_minutes) {
+            return Err(ApiError::Validation(format!(
+                "Az emlékeztető ideje 0 és {MAX_REMINDER_LEAD_MINUTES} perc között lehet!"
+            ))
+            .into());
+        }
+
+        sqlx::query!(
+            "INSERT INTO notification_prefs(user_id, digest_enabled, reminder_lead_minutes, channel)
+             VALUES($1, $2, $3, $4)
+             ON CONFLICT (user_id) DO UPDATE
+             SET digest_enabled = EXCLUDED.digest_enabled,
+                 reminder_lead_minutes = EXCLUDED.reminder_lead_minutes,
+                 channel = EXCLUDED.channel,
+                 updated_at = NOW()",
+            user_id,
+            prefs.digest_enabled,
+            prefs.reminder_lead_minutes,
+            prefs.channel.to_string()
+        )
+        .execute(&db.pool)
+        .await?;
+
+        Ok(())
+    }
+}
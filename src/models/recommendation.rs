@@ -1,10 +1,17 @@
 use crate::utils::encrypt::HmacSecret;
-use crate::{database::Database, utils::encrypt};
+use crate::{
+    database::Database,
+    utils::{contact, encrypt, validation},
+};
 use anyhow::{Ok, Result, anyhow};
 use chacha20poly1305::Key;
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
-use crate::models::user::User;
+use crate::models::{
+    dto::{RecommendationCheckDto, ReferrerCountDto, SearchHitDto},
+    user::User,
+};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
@@ -17,6 +24,8 @@ pub struct CustomerRecommendation {
     pub city: Option<String>,
     pub referral_name: Option<String>,
     pub created_by: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 impl CustomerRecommendation {
@@ -56,6 +65,74 @@ impl CustomerRecommendation {
         Ok(is_exists.is_some())
     }
 
+    /// Pre-flight duplicate check, so the UI can warn before the whole form is filled in.
+    pub async fn search_brief(
+        db: &Database,
+        hmac_secret: &HmacSecret,
+        user_ids: &[i32],
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<SearchHitDto>> {
+        let rows = sqlx::query!(
+            r#"SELECT uuid AS "uuid!", full_name, referral_name
+             FROM customer_recommendations
+             WHERE user_id = ANY($1)
+               AND (full_name ILIKE '%' || $2 || '%' OR phone_number_hash = $3)
+             ORDER BY full_name
+             LIMIT $4"#,
+            user_ids,
+            validation::escape_like(query),
+            encrypt::hash_value(hmac_secret, &contact::canonical(query)),
+            limit
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SearchHitDto {
+                uuid: row.uuid,
+                label: row.full_name,
+                detail: Some(row.referral_name),
+            })
+            .collect())
+    }
+
+    pub async fn check_existing(
+        db: &Database,
+        hmac_secret: &HmacSecret,
+        full_name: String,
+        phone_number: String,
+    ) -> Result<RecommendationCheckDto> {
+        let phone_hash = encrypt::hash_value(hmac_secret, &phone_number);
+        let candidate = CustomerRecommendation {
+            full_name: Some(full_name),
+            phone_number: Some(phone_number),
+            ..Default::default()
+        };
+        let recommendation = CustomerRecommendation::is_exists(db, hmac_secret, &candidate).await?;
+
+        let row = sqlx::query!(
+            r#"SELECT
+                EXISTS(SELECT 1 FROM customers WHERE phone_number_hash = $1) AS "customer!",
+                EXISTS(
+                    SELECT 1 FROM customer_leads l
+                    JOIN customers c ON c.id = l.customer_id
+                    WHERE c.phone_number_hash = $1
+                ) AS "lead!""#,
+            phone_hash
+        )
+        .fetch_one(&db.pool)
+        .await?;
+
+        Ok(RecommendationCheckDto {
+            exists: recommendation || row.customer || row.lead,
+            recommendation,
+            customer: row.customer,
+            lead: row.lead,
+        })
+    }
+
     pub async fn create(
         db: &Database,
         key: &Key,
@@ -84,6 +161,11 @@ impl CustomerRecommendation {
             .await?
             .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
 
+        let mut conn = db.pool.acquire().await?;
+        let created_by =
+            User::normalize_created_by(&mut conn, customer_recommendation.created_by.as_deref())
+                .await?;
+
         let row = sqlx::query!(
             "INSERT INTO customer_recommendations(full_name, phone_number_enc, phone_number_nonce, phone_number_hash, city_enc, city_nonce, referral_name, user_id, created_by)
              VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9)
@@ -96,9 +178,9 @@ impl CustomerRecommendation {
             city_nonce,
             customer_recommendation.referral_name,
             user_id,
-            customer_recommendation.created_by
+            created_by
         )
-        .fetch_one(&db.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         Ok(row.uuid.unwrap())
@@ -168,14 +250,17 @@ impl CustomerRecommendation {
             .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
 
         let rows = sqlx::query!(
-            "SELECT uuid, full_name, phone_number_enc, phone_number_nonce, city_enc, city_nonce, referral_name, created_by
+            "SELECT uuid, full_name, phone_number_enc, phone_number_nonce, city_enc, city_nonce, referral_name, created_by, created_at, updated_at
              FROM customer_recommendations
              WHERE user_id = $1
-             ORDER BY full_name ASC",
-            user_id
+             ORDER BY full_name ASC
+             LIMIT $2",
+            user_id,
+            validation::decrypt_fetch_limit()
         )
         .fetch_all(&db.pool)
         .await?;
+        validation::ensure_decrypt_limit(rows.len())?;
 
         Ok(rows
             .into_iter()
@@ -190,6 +275,84 @@ impl CustomerRecommendation {
                 city: encrypt::decrypt_value(key, &row.city_enc, &row.city_nonce),
                 referral_name: Some(row.referral_name),
                 created_by: Some(row.created_by),
+                created_at: Some(row.created_at),
+                updated_at: Some(row.updated_at),
+            })
+            .collect())
+    }
+
+    pub async fn get_by_referral(
+        db: &Database,
+        key: &Key,
+        user_uuid: Uuid,
+        referral_name: &str,
+    ) -> Result<Vec<CustomerRecommendation>> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let rows = sqlx::query!(
+            "SELECT uuid, full_name, phone_number_enc, phone_number_nonce, city_enc, city_nonce, referral_name, created_by, created_at, updated_at
+             FROM customer_recommendations
+             WHERE user_id = $1 AND LOWER(TRIM(referral_name)) = LOWER(TRIM($2))
+             ORDER BY full_name ASC
+             LIMIT $3",
+            user_id,
+            referral_name,
+            validation::decrypt_fetch_limit()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+        validation::ensure_decrypt_limit(rows.len())?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| CustomerRecommendation {
+                uuid: row.uuid,
+                full_name: Some(row.full_name),
+                phone_number: encrypt::decrypt_value(
+                    key,
+                    &row.phone_number_enc,
+                    &row.phone_number_nonce,
+                ),
+                city: encrypt::decrypt_value(key, &row.city_enc, &row.city_nonce),
+                referral_name: Some(row.referral_name),
+                created_by: Some(row.created_by),
+                created_at: Some(row.created_at),
+                updated_at: Some(row.updated_at),
+            })
+            .collect())
+    }
+
+    pub async fn get_referrer_leaderboard(
+        db: &Database,
+        user_uuid: Uuid,
+        limit: i64,
+    ) -> Result<Vec<ReferrerCountDto>> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let rows = sqlx::query!(
+            r#"SELECT
+                TRIM(referral_name) AS "referral_name!",
+                COUNT(*) AS "count!"
+            FROM customer_recommendations
+            WHERE user_id = $1 AND TRIM(referral_name) <> ''
+            GROUP BY 1
+            ORDER BY 2 DESC, 1
+            LIMIT $2"#,
+            user_id,
+            limit
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ReferrerCountDto {
+                referral_name: row.referral_name,
+                count: row.count,
             })
             .collect())
     }
@@ -200,7 +363,7 @@ impl CustomerRecommendation {
         recommendation_uuid: Uuid,
     ) -> Result<CustomerRecommendation> {
         let row = sqlx::query!(
-            "SELECT uuid, full_name, phone_number_enc, phone_number_nonce, city_enc, city_nonce, referral_name, created_by
+            "SELECT uuid, full_name, phone_number_enc, phone_number_nonce, city_enc, city_nonce, referral_name, created_by, created_at, updated_at
              FROM customer_recommendations
              WHERE uuid = $1",
             recommendation_uuid
@@ -219,6 +382,8 @@ impl CustomerRecommendation {
             city: encrypt::decrypt_value(key, &row.city_enc, &row.city_nonce),
             referral_name: Some(row.referral_name),
             created_by: Some(row.created_by),
+            created_at: Some(row.created_at),
+            updated_at: Some(row.updated_at),
         })
     }
 
@@ -1,7 +1,12 @@
 use crate::utils::encrypt::HmacSecret;
-use crate::{database::Database, utils::encrypt};
+use crate::{
+    database::Database,
+    models::user::User,
+    utils::{encrypt, validation},
+};
 use anyhow::{Ok, Result, anyhow};
 use chacha20poly1305::Key;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use uuid::Uuid;
@@ -15,6 +20,8 @@ pub struct Recruitment {
     pub phone_number: Option<String>,
     pub description: Option<String>,
     pub created_by: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 impl Recruitment {
@@ -58,6 +65,10 @@ impl Recruitment {
         let phone_hash = encrypt::hash_value(hmac_secret, phone);
         let (phone_enc, phone_nonce) = encrypt::encrypt_value(key, phone);
 
+        let mut conn = db.pool.acquire().await?;
+        let created_by =
+            User::normalize_created_by(&mut conn, recruitment.created_by.as_deref()).await?;
+
         let row = sqlx::query!(
             "INSERT INTO recruitment(full_name, email_enc, email_nonce, email_hash, phone_number_enc, phone_number_nonce, phone_number_hash, description, created_by)
              VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9)
@@ -70,9 +81,9 @@ impl Recruitment {
             phone_nonce,
             phone_hash,
             recruitment.description,
-            recruitment.created_by
+            created_by
         )
-        .fetch_one(&db.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         Ok(row.uuid.unwrap())
@@ -140,12 +151,15 @@ impl Recruitment {
 
     pub async fn get_all(db: &Database, key: &Key) -> Result<Vec<Recruitment>> {
         let rows = sqlx::query!(
-            "SELECT uuid, full_name, email_enc, email_nonce, phone_number_enc, phone_number_nonce, description, created_by
+            "SELECT uuid, full_name, email_enc, email_nonce, phone_number_enc, phone_number_nonce, description, created_by, created_at, updated_at
              FROM recruitment
-             ORDER BY full_name ASC"
+             ORDER BY full_name ASC
+             LIMIT $1",
+            validation::decrypt_fetch_limit()
         )
         .fetch_all(&db.pool)
         .await?;
+        validation::ensure_decrypt_limit(rows.len())?;
 
         Ok(rows
             .into_iter()
@@ -160,6 +174,8 @@ impl Recruitment {
                 ),
                 description: Some(row.description),
                 created_by: Some(row.created_by),
+                created_at: Some(row.created_at),
+                updated_at: Some(row.updated_at),
             })
             .collect())
     }
@@ -170,7 +186,7 @@ impl Recruitment {
         recruitment_uuid: Uuid,
     ) -> Result<Recruitment> {
         let row = sqlx::query!(
-            "SELECT uuid, full_name, email_enc, email_nonce, phone_number_enc, phone_number_nonce, description, created_by
+            "SELECT uuid, full_name, email_enc, email_nonce, phone_number_enc, phone_number_nonce, description, created_by, created_at, updated_at
              FROM recruitment
              WHERE uuid = $1",
             recruitment_uuid
@@ -189,6 +205,8 @@ impl Recruitment {
             ),
             description: Some(row.description),
             created_by: Some(row.created_by),
+            created_at: Some(row.created_at),
+            updated_at: Some(row.updated_at),
         })
     }
 
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{database::Database, utils::settings};
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Setting {
+    pub key: String,
+    // Stored override, `None` while the env variable (or the built-in default) applies
+    pub value: Option<String>,
+    pub effective_value: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl Setting {
+    /// Reloads every stored override into the in-process settings cache.
+    pub async fn refresh(db: &Database) -> Result<()> {
+        let rows = sqlx::query!("SELECT key, value FROM settings")
+            .fetch_all(&db.pool)
+            .await?;
+
+        settings::replace_overrides(
+            rows.into_iter()
+                .map(|row| (row.key, row.value))
+                .collect::<HashMap<_, _>>(),
+        );
+        Ok(())
+    }
+
+    pub async fn get_all(db: &Database) -> Result<Vec<Self>> {
+        let rows = sqlx::query!("SELECT key, value, updated_at FROM settings")
+            .fetch_all(&db.pool)
+            .await?;
+        let stored: HashMap<_, _> = rows
+            .into_iter()
+            .map(|row| (row.key, (row.value, row.updated_at)))
+            .collect();
+
+        Ok(settings::TUNABLE_SETTINGS
+            .iter()
+            .map(|(key, _)| {
+                let (value, updated_at) = stored.get(*key).cloned().unzip();
+                Self {
+                    key: key.to_string(),
+                    value,
+                    effective_value: settings::get(key),
+                    updated_at,
+                }
+            })
+            .collect())
+    }
+
+    /// Stores an override, or removes it when `value` is `None`, then refreshes the cache.
+    pub async fn save(db: &Database, user_id: i32, key: &str, value: Option<String>) -> Result<()> {
+        match value.map(|value| value.trim().to_string()) {
+            Some(value) => {
+                settings::validate(key, &value)?;
+                sqlx::query!(
+                    "INSERT INTO settings(key, value, updated_by)
+                     VALUES($1, $2, $3)
+                     ON CONFLICT (key) DO UPDATE
+                     SET value = EXCLUDED.value,
+                         updated_by = EXCLUDED.updated_by,
+                         updated_at = NOW()",
+                    key,
+                    value,
+                    user_id
+                )
+                .execute(&db.pool)
+                .await?;
+            }
+            None => {
+                settings::ensure_tunable(key)?;
+                sqlx::query!("DELETE FROM settings WHERE key = $1", key)
+                    .execute(&db.pool)
+                    .await?;
+            }
+        }
+
+        Self::refresh(db).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::*;
+    use crate::utils::{redis::Token, validation};
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn saved_setting_reaches_its_check() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let username = Token::generate_token();
+        let user_id = sqlx::query_scalar!(
+            "INSERT INTO users(email, username, password, user_role)
+             VALUES($1, $2, '', 'Leader') RETURNING id",
+            format!("{username}@example.com"),
+            username
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        let previous =
+            sqlx::query_scalar!("SELECT value FROM settings WHERE key = 'MAX_CHART_SPAN_DAYS'")
+                .fetch_optional(&db.pool)
+                .await
+                .unwrap();
+        let end = Utc::now().naive_utc();
+        let start = end - Duration::days(20);
+
+        Setting::save(&db, user_id, "MAX_CHART_SPAN_DAYS", Some("10".to_string()))
+            .await
+            .unwrap();
+        let narrowed = validation::validate_chart_span(start, end);
+        Setting::save(&db, user_id, "MAX_CHART_SPAN_DAYS", Some("30".to_string()))
+            .await
+            .unwrap();
+        let widened = validation::validate_chart_span(start, end);
+        let invalid =
+            Setting::save(&db, user_id, "MAX_CHART_SPAN_DAYS", Some("0".to_string())).await;
+
+        Setting::save(&db, user_id, "MAX_CHART_SPAN_DAYS", previous)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        assert!(narrowed.is_err());
+        assert!(widened.is_ok());
+        assert!(invalid.is_err());
+    }
+}
@@ -1,15 +1,41 @@
-use std::env;
+use std::{collections::HashMap, env};
 
 use anyhow::{Ok, Result, anyhow};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use futures_util::try_join;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use sqlx::{FromRow, prelude::Type};
+use strum::Display;
 use uuid::Uuid;
 
 use crate::{
     database::Database,
-    models::{dto::ManagerNameDto, user_info::UserInfo},
-    utils::{jwt::generate_jwt_token, password_hashing},
+    models::{
+        audit_log::{AuditAction, AuditLog},
+        contract::Contract,
+        customer::Customer,
+        dto::{
+            ActivityDayDto, AssignableUserDto, GlobalSearchDto, KpiDto, ManagerNameDto, SessionDto,
+            SessionTokensDto,
+        },
+        intervention_task::InterventionTask,
+        lead::Lead,
+        recommendation::CustomerRecommendation,
+        user_date::UserMeetDate,
+        user_info::UserInfo,
+    },
+    utils::{
+        encrypt::HmacSecret,
+        error::ApiError,
+        jwt::{
+            IMPERSONATION_EXPIRE_SECONDS, REFRESH_TOKEN_EXPIRE_SECONDS, access_token_seconds,
+            generate_impersonation_token, generate_jwt_token,
+        },
+        password_hashing,
+        redis::{OtpStatus, Redis, Token},
+        validation,
+    },
 };
 
 #[skip_serializing_none]
@@ -23,6 +49,9 @@ pub struct User {
     pub password: Option<String>,
     pub user_role: Option<UserRole>,
     pub manager_uuid: Option<Uuid>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Type, PartialEq, Eq, PartialOrd, Ord)]
@@ -42,11 +71,43 @@ impl From<String> for UserRole {
     }
 }
 
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Display)]
+pub enum ReassignEntity {
+    Customers,
+    Contracts,
+    Leads,
+    InterventionTasks,
+    Meetings,
+    Recommendations,
+}
+
+impl ReassignEntity {
+    pub const ALL: [ReassignEntity; 6] = [
+        ReassignEntity::Customers,
+        ReassignEntity::Contracts,
+        ReassignEntity::Leads,
+        ReassignEntity::InterventionTasks,
+        ReassignEntity::Meetings,
+        ReassignEntity::Recommendations,
+    ];
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ListingScope {
+    #[default]
+    Own,
+    Team,
+}
+
 #[derive(Serialize)]
 pub enum SignInResult {
-    UserToken(String),
+    UserToken(SessionTokensDto),
+    TwoFactorRequired(Uuid),
 }
 
+const OTP_EXPIRE_SECONDS: u64 = 300;
+const OTP_MAX_ATTEMPTS: i64 = 5;
+
 impl User {
     pub async fn get_id_by_uuid(db: &Database, user_uuid: Option<Uuid>) -> Result<Option<i32>> {
         let user = sqlx::query_scalar!("SELECT id FROM users WHERE uuid = $1", user_uuid)
@@ -65,11 +126,18 @@ impl User {
     }
 
     pub async fn get_role(db: &Database, user_id: i32) -> Result<UserRole> {
+        if let Some(user_role) = db.role_cache.get(&user_id) {
+            return Ok(user_role);
+        }
+
         let user = sqlx::query!("SELECT user_role FROM users WHERE id = $1", user_id)
             .fetch_one(&db.pool)
             .await?;
 
-        Ok(UserRole::from(user.user_role))
+        let user_role = UserRole::from(user.user_role);
+        db.role_cache.insert(user_id, user_role.clone());
+
+        Ok(user_role)
     }
 
     pub async fn require_role(db: &Database, min_role: UserRole, user_id: i32) -> Result<()> {
@@ -81,6 +149,202 @@ impl User {
         Err(anyhow!("Ehez a folyamathoz nincs jogosultságod!"))
     }
 
+    pub async fn require_access(db: &Database, user_id: i32, target_uuid: Uuid) -> Result<()> {
+        let target = sqlx::query!(
+            "SELECT id, manager_id FROM users WHERE uuid = $1",
+            target_uuid
+        )
+        .fetch_optional(&db.pool)
+        .await?
+        .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        if target.id == user_id {
+            return Ok(());
+        }
+
+        match Self::get_role(db, user_id).await? {
+            UserRole::Leader => Ok(()),
+            UserRole::Manager if target.manager_id == Some(user_id) => Ok(()),
+            _ => Err(anyhow!("Ehez a folyamathoz nincs jogosultságod!")),
+        }
+    }
+
+    /// Resolves the owners whose records a listing should include: the user alone for
+    /// `Own`. For `Team`, a manager gets themselves plus their direct reports (not their
+    /// reports' reports) and a leader gets every user.
+    pub async fn get_scoped_ids(
+        db: &Database,
+        user_uuid: Uuid,
+        scope: ListingScope,
+    ) -> Result<Vec<i32>> {
+        let user_id = Self::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        if scope == ListingScope::Own {
+            return Ok(vec![user_id]);
+        }
+
+        if Self::get_role(db, user_id).await? < UserRole::Manager {
+            return Err(ApiError::Forbidden(
+                "Csapat nézet csak menedzsereknek érhető el!".to_string(),
+            )
+            .into());
+        }
+
+        let ids = sqlx::query_scalar!(
+            "SELECT o.id
+             FROM users u
+             JOIN users o ON (
+                u.user_role = 'Leader' OR (u.user_role = 'Manager' AND (o.manager_id = u.id OR o.id = u.id))
+             )
+             WHERE u.id = $1",
+            user_id
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(ids)
+    }
+
+    /// Managers and leaders see their team, everyone else only their own records.
+    pub async fn get_authority_ids(db: &Database, user_id: i32) -> Result<Vec<i32>> {
+        let user_uuid = Self::get_uuid_by_id(db, user_id)
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+        let scope = if Self::get_role(db, user_id).await? >= UserRole::Manager {
+            ListingScope::Team
+        } else {
+            ListingScope::Own
+        };
+
+        Self::get_scoped_ids(db, user_uuid, scope).await
+    }
+
+    /// Resolves `created_by` to the matching user's canonical full name. A missing name
+    /// falls back to `CREATED_BY_FALLBACK` when configured, an unknown one is rejected.
+    pub async fn normalize_created_by(
+        conn: &mut sqlx::PgConnection,
+        created_by: Option<&str>,
+    ) -> Result<String> {
+        let created_by = created_by
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .or_else(validation::created_by_fallback)
+            .ok_or_else(|| ApiError::Validation("A rögzítő megadása kötelező!".to_string()))?;
+
+        let full_name = sqlx::query_scalar!(
+            "SELECT full_name FROM user_info WHERE LOWER(full_name) = LOWER($1) LIMIT 1",
+            created_by.trim()
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        full_name
+            .ok_or_else(|| ApiError::Validation(format!("Ismeretlen rögzítő: {created_by}")).into())
+    }
+
+    /// Rewrites stored `created_by` values to the canonical full name of the user they
+    /// name. Values that match no user are left untouched for review.
+    pub async fn backfill_created_by(db: &Database) -> Result<u64> {
+        let mut tx = db.pool.begin().await?;
+        let mut updated = 0;
+
+        updated += sqlx::query!(
+            "UPDATE customers t SET created_by = ui.full_name
+             FROM user_info ui
+             WHERE LOWER(TRIM(t.created_by)) = LOWER(ui.full_name)
+               AND t.created_by <> ui.full_name"
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        updated += sqlx::query!(
+            "UPDATE customer_leads t SET created_by = ui.full_name
+             FROM user_info ui
+             WHERE LOWER(TRIM(t.created_by)) = LOWER(ui.full_name)
+               AND t.created_by <> ui.full_name"
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        updated += sqlx::query!(
+            "UPDATE user_dates t SET created_by = ui.full_name
+             FROM user_info ui
+             WHERE LOWER(TRIM(t.created_by)) = LOWER(ui.full_name)
+               AND t.created_by <> ui.full_name"
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        updated += sqlx::query!(
+            "UPDATE customer_contracts t SET created_by = ui.full_name
+             FROM user_info ui
+             WHERE LOWER(TRIM(t.created_by)) = LOWER(ui.full_name)
+               AND t.created_by <> ui.full_name"
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        updated += sqlx::query!(
+            "UPDATE customer_intervention_tasks t SET created_by = ui.full_name
+             FROM user_info ui
+             WHERE LOWER(TRIM(t.created_by)) = LOWER(ui.full_name)
+               AND t.created_by <> ui.full_name"
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        updated += sqlx::query!(
+            "UPDATE customer_recommendations t SET created_by = ui.full_name
+             FROM user_info ui
+             WHERE LOWER(TRIM(t.created_by)) = LOWER(ui.full_name)
+               AND t.created_by <> ui.full_name"
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    async fn ensure_codes_available(
+        db: &Database,
+        info: &UserInfo,
+        user_id: Option<i32>,
+    ) -> Result<()> {
+        if let Some(hufa_code) = &info.hufa_code {
+            let taken = sqlx::query_scalar!(
+                "SELECT id FROM user_info WHERE hufa_code = $1 AND user_id IS DISTINCT FROM $2",
+                hufa_code,
+                user_id
+            )
+            .fetch_optional(&db.pool)
+            .await?;
+            if taken.is_some() {
+                return Err(ApiError::Conflict("Ez a HUFA kód már foglalt".to_string()).into());
+            }
+        }
+
+        if let Some(agent_code) = &info.agent_code {
+            let taken = sqlx::query_scalar!(
+                "SELECT id FROM user_info WHERE agent_code = $1 AND user_id IS DISTINCT FROM $2",
+                agent_code,
+                user_id
+            )
+            .fetch_optional(&db.pool)
+            .await?;
+            if taken.is_some() {
+                return Err(ApiError::Conflict("Ez az ügynökkód már foglalt".to_string()).into());
+            }
+        }
+
+        Ok(())
+    }
+
     async fn is_exists(db: &Database, user: &User) -> Result<bool> {
         let is_exists = sqlx::query!(
             "SELECT id FROM users
@@ -108,10 +372,17 @@ impl User {
 }
 
 impl User {
-    pub async fn create(db: &Database, new_user: User) -> Result<()> {
+    pub async fn create(db: &Database, mut new_user: User) -> Result<()> {
         if User::is_exists(db, &new_user).await? {
             return Err(anyhow!("Ez az e-mail cím vagy felhasználónév már létezik."));
         }
+        new_user.info.normalize_codes();
+        Self::ensure_codes_available(db, &new_user.info, None).await?;
+
+        let manager_id = match new_user.manager_uuid {
+            Some(manager_uuid) => Some(Self::resolve_manager_id(db, manager_uuid).await?),
+            None => None,
+        };
 
         let hashed_password = password_hashing::hash_password(&new_user.password.unwrap());
 
@@ -123,7 +394,7 @@ impl User {
             new_user.username,
             hashed_password,
             if new_user.manager_uuid.is_some() { "Agent" } else { "Manager" },
-            Self::get_id_by_uuid(db, new_user.manager_uuid).await?
+            manager_id
         )
         .fetch_one(&mut *tx)
         .await?;
@@ -145,9 +416,196 @@ impl User {
         Ok(())
     }
 
-    pub async fn sign_in_with_username(db: &Database, user: User) -> Result<SignInResult> {
+    // The new user's uuid only exists after the insert, so requiring an existing
+    // Manager/Leader here also rules out a self reference
+    async fn resolve_manager_id(db: &Database, manager_uuid: Uuid) -> Result<i32> {
+        let manager = sqlx::query!(
+            "SELECT id, user_role FROM users WHERE uuid = $1",
+            manager_uuid
+        )
+        .fetch_optional(&db.pool)
+        .await?
+        .ok_or_else(|| ApiError::Validation("A megadott vezető nem található!".to_string()))?;
+
+        if UserRole::from(manager.user_role) < UserRole::Manager {
+            return Err(
+                ApiError::Validation("A megadott felhasználó nem vezető!".to_string()).into(),
+            );
+        }
+        Ok(manager.id)
+    }
+
+    // Password resets only reach users ranked below the actor, not their peers
+    async fn require_outranks(db: &Database, actor_id: i32, target_uuid: Uuid) -> Result<i32> {
+        let target = sqlx::query!(
+            "SELECT id, user_role FROM users WHERE uuid = $1",
+            target_uuid
+        )
+        .fetch_optional(&db.pool)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Felhasználó nem található!".to_string()))?;
+
+        if UserRole::from(target.user_role) >= Self::get_role(db, actor_id).await? {
+            return Err(ApiError::Forbidden(
+                "Csak alacsonyabb jogosultságú felhasználó jelszavát állíthatod vissza!"
+                    .to_string(),
+            )
+            .into());
+        }
+        Ok(target.id)
+    }
+
+    pub async fn admin_reset_password(
+        db: &Database,
+        actor_id: i32,
+        target_uuid: Uuid,
+        new_password: Option<String>,
+    ) -> Result<Option<String>> {
+        let (password, temporary_password) = match new_password {
+            Some(password) => (password, None),
+            None => {
+                let password = Self::generate_password();
+                (password.clone(), Some(password))
+            }
+        };
+        password_hashing::validate_password_policy(&password)?;
+        let user_id = Self::require_outranks(db, actor_id, target_uuid).await?;
+
+        let hashed_password = password_hashing::hash_password(&password);
+        sqlx::query!(
+            "UPDATE users SET password = $1, must_change_password = $3 WHERE id = $2",
+            hashed_password,
+            user_id,
+            temporary_password.is_some()
+        )
+        .execute(&db.pool)
+        .await?;
+        db.password_change_cache.invalidate(&user_id);
+
+        // Whoever knew the old password must not keep a session or refresh token
+        let mut con = db.redis.clone();
+        Redis::revoke_sessions(&mut con, user_id, None).await?;
+
+        AuditLog::record(
+            db,
+            actor_id,
+            AuditAction::PasswordReset,
+            Some(target_uuid),
+            None,
+        )
+        .await?;
+
+        Ok(temporary_password)
+    }
+
+    pub async fn generate_temp_password(
+        db: &Database,
+        actor_id: i32,
+        target_uuid: Uuid,
+    ) -> Result<String> {
+        let user_id = Self::require_outranks(db, actor_id, target_uuid).await?;
+        let password = Self::generate_password();
+        let hashed_password = password_hashing::hash_password(&password);
+        sqlx::query!(
+            "UPDATE users SET password = $1, must_change_password = TRUE WHERE id = $2",
+            hashed_password,
+            user_id
+        )
+        .execute(&db.pool)
+        .await?;
+        db.password_change_cache.invalidate(&user_id);
+
+        let mut con = db.redis.clone();
+        Redis::revoke_sessions(&mut con, user_id, None).await?;
+
+        AuditLog::record(
+            db,
+            actor_id,
+            AuditAction::TemporaryPasswordGenerated,
+            Some(target_uuid),
+            None,
+        )
+        .await?;
+
+        Ok(password)
+    }
+
+    /// Other sessions of the user are revoked, only `current_sid` stays signed in.
+    pub async fn change_password(
+        db: &Database,
+        user_id: i32,
+        current_sid: Option<&str>,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<()> {
+        let user = sqlx::query!("SELECT uuid, password FROM users WHERE id = $1", user_id)
+            .fetch_optional(&db.pool)
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Felhasználó nem található!".to_string()))?;
+
+        if !password_hashing::verify_password(current_password, &user.password) {
+            return Err(ApiError::Validation("Helytelen jelszó!".to_string()).into());
+        }
+        password_hashing::validate_password_policy(new_password)?;
+
+        sqlx::query!(
+            "UPDATE users SET password = $1, must_change_password = FALSE WHERE id = $2",
+            password_hashing::hash_password(new_password),
+            user_id
+        )
+        .execute(&db.pool)
+        .await?;
+
+        db.password_change_cache.invalidate(&user_id);
+
+        let mut con = db.redis.clone();
+        Redis::revoke_sessions(&mut con, user_id, current_sid).await?;
+
+        AuditLog::record(db, user_id, AuditAction::PasswordChanged, user.uuid, None).await?;
+
+        Ok(())
+    }
+
+    pub async fn ensure_password_changed(db: &Database, user_id: i32) -> Result<()> {
+        let must_change_password = match db.password_change_cache.get(&user_id) {
+            Some(must_change_password) => must_change_password,
+            None => {
+                let must_change_password = sqlx::query_scalar!(
+                    "SELECT must_change_password FROM users WHERE id = $1",
+                    user_id
+                )
+                .fetch_optional(&db.pool)
+                .await?
+                .ok_or_else(|| ApiError::Unauthorized("Felhasználó nem található!".to_string()))?;
+                db.password_change_cache
+                    .insert(user_id, must_change_password);
+                must_change_password
+            }
+        };
+
+        if must_change_password {
+            return Err(ApiError::Forbidden("Jelszócsere szükséges".to_string()).into());
+        }
+
+        Ok(())
+    }
+
+    fn generate_password() -> String {
+        loop {
+            let token = Token::generate_token();
+            if password_hashing::validate_password_policy(&token).is_ok() {
+                break token;
+            }
+        }
+    }
+
+    pub async fn sign_in_with_username(
+        db: &Database,
+        user: User,
+        device: Option<String>,
+    ) -> Result<SignInResult> {
         let user_data = sqlx::query!(
-            "SELECT id as \"id!\", username, password FROM users WHERE username = $1",
+            "SELECT id as \"id!\", uuid, email, username, password, two_factor_enabled FROM users WHERE username = $1",
             user.username
         )
         .fetch_optional(&db.pool)
@@ -157,13 +615,252 @@ impl User {
             return Err(anyhow!("Felhasználó nem található"));
         };
 
-        if password_hashing::verify_password(&user.password.unwrap(), &hashed_user.password) {
-            Ok(SignInResult::UserToken(
-                generate_jwt_token(hashed_user.id as usize, env::var("AUTH_SECRET").unwrap()).await,
-            ))
-        } else {
-            Err(anyhow!("Helytelen jelszó!"))
+        if !password_hashing::verify_password(&user.password.unwrap(), &hashed_user.password) {
+            return Err(anyhow!("Helytelen jelszó!"));
+        }
+
+        if hashed_user.two_factor_enabled {
+            let code = Token::generate_six_digit_number();
+            let mut con = db.redis.clone();
+            Redis::set_otp(&mut con, hashed_user.id, &code, OTP_EXPIRE_SECONDS).await?;
+            Self::send_otp(hashed_user.id, &hashed_user.email, &code);
+
+            return Ok(SignInResult::TwoFactorRequired(hashed_user.uuid.unwrap()));
+        }
+
+        Self::issue_token(db, hashed_user.id, device).await
+    }
+
+    pub async fn verify_otp(
+        db: &Database,
+        user_uuid: Uuid,
+        code: &str,
+        device: Option<String>,
+    ) -> Result<SignInResult> {
+        let user_id = Self::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let mut con = db.redis.clone();
+        match Redis::verify_otp(&mut con, user_id, code.trim(), OTP_MAX_ATTEMPTS).await? {
+            OtpStatus::Valid => Self::issue_token(db, user_id, device).await,
+            OtpStatus::Invalid => Err(anyhow!("Helytelen ellenőrző kód!")),
+            OtpStatus::Expired => Err(anyhow!("Az ellenőrző kód lejárt, jelentkezz be újra!")),
+            OtpStatus::TooManyAttempts => Err(anyhow!(
+                "Túl sok sikertelen próbálkozás, jelentkezz be újra!"
+            )),
+        }
+    }
+
+    pub async fn set_two_factor(db: &Database, user_id: i32, enabled: bool) -> Result<()> {
+        sqlx::query!(
+            "UPDATE users SET two_factor_enabled = $1 WHERE id = $2",
+            enabled,
+            user_id
+        )
+        .execute(&db.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn issue_token(
+        db: &Database,
+        user_id: i32,
+        device: Option<String>,
+    ) -> Result<SignInResult> {
+        sqlx::query!(
+            "UPDATE users SET last_login_at = NOW() WHERE id = $1",
+            user_id
+        )
+        .execute(&db.pool)
+        .await?;
+
+        let sid = Token::generate_token();
+        let refresh_secret = Token::generate_token();
+        let mut con = db.redis.clone();
+        Redis::create_session(
+            &mut con,
+            user_id,
+            &sid,
+            device.as_deref().unwrap_or_default(),
+            Utc::now().timestamp(),
+            REFRESH_TOKEN_EXPIRE_SECONDS,
+        )
+        .await?;
+        Redis::set_refresh_token(&mut con, &sid, &refresh_secret).await?;
+
+        Ok(SignInResult::UserToken(
+            Self::session_tokens(user_id, sid, &refresh_secret).await,
+        ))
+    }
+
+    // Refresh tokens are "<session id>.<secret>", the secret is kept in the session
+    async fn session_tokens(user_id: i32, sid: String, refresh_secret: &str) -> SessionTokensDto {
+        let refresh_token = format!("{sid}.{refresh_secret}");
+        let access_token = generate_jwt_token(
+            user_id as usize,
+            Some(sid),
+            env::var("AUTH_SECRET").unwrap(),
+        )
+        .await;
+
+        SessionTokensDto {
+            access_token,
+            refresh_token,
+            expires_at: Utc::now() + chrono::Duration::seconds(access_token_seconds()),
+        }
+    }
+
+    /// Mints a new access token for the session behind the refresh token and rotates the
+    /// refresh token. Logging out or revoking the session invalidates it as well.
+    pub async fn refresh_session(db: &Database, refresh_token: &str) -> Result<SessionTokensDto> {
+        let invalid =
+            || ApiError::Unauthorized("A frissítő token érvénytelen vagy lejárt!".to_string());
+        let (sid, secret) = refresh_token.trim().split_once('.').ok_or_else(invalid)?;
+
+        let new_secret = Token::generate_token();
+        let mut con = db.redis.clone();
+        let user_id = Redis::rotate_refresh_token(&mut con, sid, secret, &new_secret)
+            .await?
+            .ok_or_else(invalid)?;
+
+        // The session outlives a deleted user unless something removes it
+        if !Self::is_exists_by_id(db, user_id).await? {
+            Redis::revoke_sessions(&mut con, user_id, None).await?;
+            return Err(invalid().into());
+        }
+
+        Ok(Self::session_tokens(user_id, sid.to_string(), &new_secret).await)
+    }
+
+    /// Issues a short lived token for a lower ranked user, the real actor stays in the
+    /// claims and the session is listed (and revocable) under the actor.
+    pub async fn impersonate(
+        db: &Database,
+        actor_id: i32,
+        target_uuid: Uuid,
+    ) -> Result<(String, DateTime<Utc>)> {
+        let target = sqlx::query!(
+            "SELECT id, user_role FROM users WHERE uuid = $1",
+            target_uuid
+        )
+        .fetch_optional(&db.pool)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Felhasználó nem található!".to_string()))?;
+
+        if target.id == actor_id {
+            return Err(ApiError::Validation(
+                "Saját magadat nem személyesítheted meg!".to_string(),
+            )
+            .into());
+        }
+        if UserRole::from(target.user_role) >= Self::get_role(db, actor_id).await? {
+            return Err(ApiError::Forbidden(
+                "Csak alacsonyabb jogosultságú felhasználót személyesíthetsz meg!".to_string(),
+            )
+            .into());
+        }
+
+        let sid = Token::generate_token();
+        let issued_at = Utc::now();
+        let mut con = db.redis.clone();
+        Redis::create_session(
+            &mut con,
+            actor_id,
+            &sid,
+            &format!("Megszemélyesítés: {target_uuid}"),
+            issued_at.timestamp(),
+            IMPERSONATION_EXPIRE_SECONDS,
+        )
+        .await?;
+        AuditLog::record(
+            db,
+            actor_id,
+            AuditAction::Impersonation,
+            Some(target_uuid),
+            None,
+        )
+        .await?;
+
+        let token = generate_impersonation_token(
+            target.id as usize,
+            actor_id as usize,
+            sid,
+            env::var("AUTH_SECRET").unwrap(),
+        )
+        .await;
+        Ok((
+            token,
+            issued_at + chrono::Duration::seconds(IMPERSONATION_EXPIRE_SECONDS),
+        ))
+    }
+
+    pub async fn ensure_session_active(db: &Database, sid: &str) -> Result<()> {
+        let mut con = db.redis.clone();
+        if !Redis::is_session_active(&mut con, sid).await? {
+            return Err(ApiError::Unauthorized("A munkamenet lejárt!".to_string()).into());
+        }
+        Ok(())
+    }
+
+    pub async fn get_sessions(
+        db: &Database,
+        user_id: i32,
+        current_sid: Option<&str>,
+    ) -> Result<Vec<SessionDto>> {
+        let mut con = db.redis.clone();
+        let timestamp = |fields: &HashMap<String, String>, name: &str| {
+            fields
+                .get(name)
+                .and_then(|value| value.parse().ok())
+                .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        };
+
+        let mut sessions: Vec<SessionDto> = Redis::get_sessions(&mut con, user_id)
+            .await?
+            .into_iter()
+            .map(|(sid, fields)| SessionDto {
+                current: current_sid == Some(sid.as_str()),
+                device: fields.get("device").filter(|d| !d.is_empty()).cloned(),
+                issued_at: timestamp(&fields, "issued_at"),
+                expires_at: timestamp(&fields, "expires_at"),
+                session_id: sid,
+            })
+            .collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.issued_at));
+
+        Ok(sessions)
+    }
+
+    /// Ends the session behind the token, so the token is rejected from now on.
+    pub async fn logout(db: &Database, user_id: i32, sid: Option<&str>) -> Result<()> {
+        // Tokens issued before sessions were tracked can't be revoked, they only expire
+        let sid = sid.ok_or_else(|| {
+            ApiError::Validation("A token nem tartozik munkamenethez!".to_string())
+        })?;
+
+        let mut con = db.redis.clone();
+        if !Redis::revoke_session(&mut con, user_id, sid).await? {
+            return Err(ApiError::Unauthorized("A munkamenet lejárt!".to_string()).into());
         }
+        Ok(())
+    }
+
+    /// Revokes every session of the user except the one making the request.
+    pub async fn revoke_other_sessions(
+        db: &Database,
+        user_id: i32,
+        current_sid: Option<&str>,
+    ) -> Result<usize> {
+        let mut con = db.redis.clone();
+        Ok(Redis::revoke_sessions(&mut con, user_id, current_sid).await?)
+    }
+
+    // Delivery stub until an email/SMS provider is wired in. The code must never reach
+    // the logs, anyone reading them could pass the second factor.
+    fn send_otp(user_id: i32, _email: &str, _code: &str) {
+        log::info!("Ellenőrző kód elküldve, felhasználó: {user_id}");
     }
 
     pub async fn get_users(db: &Database, user_id: i32) -> Result<Vec<User>> {
@@ -176,6 +873,7 @@ impl User {
                     u.email,
                     u.username,
                     u.user_role,
+                    u.last_login_at,
                     m.uuid as manager_uuid,
                     ui.id              AS ui_id,
                     ui.full_name       AS ui_full_name,
@@ -204,17 +902,59 @@ impl User {
                 info: UserInfo {
                     full_name: Some(row.ui_full_name),
                     phone_number: Some(row.ui_phone_number),
-                    hufa_code: Some(row.ui_hufa_code),
-                    agent_code: Some(row.ui_agent_code),
+                    hufa_code: row.ui_hufa_code,
+                    agent_code: row.ui_agent_code,
                     ..Default::default()
                 },
                 manager_uuid: row.manager_uuid,
+                last_login_at: row.last_login_at,
                 ..Default::default()
             })
             .collect();
         Ok(users)
     }
 
+    pub async fn export_csv(db: &Database, user_id: i32) -> Result<Vec<u8>> {
+        let users = Self::get_users(db, user_id).await?;
+        let manager_names: HashMap<Uuid, String> = users
+            .iter()
+            .filter_map(|user| Some((user.uuid?, user.info.full_name.clone()?)))
+            .collect();
+
+        let mut writer = csv::Writer::from_writer(vec![]);
+        writer.write_record([
+            "uuid",
+            "full_name",
+            "username",
+            "email",
+            "role",
+            "manager",
+            "hufa_code",
+            "agent_code",
+        ])?;
+        for user in users {
+            let manager = user
+                .manager_uuid
+                .and_then(|uuid| manager_names.get(&uuid).cloned())
+                .unwrap_or_default();
+
+            writer.write_record([
+                user.uuid.map(|uuid| uuid.to_string()).unwrap_or_default(),
+                user.info.full_name.unwrap_or_default(),
+                user.username.unwrap_or_default(),
+                user.email.unwrap_or_default(),
+                user.user_role
+                    .map(|role| format!("{role:?}"))
+                    .unwrap_or_default(),
+                manager,
+                user.info.hufa_code.unwrap_or_default(),
+                user.info.agent_code.unwrap_or_default(),
+            ])?;
+        }
+
+        Ok(writer.into_inner()?)
+    }
+
     pub async fn get_users_by_id(db: &Database, user_uuid: Uuid) -> Result<Vec<User>> {
         let user_id = Self::get_id_by_uuid(db, Some(user_uuid)).await?.unwrap();
         if !User::is_exists_by_id(db, user_id).await? {
@@ -256,8 +996,8 @@ impl User {
                 info: UserInfo {
                     full_name: Some(row.ui_full_name),
                     phone_number: Some(row.ui_phone_number),
-                    hufa_code: Some(row.ui_hufa_code),
-                    agent_code: Some(row.ui_agent_code),
+                    hufa_code: row.ui_hufa_code,
+                    agent_code: row.ui_agent_code,
                     ..Default::default()
                 },
                 manager_uuid: row.manager_uuid,
@@ -278,7 +1018,9 @@ impl User {
                     ui.full_name       AS ui_full_name,
                     ui.phone_number    AS ui_phone_number,
                     ui.hufa_code       AS ui_hufa_code,
-                    ui.agent_code      AS ui_agent_code
+                    ui.agent_code      AS ui_agent_code,
+                    u.created_at       AS user_created_at,
+                    u.updated_at       AS user_updated_at
              FROM users u
              JOIN user_info ui ON ui.user_id = u.id
              WHERE user_id = $1",
@@ -292,20 +1034,132 @@ impl User {
             info: UserInfo {
                 full_name: Some(row.ui_full_name),
                 phone_number: Some(row.ui_phone_number),
-                hufa_code: Some(row.ui_hufa_code),
-                agent_code: Some(row.ui_agent_code),
+                hufa_code: row.ui_hufa_code,
+                agent_code: row.ui_agent_code,
                 ..Default::default()
             },
             user_role: Some(UserRole::from(row.user_user_role)),
+            created_at: Some(row.user_created_at),
+            updated_at: Some(row.user_updated_at),
             ..Default::default()
         })
     }
 
-    pub async fn modify_info(db: &Database, user_uuid: Uuid, user: User) -> Result<()> {
+    /// Records the user created per day, days without activity are returned with zeros.
+    pub async fn get_activity_timeline(
+        db: &Database,
+        user_uuid: Uuid,
+        start_date: NaiveDateTime,
+        end_date: NaiveDateTime,
+    ) -> Result<Vec<ActivityDayDto>> {
+        let user_id = Self::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let rows = sqlx::query!(
+            r#"SELECT
+                d.day::DATE AS "date!",
+                (SELECT COUNT(*) FROM customers c
+                 WHERE c.user_id = $1 AND c.created_at::DATE = d.day::DATE) AS "customers!",
+                (SELECT COUNT(*) FROM customer_contracts cc
+                 WHERE cc.user_id = $1 AND cc.created_at::DATE = d.day::DATE) AS "contracts!",
+                (SELECT COUNT(*) FROM customer_leads l
+                 WHERE l.user_id = $1 AND l.created_at::DATE = d.day::DATE) AS "leads!",
+                (SELECT COUNT(*) FROM user_dates ud
+                 WHERE ud.user_id = $1 AND ud.created_at::DATE = d.day::DATE) AS "meetings!"
+            FROM generate_series($2::TIMESTAMP::DATE, $3::TIMESTAMP::DATE, INTERVAL '1 day') AS d(day)
+            ORDER BY d.day"#,
+            user_id,
+            start_date,
+            end_date
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ActivityDayDto {
+                date: row.date,
+                customers: row.customers,
+                contracts: row.contracts,
+                leads: row.leads,
+                meetings: row.meetings,
+            })
+            .collect())
+    }
+
+    /// Assembles the app's opening dashboard from the existing production, meeting and task
+    /// queries, run concurrently.
+    pub async fn get_kpis(db: &Database, user_uuid: Uuid) -> Result<KpiDto> {
+        let (
+            production_value,
+            production_count,
+            (this_month_value, last_month_value),
+            meetings,
+            open_task_count,
+        ) = try_join!(
+            Contract::get_production_value_by_user_uuid(db, user_uuid),
+            Contract::get_production_count_by_user_uuid(db, user_uuid),
+            Contract::get_monthly_production_values_by_user_uuid(db, user_uuid),
+            UserMeetDate::get_is_completed_chart_by_user_uuid(db, user_uuid),
+            InterventionTask::count_open_by_user_uuid(db, user_uuid),
+        )?;
+        let total_meetings = meetings.yes + meetings.no;
+
+        Ok(KpiDto {
+            production_value,
+            production_count,
+            this_month_value,
+            last_month_value,
+            month_over_month_delta: this_month_value - last_month_value,
+            meeting_completion_rate: (total_meetings > 0)
+                .then(|| meetings.yes as f64 / total_meetings as f64),
+            open_task_count,
+        })
+    }
+
+    /// Top matches per category, limited to records owned by users the caller has
+    /// authority over.
+    pub async fn global_search(
+        db: &Database,
+        hmac_secret: &HmacSecret,
+        user_id: i32,
+        query: &str,
+        limit: i64,
+    ) -> Result<GlobalSearchDto> {
+        let user_ids = Self::get_authority_ids(db, user_id).await?;
+        let (customers, contracts, leads, recommendations) = try_join!(
+            Customer::search_brief(db, hmac_secret, &user_ids, query, limit),
+            Contract::search_brief(db, &user_ids, query, limit),
+            Lead::search_brief(db, hmac_secret, &user_ids, query, limit),
+            CustomerRecommendation::search_brief(db, hmac_secret, &user_ids, query, limit),
+        )?;
+
+        Ok(GlobalSearchDto {
+            customers,
+            contracts,
+            leads,
+            recommendations,
+        })
+    }
+
+    pub async fn get_info_by_uuid(db: &Database, user_uuid: Uuid) -> Result<User> {
+        let user_id = Self::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Felhasználó nem található!".to_string()))?;
+
+        let mut user = Self::get_info_by_id(db, user_id).await?;
+        user.uuid = Some(user_uuid);
+        Ok(user)
+    }
+
+    pub async fn modify_info(db: &Database, user_uuid: Uuid, mut user: User) -> Result<()> {
         let user_id = Self::get_id_by_uuid(db, Some(user_uuid)).await?.unwrap();
         if !User::is_exists_by_id(db, user_id).await? {
             return Err(anyhow!("Invalid user_id"));
         }
+        user.info.normalize_codes();
+        Self::ensure_codes_available(db, &user.info, Some(user_id)).await?;
 
         let mut tx = db.pool.begin().await?;
         sqlx::query!(
@@ -365,6 +1219,7 @@ impl User {
             .execute(&db.pool)
             .await?;
         }
+        db.role_cache.invalidate(&user_id);
 
         Ok(())
     }
@@ -382,6 +1237,10 @@ impl User {
         )
         .execute(&db.pool)
         .await?;
+        db.role_cache.invalidate(&user_id);
+
+        let mut con = db.redis.clone();
+        Redis::revoke_sessions(&mut con, user_id, None).await?;
 
         Ok(())
     }
@@ -430,7 +1289,7 @@ impl User {
                     "SELECT u.uuid, ui.full_name, u.user_role
                      FROM users u
                      JOIN user_info ui ON ui.user_id = u.id
-                     WHERE u.id = $1 OR u.manager_id = $1 AND (
+                     WHERE (u.id = $1 OR u.manager_id = $1) AND (
                         ($2 = 'Leader' AND u.user_role = 'Leader')
                         OR ($2 = 'Manager' AND u.user_role IN ('Manager', 'Leader'))
                         OR ($2 = 'Any')
@@ -487,19 +1346,182 @@ impl User {
 
         Ok(users)
     }
+
+    /// Moves the source user's records to the target, limited to the `from`..`to` window.
+    /// Contracts and leads are windowed by `handle_at`, meetings by `meet_date`,
+    /// everything else by `created_at`. Customers take all their related records along.
+    pub async fn reassign_records(
+        db: &Database,
+        source_uuid: Uuid,
+        target_uuid: Uuid,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        entities: &[ReassignEntity],
+    ) -> Result<HashMap<String, u64>> {
+        let source_id = Self::get_id_by_uuid(db, Some(source_uuid))
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Felhasználó nem található!".to_string()))?;
+        let target_id = Self::get_id_by_uuid(db, Some(target_uuid))
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Felhasználó nem található!".to_string()))?;
+        if source_id == target_id {
+            return Err(ApiError::Validation(
+                "A rekordokat nem adhatod át ugyanannak a felhasználónak!".to_string(),
+            )
+            .into());
+        }
+        let (from_utc, to_utc) = (from.map(|d| d.and_utc()), to.map(|d| d.and_utc()));
+
+        let mut tx = db.pool.begin().await?;
+        let mut affected = HashMap::new();
+        for entity in entities {
+            let rows_affected = match entity {
+                ReassignEntity::Customers => {
+                    let customer_uuids = sqlx::query_scalar!(
+                        r#"SELECT uuid AS "uuid!" FROM customers
+                         WHERE user_id = $1
+                           AND ($2::TIMESTAMPTZ IS NULL OR created_at >= $2)
+                           AND ($3::TIMESTAMPTZ IS NULL OR created_at <= $3)
+                         FOR UPDATE"#,
+                        source_id,
+                        from_utc,
+                        to_utc
+                    )
+                    .fetch_all(&mut *tx)
+                    .await?;
+
+                    Customer::move_with_related(&mut tx, &customer_uuids, target_id).await?
+                }
+                ReassignEntity::Contracts => sqlx::query!(
+                    "UPDATE customer_contracts SET user_id = $2
+                         WHERE user_id = $1
+                           AND ($3::TIMESTAMPTZ IS NULL OR handle_at >= $3)
+                           AND ($4::TIMESTAMPTZ IS NULL OR handle_at <= $4)",
+                    source_id,
+                    target_id,
+                    from_utc,
+                    to_utc
+                )
+                .execute(&mut *tx)
+                .await?
+                .rows_affected(),
+                ReassignEntity::Leads => sqlx::query!(
+                    "UPDATE customer_leads SET user_id = $2
+                         WHERE user_id = $1
+                           AND ($3::TIMESTAMPTZ IS NULL OR handle_at >= $3)
+                           AND ($4::TIMESTAMPTZ IS NULL OR handle_at <= $4)",
+                    source_id,
+                    target_id,
+                    from_utc,
+                    to_utc
+                )
+                .execute(&mut *tx)
+                .await?
+                .rows_affected(),
+                ReassignEntity::InterventionTasks => sqlx::query!(
+                    "UPDATE customer_intervention_tasks SET user_id = $2
+                         WHERE user_id = $1
+                           AND ($3::TIMESTAMPTZ IS NULL OR created_at >= $3)
+                           AND ($4::TIMESTAMPTZ IS NULL OR created_at <= $4)",
+                    source_id,
+                    target_id,
+                    from_utc,
+                    to_utc
+                )
+                .execute(&mut *tx)
+                .await?
+                .rows_affected(),
+                ReassignEntity::Meetings => sqlx::query!(
+                    "UPDATE user_dates SET user_id = $2
+                         WHERE user_id = $1
+                           AND ($3::TIMESTAMP IS NULL OR meet_date >= $3)
+                           AND ($4::TIMESTAMP IS NULL OR meet_date <= $4)",
+                    source_id,
+                    target_id,
+                    from,
+                    to
+                )
+                .execute(&mut *tx)
+                .await?
+                .rows_affected(),
+                ReassignEntity::Recommendations => sqlx::query!(
+                    "UPDATE customer_recommendations SET user_id = $2
+                         WHERE user_id = $1
+                           AND ($3::TIMESTAMPTZ IS NULL OR created_at >= $3)
+                           AND ($4::TIMESTAMPTZ IS NULL OR created_at <= $4)",
+                    source_id,
+                    target_id,
+                    from_utc,
+                    to_utc
+                )
+                .execute(&mut *tx)
+                .await?
+                .rows_affected(),
+            };
+            *affected.entry(entity.to_string()).or_insert(0) += rows_affected;
+        }
+
+        tx.commit().await?;
+        Ok(affected)
+    }
+
+    /// Users the caller may hand records over to: a Leader reaches everyone,
+    /// a Manager their direct reports, everyone else only themselves.
+    pub async fn get_assignable(
+        db: &Database,
+        user_id: i32,
+        min_role: Option<UserRole>,
+    ) -> Result<Vec<AssignableUserDto>> {
+        let user_role = Self::get_role(db, user_id).await?;
+
+        let rows = sqlx::query!(
+            "SELECT u.uuid, ui.full_name, u.user_role
+             FROM users u
+             JOIN user_info ui ON ui.user_id = u.id
+             WHERE $2 = 'Leader' OR u.id = $1 OR ($2 = 'Manager' AND u.manager_id = $1)
+             ORDER BY CASE WHEN u.id = $1 THEN 0 ELSE 1 END, ui.full_name",
+            user_id,
+            format!("{user_role:?}")
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| AssignableUserDto {
+                uuid: row.uuid,
+                full_name: row.full_name,
+                user_role: UserRole::from(row.user_role),
+            })
+            .filter(|user| min_role.as_ref().is_none_or(|min| &user.user_role >= min))
+            .collect())
+    }
 }
 
 impl UserRole {
-    pub async fn get_managers(db: &Database, user_id: i32) -> Result<Vec<ManagerNameDto>> {
+    pub async fn get_managers(
+        db: &Database,
+        user_id: i32,
+        role_filter: Option<UserRole>,
+        prefix: &str,
+        limit: i64,
+    ) -> Result<Vec<ManagerNameDto>> {
+        let prefix = validation::escape_like(prefix.trim());
         let rows = sqlx::query!(
             r#"
             SELECT u.uuid as user_uuid, ui.full_name, user_role
             FROM users u
             JOIN user_info ui ON ui.user_id = u.id
             WHERE (u.user_role = 'Manager' OR u.user_role = 'Leader') AND u.id != $1
-            ORDER BY u.user_role ASC, ui.full_name
+              AND ($2::TEXT IS NULL OR u.user_role = $2)
+              AND ui.full_name ILIKE $3 || '%'
+            ORDER BY u.user_role ASC, ui.full_name, u.id
+            LIMIT $4
             "#,
-            user_id
+            user_id,
+            role_filter.map(|role| format!("{role:?}")),
+            prefix,
+            limit
         )
         .fetch_all(&db.pool)
         .await?;
@@ -514,3 +1536,441 @@ impl UserRole {
             .collect())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sessions live only in Redis, so no user row is needed behind the id
+    const TEST_USER_ID: i32 = i32::MAX - 1;
+
+    fn session_id(tokens: &SignInResult) -> String {
+        let SignInResult::UserToken(tokens) = tokens else {
+            panic!("expected a token");
+        };
+        tokens.refresh_token.split_once('.').unwrap().0.to_string()
+    }
+
+    async fn insert_user(db: &Database, full_name: &str, user_role: &str) -> i32 {
+        let username = Token::generate_token();
+        let user_id = sqlx::query_scalar!(
+            "INSERT INTO users(email, username, password, user_role)
+             VALUES($1, $2, '', $3) RETURNING id",
+            format!("{username}@example.com"),
+            username,
+            user_role
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "INSERT INTO user_info(user_id, full_name, phone_number) VALUES($1, $2, $3)",
+            user_id,
+            full_name,
+            username
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        user_id
+    }
+
+    async fn delete_user(db: &Database, user_id: i32) {
+        sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn created_by_resolves_known_names_only() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let full_name = format!("Teszt Elek {}", Token::generate_token());
+        let user_id = insert_user(&db, &full_name, "Agent").await;
+
+        let mut conn = db.pool.acquire().await.unwrap();
+        let known =
+            User::normalize_created_by(&mut conn, Some(&format!(" {} ", full_name.to_uppercase())))
+                .await;
+        let unknown = User::normalize_created_by(&mut conn, Some("Nem Létező Rögzítő")).await;
+        delete_user(&db, user_id).await;
+
+        assert_eq!(known.unwrap(), full_name);
+        assert!(matches!(
+            unknown.unwrap_err().downcast_ref::<ApiError>(),
+            Some(ApiError::Validation(_))
+        ));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL, REDIS_URL and AUTH_SECRET"]
+    async fn revoked_sessions_reject_their_tokens() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+
+        let first = session_id(&User::issue_token(&db, TEST_USER_ID, None).await.unwrap());
+        let second = session_id(&User::issue_token(&db, TEST_USER_ID, None).await.unwrap());
+        assert!(User::ensure_session_active(&db, &first).await.is_ok());
+        assert!(User::ensure_session_active(&db, &second).await.is_ok());
+
+        let revoked = User::revoke_other_sessions(&db, TEST_USER_ID, None)
+            .await
+            .unwrap();
+
+        assert!(revoked >= 2);
+        assert!(User::ensure_session_active(&db, &first).await.is_err());
+        assert!(User::ensure_session_active(&db, &second).await.is_err());
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn reassigned_customers_take_their_contracts() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let source_id =
+            insert_user(&db, &format!("Forrás {}", Token::generate_token()), "Agent").await;
+        let target_id =
+            insert_user(&db, &format!("Cél {}", Token::generate_token()), "Agent").await;
+        let source_uuid = User::get_uuid_by_id(&db, source_id).await.unwrap().unwrap();
+        let target_uuid = User::get_uuid_by_id(&db, target_id).await.unwrap().unwrap();
+        let customer_id = sqlx::query_scalar!(
+            "INSERT INTO customers(full_name, phone_number_enc, phone_number_nonce, email_enc, email_nonce, address_enc, address_nonce, created_by, user_id)
+             VALUES('Teszt Ügyfél', '', '', '', '', '', '', 'Teszt', $1)
+             RETURNING id",
+            source_id
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        let contract_id = sqlx::query_scalar!(
+            "INSERT INTO customer_contracts(contract_number, contract_type, annual_fee, payment_frequency, payment_method, customer_id, created_by, user_id)
+             VALUES($1, 'Kgfb', 10000, 'Annual', 'Transfer', $2, 'Teszt', $3)
+             RETURNING id",
+            Token::generate_token(),
+            customer_id,
+            source_id
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+
+        let same_user = User::reassign_records(
+            &db,
+            source_uuid,
+            source_uuid,
+            None,
+            None,
+            &[ReassignEntity::Customers],
+        )
+        .await;
+        let affected = User::reassign_records(
+            &db,
+            source_uuid,
+            target_uuid,
+            None,
+            None,
+            &[ReassignEntity::Customers],
+        )
+        .await;
+        let contract_owner = sqlx::query_scalar!(
+            "SELECT user_id FROM customer_contracts WHERE id = $1",
+            contract_id
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query!("DELETE FROM customers WHERE id = $1", customer_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        delete_user(&db, source_id).await;
+        delete_user(&db, target_id).await;
+
+        assert!(matches!(
+            same_user.unwrap_err().downcast_ref::<ApiError>(),
+            Some(ApiError::Validation(_))
+        ));
+        assert_eq!(affected.unwrap()["Customers"], 1);
+        assert_eq!(contract_owner, Some(target_id));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn managers_are_filtered_by_prefix_and_limited() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let prefix = format!("Teszt Vezető {}", Token::generate_token());
+        let first = insert_user(&db, &format!("{prefix} A"), "Manager").await;
+        let second = insert_user(&db, &format!("{prefix} B"), "Manager").await;
+        let agent = insert_user(&db, &format!("{prefix} C"), "Agent").await;
+
+        let all = UserRole::get_managers(&db, 0, None, &prefix, 10).await;
+        let limited = UserRole::get_managers(&db, 0, None, &prefix, 1).await;
+        let excluded = UserRole::get_managers(&db, first, None, &prefix, 10).await;
+        for user_id in [first, second, agent] {
+            delete_user(&db, user_id).await;
+        }
+
+        let names = |list: Vec<ManagerNameDto>| -> Vec<String> {
+            list.into_iter().map(|manager| manager.full_name).collect()
+        };
+        assert_eq!(
+            names(all.unwrap()),
+            [format!("{prefix} A"), format!("{prefix} B")]
+        );
+        assert_eq!(names(limited.unwrap()), [format!("{prefix} A")]);
+        assert_eq!(names(excluded.unwrap()), [format!("{prefix} B")]);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn sub_users_of_a_manager_respect_the_role_filter() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let name = |role: &str| format!("Teszt {role} {}", Token::generate_token());
+        let manager = insert_user(&db, &name("Menedzser"), "Manager").await;
+        let agent = insert_user(&db, &name("Ügynök"), "Agent").await;
+        let outsider = insert_user(&db, &name("Kívülálló"), "Agent").await;
+        sqlx::query!(
+            "UPDATE users SET manager_id = $1 WHERE id = $2",
+            manager,
+            agent
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+        let manager_uuid = User::get_uuid_by_id(&db, manager).await.unwrap();
+        let agent_uuid = User::get_uuid_by_id(&db, agent).await.unwrap();
+
+        let leaders = User::get_sub_users(&db, manager, "Leader".to_string()).await;
+        let managers = User::get_sub_users(&db, manager, "Manager".to_string()).await;
+        let everyone = User::get_sub_users(&db, manager, "Any".to_string()).await;
+        for user_id in [manager, agent, outsider] {
+            delete_user(&db, user_id).await;
+        }
+
+        let uuids = |users: Result<Vec<User>>| -> Vec<Option<Uuid>> {
+            users.unwrap().into_iter().map(|user| user.uuid).collect()
+        };
+        assert_eq!(uuids(leaders), []);
+        assert_eq!(uuids(managers), [manager_uuid]);
+        assert_eq!(uuids(everyone), [manager_uuid, agent_uuid]);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn duplicate_agent_codes_are_rejected() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let agent_code = Token::generate_token();
+        let new_user = |agent_code: Option<String>| {
+            let username = Token::generate_token();
+            User {
+                email: Some(format!("{username}@example.com")),
+                username: Some(username.clone()),
+                password: Some("Teszt-jelszo-123".to_string()),
+                info: UserInfo {
+                    full_name: Some(format!("Teszt Ügynök {username}")),
+                    phone_number: Some(username),
+                    hufa_code: Some(" ".to_string()),
+                    agent_code,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+        };
+        let users = [
+            new_user(Some(agent_code.clone())),
+            new_user(Some(format!(" {agent_code} "))),
+            new_user(None),
+        ];
+        let usernames: Vec<String> = users
+            .iter()
+            .filter_map(|user| user.username.clone())
+            .collect();
+
+        let mut results = Vec::new();
+        for user in users {
+            results.push(User::create(&db, user).await);
+        }
+        sqlx::query!("DELETE FROM users WHERE username = ANY($1)", &usernames)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let [first, duplicate, without_code] = results.try_into().unwrap();
+        first.unwrap();
+        assert!(matches!(
+            ApiError::from(duplicate.unwrap_err()),
+            ApiError::Conflict(_)
+        ));
+        // Blank HUFA codes are stored as NULL, so they don't collide either
+        without_code.unwrap();
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn kpis_match_the_seeded_records() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let user_id =
+            insert_user(&db, &format!("Teszt {}", Token::generate_token()), "Agent").await;
+        let user_uuid = User::get_uuid_by_id(&db, user_id).await.unwrap().unwrap();
+        let customer_id = sqlx::query_scalar!(
+            "INSERT INTO customers(full_name, phone_number_enc, phone_number_nonce, email_enc, email_nonce, address_enc, address_nonce, user_id, created_by)
+             VALUES('Teszt Ügyfél', '', '', '', '', '', '', $1, 'Teszt')
+             RETURNING id",
+            user_id
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        // Offsets from the start of the current month
+        for (annual_fee, offset) in [
+            (10000, "1 hour"),
+            (5000, "2 hours"),
+            (7000, "-1 day"),
+            (3000, "-1 year"),
+        ] {
+            sqlx::query!(
+                "INSERT INTO customer_contracts(contract_number, contract_type, annual_fee, payment_frequency, payment_method, customer_id, user_id, created_by, handle_at)
+                 VALUES($1, 'Kgfb', $2, 'Annual', 'Transfer', $3, $4, 'Teszt', DATE_TRUNC('month', NOW()) + $5::TEXT::INTERVAL)",
+                Token::generate_token(),
+                annual_fee,
+                customer_id,
+                user_id,
+                offset
+            )
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        }
+        for is_completed in [true, true, false] {
+            sqlx::query!(
+                "INSERT INTO user_dates(meet_date, full_name, phone_number_enc, phone_number_nonce, meet_location, meet_type, is_completed, created_by, user_id)
+                 VALUES(NOW(), 'Teszt Ügyfél', '', '', 'Iroda', 'Consultation', $1, 'Teszt', $2)",
+                is_completed,
+                user_id
+            )
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        }
+        for status in ["Pending", "PaymentPromise", "Processed"] {
+            sqlx::query!(
+                "INSERT INTO customer_intervention_tasks(contract_number, product_name, outstanding_days, balance, processing_deadline, status, customer_id, user_id, created_by)
+                 VALUES('T-1', 'Kgfb', 30, 10000, NOW(), $1, $2, $3, 'Teszt')",
+                status,
+                customer_id,
+                user_id
+            )
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        }
+
+        let kpis = User::get_kpis(&db, user_uuid).await;
+        sqlx::query!("DELETE FROM customers WHERE id = $1", customer_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM user_dates WHERE user_id = $1", user_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        delete_user(&db, user_id).await;
+
+        let kpis = kpis.unwrap();
+        assert_eq!((kpis.production_value, kpis.production_count), (25000, 4));
+        assert_eq!(
+            (
+                kpis.this_month_value,
+                kpis.last_month_value,
+                kpis.month_over_month_delta
+            ),
+            (15000, 7000, 8000)
+        );
+        assert_eq!(kpis.meeting_completion_rate, Some(2.0 / 3.0));
+        assert_eq!(kpis.open_task_count, 2);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn global_search_returns_hits_per_category() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let hmac_secret = Token::generate_token().into_bytes();
+        let user_id =
+            insert_user(&db, &format!("Teszt {}", Token::generate_token()), "Agent").await;
+        let other_id =
+            insert_user(&db, &format!("Teszt {}", Token::generate_token()), "Agent").await;
+        let name = format!("Kereső {}", Token::generate_token());
+        let mut customer_ids = Vec::new();
+        for owner_id in [user_id, other_id] {
+            let customer_id = sqlx::query_scalar!(
+                "INSERT INTO customers(full_name, phone_number_enc, phone_number_nonce, email_enc, email_nonce, address_enc, address_nonce, user_id, created_by)
+                 VALUES($1, '', '', '', '', '', '', $2, 'Teszt')
+                 RETURNING id",
+                name,
+                owner_id
+            )
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+            sqlx::query!(
+                "INSERT INTO customer_contracts(contract_number, contract_type, annual_fee, payment_frequency, payment_method, customer_id, user_id, created_by)
+                 VALUES($1, 'Kgfb', 10000, 'Annual', 'Transfer', $2, $3, 'Teszt')",
+                Token::generate_token(),
+                customer_id,
+                owner_id
+            )
+            .execute(&db.pool)
+            .await
+            .unwrap();
+            sqlx::query!(
+                "INSERT INTO customer_leads(lead_type, inquiry_type, lead_status, customer_id, user_id, created_by)
+                 VALUES('Personal', 'Kgfb', 'Opened', $1, $2, 'Teszt')",
+                customer_id,
+                owner_id
+            )
+            .execute(&db.pool)
+            .await
+            .unwrap();
+            sqlx::query!(
+                "INSERT INTO customer_recommendations(full_name, phone_number_enc, phone_number_nonce, city_enc, city_nonce, referral_name, user_id, created_by)
+                 VALUES($1, '', '', '', '', 'Teszt Ajánló', $2, 'Teszt')",
+                name,
+                owner_id
+            )
+            .execute(&db.pool)
+            .await
+            .unwrap();
+            customer_ids.push(customer_id);
+        }
+
+        let results = User::global_search(&db, &hmac_secret, user_id, &name, 5).await;
+        sqlx::query!("DELETE FROM customers WHERE id = ANY($1)", &customer_ids)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query!(
+            "DELETE FROM customer_recommendations WHERE user_id = ANY($1)",
+            &[user_id, other_id]
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        delete_user(&db, user_id).await;
+        delete_user(&db, other_id).await;
+
+        let results = results.unwrap();
+        assert_eq!(results.customers.len(), 1);
+        assert_eq!(results.customers[0].label, name);
+        assert_eq!(results.contracts.len(), 1);
+        assert_eq!(results.leads.len(), 1);
+        assert_eq!(results.recommendations.len(), 1);
+    }
+}
@@ -3,17 +3,26 @@ use chacha20poly1305::Key;
 use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
-use sqlx::prelude::Type;
+use sqlx::{Connection, prelude::Type};
 use strum::{AsRefStr, Display, EnumString};
 use uuid::Uuid;
 
 use crate::{
     database::Database,
     models::{
-        dto::{DatesMonthlyChartDto, DatesWeeklyChartDto, IsCompletedChartDto, MeetTypeChartDto},
+        customer::Customer,
+        dto::{
+            BulkMeetDateFailureDto, BulkMeetDateSummaryDto, DailyCountDto, DatesMonthlyChartDto,
+            DatesWeeklyChartDto, IsCompletedChartDto, LocationCountDto, MeetTypeChartDto,
+            TeamMeetDateDto,
+        },
         user::User,
     },
-    utils::encrypt::{self, HmacSecret},
+    utils::{
+        encrypt::{self, HmacSecret},
+        error::ApiError,
+        validation,
+    },
 };
 
 #[skip_serializing_none]
@@ -29,6 +38,7 @@ pub struct UserMeetDate {
     pub is_completed: Option<bool>,
     pub created_by: Option<String>,
     pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
     pub user_id: Option<i32>,
 }
 
@@ -45,6 +55,7 @@ impl UserMeetDate {
         db: &Database,
         key: &Key,
         hmac_secret: &HmacSecret,
+        actor_id: i32,
         user_uuid: Uuid,
         new_meet_date: UserMeetDate,
     ) -> Result<i32> {
@@ -52,28 +63,149 @@ impl UserMeetDate {
             .await?
             .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
 
-        let phone = new_meet_date.phone_number.as_deref().unwrap();
-        let phone_hash = encrypt::hash_value(&hmac_secret, phone);
-        let (phone_enc, phone_nonce) = encrypt::encrypt_value(&key, phone);
+        let mut tx = db.pool.begin().await?;
+        let (id, _) = Self::insert_in(
+            &mut tx,
+            db,
+            key,
+            hmac_secret,
+            actor_id,
+            user_id,
+            &new_meet_date,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(id)
+    }
+
+    async fn has_conflict(
+        conn: &mut sqlx::PgConnection,
+        user_id: i32,
+        meet_date: NaiveDateTime,
+    ) -> Result<bool> {
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM user_dates WHERE user_id = $1 AND meet_date = $2) AS "exists!""#,
+            user_id,
+            meet_date
+        )
+        .fetch_one(conn)
+        .await?;
+
+        Ok(exists)
+    }
+
+    /// Shared by the single and the bulk create, so both run the same checks.
+    async fn insert_in(
+        conn: &mut sqlx::PgConnection,
+        db: &Database,
+        key: &Key,
+        hmac_secret: &HmacSecret,
+        actor_id: i32,
+        user_id: i32,
+        meeting: &UserMeetDate,
+    ) -> Result<(i32, Uuid)> {
+        let meet_date = meeting
+            .meet_date
+            .ok_or_else(|| ApiError::Validation("Időpont megadása kötelező!".to_string()))?;
+        let phone = meeting
+            .phone_number
+            .as_deref()
+            .ok_or_else(|| ApiError::Validation("Telefonszám megadása kötelező!".to_string()))?;
+
+        if Self::has_conflict(conn, user_id, meet_date).await? {
+            return Err(ApiError::Conflict(
+                "Az időpont ütközik egy másik találkozóval!".to_string(),
+            )
+            .into());
+        }
+
+        let phone_hash = encrypt::hash_value(hmac_secret, phone);
+        let (phone_enc, phone_nonce) = encrypt::encrypt_value(key, phone);
+
+        let customer_id = sqlx::query_scalar!(
+            "SELECT id FROM customers WHERE phone_number_hash = $1",
+            phone_hash
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+        if let Some(customer_id) = customer_id {
+            Customer::ensure_contactable(conn, db, customer_id, Some(actor_id)).await?;
+        }
+
+        let created_by = User::normalize_created_by(conn, meeting.created_by.as_deref()).await?;
 
         let row = sqlx::query!(
             "INSERT INTO user_dates(meet_date, full_name, phone_number_enc, phone_number_nonce, phone_number_hash, meet_location, meet_type, created_by, user_id)
              VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9)
-             RETURNING id",
-            new_meet_date.meet_date,
-            new_meet_date.full_name,
+             RETURNING id, uuid",
+            meet_date,
+            meeting.full_name,
             phone_enc,
             phone_nonce,
             phone_hash,
-            new_meet_date.meet_location,
-            new_meet_date.meet_type.map(|t| t.to_string()),
-            new_meet_date.created_by,
+            meeting.meet_location,
+            meeting.meet_type.as_ref().map(|t| t.to_string()),
+            created_by,
             user_id
         )
-        .fetch_one(&db.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
-        Ok(row.id)
+        let uuid = row
+            .uuid
+            .ok_or_else(|| anyhow!("Az időpont létrehozása sikertelen!"))?;
+        Ok((row.id, uuid))
+    }
+
+    /// Creates every meeting in one transaction; conflicting or invalid items are
+    /// rolled back individually and reported instead of failing the whole batch.
+    pub async fn create_many(
+        db: &Database,
+        key: &Key,
+        hmac_secret: &HmacSecret,
+        actor_id: i32,
+        user_uuid: Uuid,
+        meetings: Vec<UserMeetDate>,
+    ) -> Result<BulkMeetDateSummaryDto> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let mut created = Vec::new();
+        let mut failed = Vec::new();
+        let mut tx = db.pool.begin().await?;
+        for (index, meeting) in meetings.into_iter().enumerate() {
+            let meet_date = meeting.meet_date;
+            let mut savepoint = tx.begin().await?;
+            match Self::insert_in(
+                &mut savepoint,
+                db,
+                key,
+                hmac_secret,
+                actor_id,
+                user_id,
+                &meeting,
+            )
+            .await
+            {
+                Result::Ok((_, uuid)) => {
+                    savepoint.commit().await?;
+                    created.push(uuid);
+                }
+                Err(e) => {
+                    savepoint.rollback().await?;
+                    failed.push(BulkMeetDateFailureDto {
+                        index,
+                        meet_date,
+                        reason: ApiError::from(e).message().to_string(),
+                    });
+                }
+            }
+        }
+        tx.commit().await?;
+
+        Ok(BulkMeetDateSummaryDto { created, failed })
     }
 
     pub async fn modify(
@@ -126,35 +258,151 @@ impl UserMeetDate {
             .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
 
         let rows = sqlx::query!(
-            "SELECT uuid, meet_date, full_name, phone_number_enc, phone_number_nonce, phone_number_hash, meet_location, meet_type, is_completed, created_by, created_at
+            "SELECT uuid, meet_date, full_name, phone_number_enc, phone_number_nonce, phone_number_hash, meet_location, meet_type, is_completed, created_by, created_at, updated_at
              FROM user_dates
              WHERE user_id = $1 AND TRIM(TO_CHAR(meet_date, 'Month')) = $2
-             ORDER BY meet_date DESC",
+             ORDER BY meet_date DESC
+             LIMIT $3",
             user_id,
-            selected_month
+            selected_month,
+            validation::decrypt_fetch_limit()
         )
         .fetch_all(&db.pool)
         .await?;
+        validation::ensure_decrypt_limit(rows.len())?;
 
-        Ok(rows
-            .into_iter()
-            .map(|row| UserMeetDate {
-                uuid: row.uuid,
-                meet_date: Some(row.meet_date),
-                full_name: Some(row.full_name),
-                phone_number: encrypt::decrypt_value(
-                    key,
-                    &row.phone_number_enc,
-                    &row.phone_number_nonce,
-                ),
-                meet_location: Some(row.meet_location),
-                meet_type: Some(row.meet_type.parse().unwrap()),
-                is_completed: Some(row.is_completed),
-                created_by: Some(row.created_by),
-                created_at: Some(row.created_at),
-                ..Default::default()
+        rows.into_iter()
+            .map(|row| {
+                Ok(UserMeetDate {
+                    uuid: row.uuid,
+                    meet_date: Some(row.meet_date),
+                    full_name: Some(row.full_name),
+                    phone_number: encrypt::decrypt_value(
+                        key,
+                        &row.phone_number_enc,
+                        &row.phone_number_nonce,
+                    ),
+                    meet_location: Some(row.meet_location),
+                    meet_type: Some(validation::parse_stored("meet_type", &row.meet_type)?),
+                    is_completed: Some(row.is_completed),
+                    created_by: Some(row.created_by),
+                    created_at: Some(row.created_at),
+                    updated_at: Some(row.updated_at),
+                    ..Default::default()
+                })
             })
-            .collect())
+            .collect()
+    }
+
+    pub async fn get_range(
+        db: &Database,
+        key: &Key,
+        user_uuid: Uuid,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> Result<Vec<UserMeetDate>> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let rows = sqlx::query!(
+            "SELECT uuid, meet_date, full_name, phone_number_enc, phone_number_nonce, meet_location, meet_type, is_completed, created_by, created_at, updated_at
+             FROM user_dates
+             WHERE user_id = $1 AND meet_date BETWEEN $2 AND $3
+             ORDER BY meet_date ASC
+             LIMIT $4",
+            user_id,
+            from,
+            to,
+            validation::decrypt_fetch_limit()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+        validation::ensure_decrypt_limit(rows.len())?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(UserMeetDate {
+                    uuid: row.uuid,
+                    meet_date: Some(row.meet_date),
+                    full_name: Some(row.full_name),
+                    phone_number: encrypt::decrypt_value(
+                        key,
+                        &row.phone_number_enc,
+                        &row.phone_number_nonce,
+                    ),
+                    meet_location: Some(row.meet_location),
+                    meet_type: Some(validation::parse_stored("meet_type", &row.meet_type)?),
+                    is_completed: Some(row.is_completed),
+                    created_by: Some(row.created_by),
+                    created_at: Some(row.created_at),
+                    updated_at: Some(row.updated_at),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    pub async fn get_team_range(
+        db: &Database,
+        key: &Key,
+        manager_id: i32,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> Result<Vec<TeamMeetDateDto>> {
+        let rows = sqlx::query!(
+            "SELECT
+                ud.uuid,
+                ud.meet_date,
+                ud.full_name,
+                ud.phone_number_enc,
+                ud.phone_number_nonce,
+                ud.meet_location,
+                ud.meet_type,
+                ud.is_completed,
+                o.uuid AS agent_uuid,
+                oi.full_name AS agent_name
+            FROM users u
+            JOIN user_dates ud
+            ON (
+                u.user_role = 'Leader' OR (u.user_role = 'Manager' AND ud.user_id IN (
+                    SELECT id FROM users WHERE manager_id = u.id OR id = u.id
+                ))
+            )
+            JOIN users o ON o.id = ud.user_id
+            JOIN user_info oi ON oi.user_id = o.id
+            WHERE u.id = $1 AND ud.meet_date BETWEEN $2 AND $3
+            ORDER BY ud.meet_date ASC
+            LIMIT $4",
+            manager_id,
+            from,
+            to,
+            validation::decrypt_fetch_limit()
+        )
+        .fetch_all(&db.pool)
+        .await?;
+        validation::ensure_decrypt_limit(rows.len())?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(TeamMeetDateDto {
+                    uuid: row.uuid,
+                    meet_date: row.meet_date,
+                    full_name: row.full_name,
+                    phone_number: encrypt::decrypt_value(
+                        key,
+                        &row.phone_number_enc,
+                        &row.phone_number_nonce,
+                    )
+                    .unwrap_or_default(),
+                    meet_location: row.meet_location,
+                    meet_type: validation::parse_stored("meet_type", &row.meet_type)?,
+                    is_completed: row.is_completed,
+                    agent_uuid: row.agent_uuid,
+                    agent_name: row.agent_name,
+                })
+            })
+            .collect()
     }
 
     pub async fn get_by_uuid(db: &Database, key: &Key, date_uuid: Uuid) -> Result<UserMeetDate> {
@@ -170,7 +418,8 @@ impl UserMeetDate {
                 meet_type,
                 is_completed,
                 created_by,
-                created_at
+                created_at,
+                updated_at
             FROM
                 user_dates
             WHERE
@@ -190,10 +439,11 @@ impl UserMeetDate {
                 &row.phone_number_nonce,
             ),
             meet_location: Some(row.meet_location),
-            meet_type: Some(row.meet_type.parse().unwrap()),
+            meet_type: Some(validation::parse_stored("meet_type", &row.meet_type)?),
             is_completed: Some(row.is_completed),
             created_by: Some(row.created_by),
             created_at: Some(row.created_at),
+            updated_at: Some(row.updated_at),
             ..Default::default()
         })
     }
@@ -215,6 +465,23 @@ impl UserMeetDate {
         Ok(())
     }
 
+    pub async fn change_state_bulk(
+        db: &Database,
+        date_uuids: Vec<Uuid>,
+        is_completed: bool,
+    ) -> Result<u64> {
+        let result = sqlx::query!(
+            "UPDATE user_dates
+             SET is_completed = $2
+             WHERE uuid = ANY($1)",
+            &date_uuids,
+            is_completed
+        )
+        .execute(&db.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
     pub async fn change_handler(
         db: &Database,
         user_full_name: String,
@@ -396,6 +663,76 @@ impl UserMeetDate {
         })
     }
 
+    pub async fn get_daily_counts(
+        db: &Database,
+        user_uuid: Uuid,
+        start_date: NaiveDateTime,
+        end_date: NaiveDateTime,
+    ) -> Result<Vec<DailyCountDto>> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let rows = sqlx::query!(
+            r#"SELECT
+                d.day::DATE AS "date!",
+                COUNT(ud.id) AS "count!"
+            FROM generate_series($2::TIMESTAMP::DATE, $3::TIMESTAMP::DATE, INTERVAL '1 day') AS d(day)
+            LEFT JOIN user_dates ud
+                ON ud.user_id = $1
+                AND ud.meet_date::DATE = d.day::DATE
+                AND ud.meet_date BETWEEN $2 AND $3
+            GROUP BY d.day
+            ORDER BY d.day"#,
+            user_id,
+            start_date,
+            end_date
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| DailyCountDto {
+                date: row.date,
+                count: row.count,
+            })
+            .collect())
+    }
+
+    pub async fn get_location_breakdown(
+        db: &Database,
+        user_uuid: Uuid,
+        limit: i64,
+    ) -> Result<Vec<LocationCountDto>> {
+        let user_id = User::get_id_by_uuid(db, Some(user_uuid))
+            .await?
+            .ok_or_else(|| anyhow!("Felhasználó nem található!"))?;
+
+        let rows = sqlx::query!(
+            r#"SELECT
+                LOWER(REGEXP_REPLACE(TRIM(meet_location), '\s+', ' ', 'g')) AS "location!",
+                COUNT(*) AS "count!"
+            FROM user_dates
+            WHERE user_id = $1 AND TRIM(meet_location) <> ''
+            GROUP BY 1
+            ORDER BY 2 DESC, 1
+            LIMIT $2"#,
+            user_id,
+            limit
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| LocationCountDto {
+                location: row.location,
+                count: row.count,
+            })
+            .collect())
+    }
+
     pub async fn get_dates_weekly_chart_by_user_uuid(
         db: &Database,
         user_uuid: Uuid,
@@ -525,3 +862,251 @@ impl UserMeetDate {
         Ok(dates)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Timelike};
+
+    use super::*;
+    use crate::utils::redis::Token;
+
+    async fn insert_user(db: &Database, user_role: &str) -> (i32, Uuid, String) {
+        let username = Token::generate_token();
+        let full_name = format!("Teszt Ügynök {username}");
+        let user = sqlx::query!(
+            "INSERT INTO users(email, username, password, user_role)
+             VALUES($1, $2, '', $3) RETURNING id, uuid",
+            format!("{username}@example.com"),
+            username,
+            user_role
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "INSERT INTO user_info(user_id, full_name, phone_number) VALUES($1, $2, $3)",
+            user.id,
+            full_name,
+            username
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        (user.id, user.uuid.unwrap(), full_name)
+    }
+
+    // Meetings outlive their owner, so they go first
+    async fn delete_user(db: &Database, user_id: i32) {
+        sqlx::query!("DELETE FROM user_dates WHERE user_id = $1", user_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+    }
+
+    async fn insert_meeting(
+        db: &Database,
+        key: &Key,
+        user_id: i32,
+        full_name: &str,
+        meet_date: &str,
+    ) {
+        let (phone_enc, phone_nonce) = encrypt::encrypt_value(key, "+36301234567");
+        sqlx::query!(
+            "INSERT INTO user_dates(meet_date, full_name, phone_number_enc, phone_number_nonce, meet_location, meet_type, created_by, user_id)
+             VALUES($1, $2, $3, $4, 'Iroda', 'Consultation', 'Teszt', $5)",
+            datetime(meet_date),
+            full_name,
+            phone_enc,
+            phone_nonce,
+            user_id
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+    }
+
+    fn datetime(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn conflicting_slots_are_reported_by_both_create_paths() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let hmac_secret = Token::generate_token().into_bytes();
+        let full_name = format!("Teszt Rögzítő {}", Token::generate_token());
+        let username = Token::generate_token();
+        let user = sqlx::query!(
+            "INSERT INTO users(email, username, password, user_role)
+             VALUES($1, $2, '', 'Agent') RETURNING id, uuid",
+            format!("{username}@example.com"),
+            username
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "INSERT INTO user_info(user_id, full_name, phone_number) VALUES($1, $2, $3)",
+            user.id,
+            full_name,
+            username
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let user_uuid = user.uuid.unwrap();
+        let slot = (Utc::now() + Duration::days(7))
+            .naive_utc()
+            .with_nanosecond(0)
+            .unwrap();
+        let meeting = |meet_date: NaiveDateTime| UserMeetDate {
+            meet_date: Some(meet_date),
+            full_name: Some("Teszt Ügyfél".to_string()),
+            phone_number: Some("+36301234567".to_string()),
+            meet_location: Some("Iroda".to_string()),
+            meet_type: Some(MeetType::Consultation),
+            created_by: Some(full_name.clone()),
+            ..Default::default()
+        };
+
+        UserMeetDate::create(&db, &key, &hmac_secret, user.id, user_uuid, meeting(slot))
+            .await
+            .unwrap();
+        let single =
+            UserMeetDate::create(&db, &key, &hmac_secret, user.id, user_uuid, meeting(slot)).await;
+        let summary = UserMeetDate::create_many(
+            &db,
+            &key,
+            &hmac_secret,
+            user.id,
+            user_uuid,
+            vec![
+                meeting(slot + Duration::hours(1)),
+                meeting(slot),
+                meeting(slot + Duration::hours(2)),
+            ],
+        )
+        .await;
+        sqlx::query!("DELETE FROM user_dates WHERE user_id = $1", user.id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM users WHERE id = $1", user.id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            single.unwrap_err().downcast_ref::<ApiError>(),
+            Some(ApiError::Conflict(_))
+        ));
+        let summary = summary.unwrap();
+        assert_eq!(summary.created.len(), 2);
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].index, 1);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn range_spans_the_month_boundary() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let (user_id, user_uuid, _) = insert_user(&db, "Agent").await;
+        for (full_name, meet_date) in [
+            ("Február eleje", "2025-02-01 09:00"),
+            ("Január vége", "2025-01-31 23:00"),
+            ("Január", "2025-01-30 10:00"),
+            ("Korábbi", "2025-01-27 10:00"),
+            ("Későbbi", "2025-02-03 10:00"),
+        ] {
+            insert_meeting(&db, &key, user_id, full_name, meet_date).await;
+        }
+
+        let meetings = UserMeetDate::get_range(
+            &db,
+            &key,
+            user_uuid,
+            datetime("2025-01-30 00:00"),
+            datetime("2025-02-02 23:59"),
+        )
+        .await;
+        delete_user(&db, user_id).await;
+
+        let meetings = meetings.unwrap();
+        assert_eq!(
+            meetings
+                .iter()
+                .map(|meeting| meeting.full_name.as_deref().unwrap())
+                .collect::<Vec<_>>(),
+            ["Január", "Január vége", "Február eleje"]
+        );
+        assert!(
+            meetings
+                .iter()
+                .all(|meeting| meeting.phone_number.as_deref() == Some("+36301234567"))
+        );
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn team_range_labels_meetings_with_their_agent() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let key = Key::from([7u8; 32]);
+        let (manager_id, _, _) = insert_user(&db, "Manager").await;
+        let (first_id, first_uuid, first_name) = insert_user(&db, "Agent").await;
+        let (second_id, second_uuid, second_name) = insert_user(&db, "Agent").await;
+        let (outsider_id, _, _) = insert_user(&db, "Agent").await;
+        sqlx::query!(
+            "UPDATE users SET manager_id = $1 WHERE id = ANY($2)",
+            manager_id,
+            &[first_id, second_id]
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        insert_meeting(&db, &key, first_id, "Első ügyfél", "2025-03-03 09:00").await;
+        insert_meeting(&db, &key, second_id, "Második ügyfél", "2025-03-04 09:00").await;
+        insert_meeting(&db, &key, outsider_id, "Más csapat", "2025-03-04 10:00").await;
+
+        let meetings = UserMeetDate::get_team_range(
+            &db,
+            &key,
+            manager_id,
+            datetime("2025-03-01 00:00"),
+            datetime("2025-03-31 23:59"),
+        )
+        .await;
+        for user_id in [first_id, second_id, outsider_id, manager_id] {
+            delete_user(&db, user_id).await;
+        }
+
+        let meetings = meetings.unwrap();
+        assert_eq!(
+            meetings
+                .iter()
+                .map(|meeting| (
+                    meeting.full_name.as_str(),
+                    meeting.agent_uuid,
+                    meeting.agent_name.as_str()
+                ))
+                .collect::<Vec<_>>(),
+            [
+                ("Első ügyfél", Some(first_uuid), first_name.as_str()),
+                ("Második ügyfél", Some(second_uuid), second_name.as_str()),
+            ]
+        );
+        assert!(
+            meetings
+                .iter()
+                .all(|meeting| meeting.phone_number == "+36301234567")
+        );
+    }
+}
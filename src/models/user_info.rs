@@ -11,3 +11,19 @@ pub struct UserInfo {
     pub hufa_code: Option<String>,
     pub agent_code: Option<String>,
 }
+
+impl UserInfo {
+    // Blank codes are stored as NULL so they never collide with each other
+    pub fn normalize_codes(&mut self) {
+        self.hufa_code = self
+            .hufa_code
+            .take()
+            .map(|code| code.trim().to_string())
+            .filter(|code| !code.is_empty());
+        self.agent_code = self
+            .agent_code
+            .take()
+            .map(|code| code.trim().to_string())
+            .filter(|code| !code.is_empty());
+    }
+}
@@ -0,0 +1,211 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use strum::{AsRefStr, Display, EnumString};
+use uuid::Uuid;
+
+use crate::{
+    database::Database,
+    utils::{error::ApiError, redis::Token},
+};
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Default, Clone)]
+pub struct Webhook {
+    pub uuid: Option<Uuid>,
+    pub url: Option<String>,
+    pub secret: Option<String>,
+    pub event_types: Option<Vec<WebhookEvent>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+// A queued event for one subscriber, claimed by the delivery worker
+#[derive(Debug, Clone)]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub url: String,
+    pub secret: String,
+    pub event: String,
+    pub body: String,
+    pub attempts: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, EnumString, Display, AsRefStr)]
+pub enum WebhookEvent {
+    ContractCreated,
+    ContractModified,
+    ContractDeleted,
+    LeadStatusChanged,
+}
+
+impl Webhook {
+    pub async fn create(
+        db: &Database,
+        actor_id: i32,
+        url: String,
+        event_types: Vec<WebhookEvent>,
+    ) -> Result<Webhook> {
+        let url = url.trim().to_string();
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(ApiError::Validation("Érvénytelen webhook URL!".to_string()).into());
+        }
+        if event_types.is_empty() {
+            return Err(ApiError::Validation(
+                "Legalább egy eseménytípus megadása kötelező!".to_string(),
+            )
+            .into());
+        }
+
+        let secret = Token::generate_token();
+        let row = sqlx::query!(
+            "INSERT INTO webhooks(url, secret, event_types, created_by)
+             VALUES($1, $2, $3, $4)
+             RETURNING uuid, created_at",
+            url,
+            secret,
+            &event_types
+                .iter()
+                .map(|event| event.to_string())
+                .collect::<Vec<String>>(),
+            actor_id
+        )
+        .fetch_one(&db.pool)
+        .await?;
+
+        // The secret is only returned once, integrators need it to verify signatures
+        Ok(Webhook {
+            uuid: row.uuid,
+            url: Some(url),
+            secret: Some(secret),
+            event_types: Some(event_types),
+            created_at: Some(row.created_at),
+        })
+    }
+
+    pub async fn get_all(db: &Database) -> Result<Vec<Webhook>> {
+        let rows = sqlx::query!(
+            "SELECT uuid, url, event_types, created_at
+             FROM webhooks
+             ORDER BY created_at DESC"
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        let items = rows
+            .into_iter()
+            .map(|row| Webhook {
+                uuid: row.uuid,
+                url: Some(row.url),
+                event_types: Some(
+                    row.event_types
+                        .iter()
+                        .filter_map(|event| event.parse().ok())
+                        .collect(),
+                ),
+                created_at: Some(row.created_at),
+                ..Default::default()
+            })
+            .collect();
+
+        Ok(items)
+    }
+
+    pub async fn delete(db: &Database, webhook_uuid: Uuid) -> Result<()> {
+        let result = sqlx::query!("DELETE FROM webhooks WHERE uuid = $1", webhook_uuid)
+            .execute(&db.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(ApiError::NotFound("Webhook nem található!".to_string()).into());
+        }
+
+        Ok(())
+    }
+}
+
+impl WebhookDelivery {
+    /// Queues the payload for every webhook subscribed to the event.
+    pub async fn enqueue(db: &Database, event: &WebhookEvent, body: &str) -> Result<u64> {
+        let result = sqlx::query!(
+            "INSERT INTO webhook_deliveries(webhook_id, event, body)
+             SELECT id, $1::TEXT, $2 FROM webhooks WHERE $1::TEXT = ANY(event_types)",
+            event.to_string(),
+            body
+        )
+        .execute(&db.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Takes up to `limit` due deliveries and counts the attempt. A claimed delivery is
+    /// pushed `lease_seconds` ahead, so another worker picks it up again if this one dies.
+    pub async fn claim_due(db: &Database, limit: i64, lease_seconds: f64) -> Result<Vec<Self>> {
+        let rows = sqlx::query!(
+            "UPDATE webhook_deliveries d
+             SET attempts = d.attempts + 1,
+                 next_attempt_at = NOW() + make_interval(secs => $2)
+             FROM webhooks w
+             WHERE w.id = d.webhook_id
+               AND d.id IN (
+                   SELECT id FROM webhook_deliveries
+                   WHERE delivered_at IS NULL AND failed_at IS NULL AND next_attempt_at <= NOW()
+                   ORDER BY next_attempt_at
+                   LIMIT $1
+                   FOR UPDATE SKIP LOCKED
+               )
+             RETURNING d.id, d.event, d.body, d.attempts, w.url, w.secret",
+            limit,
+            lease_seconds
+        )
+        .fetch_all(&db.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Self {
+                id: row.id,
+                url: row.url,
+                secret: row.secret,
+                event: row.event,
+                body: row.body,
+                attempts: row.attempts,
+            })
+            .collect())
+    }
+
+    pub async fn mark_delivered(db: &Database, delivery_id: i32) -> Result<()> {
+        sqlx::query!(
+            "UPDATE webhook_deliveries SET delivered_at = NOW(), last_error = NULL WHERE id = $1",
+            delivery_id
+        )
+        .execute(&db.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Schedules the next attempt, or gives up for good when `retry_in_seconds` is `None`.
+    pub async fn mark_failed(
+        db: &Database,
+        delivery_id: i32,
+        error: &str,
+        retry_in_seconds: Option<f64>,
+    ) -> Result<()> {
+        sqlx::query!(
+            "UPDATE webhook_deliveries
+             SET last_error = $2,
+                 next_attempt_at = NOW() + make_interval(secs => COALESCE($3::FLOAT8, 0)),
+                 failed_at = CASE WHEN $3::FLOAT8 IS NULL THEN NOW() END
+             WHERE id = $1",
+            delivery_id,
+            error,
+            retry_in_seconds
+        )
+        .execute(&db.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use actix_web::{HttpResponse, Responder, ResponseError, Scope, web};
+
+use crate::{
+    extractors::authentication_token::AuthenticationToken,
+    models::{
+        audit_log::AuditLog,
+        user::{User, UserRole},
+    },
+    utils::{error::ApiError, pagination::PaginationQuery},
+    web_data::WebData,
+};
+
+pub fn audit_scope() -> Scope {
+    web::scope("/audit").route("/recent", web::get().to(get_recent_audit_events))
+}
+
+async fn get_recent_audit_events(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    pagination: web::Query<PaginationQuery>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    match AuditLog::get_recent_for_team(&web_data.db, auth_token.id as i32, pagination.into_inner())
+        .await
+    {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
@@ -1,36 +1,73 @@
-use actix_web::{HttpResponse, Responder, ResponseError, Scope, web};
+use actix_web::{HttpResponse, Responder, ResponseError, Scope, http::header, web};
 use chrono::NaiveDateTime;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
     extractors::authentication_token::AuthenticationToken,
     models::{
-        contract::{Contract, ContractType, PaymentFrequency, PaymentMethod},
+        contract::{Contract, ContractStatus, ContractType, PaymentFrequency, PaymentMethod},
         customer::Customer,
-        user::{User, UserRole},
+        dto::PortfolioDto,
+        user::{ListingScope, User, UserRole},
+    },
+    utils::{
+        contact::{Email, Phone},
+        error::ApiError,
+        messages,
+        pagination::PaginationQuery,
+        validation::validate_chart_span,
     },
-    utils::error::ApiError,
     web_data::WebData,
 };
 
 pub fn contract_scope() -> Scope {
     web::scope("/contract")
         .route("/create", web::post().to(create_contract))
+        .route(
+            "/create/{customer_uuid}",
+            web::post().to(create_contract_for_customer),
+        )
         .route("/modify", web::put().to(modify_contract))
+        .route("/types", web::get().to(get_contract_types))
         .route(
             "/get-all/{user_uuid}",
             web::get().to(get_contracts_by_user_uuid),
         )
+        .route(
+            "/grouped/{user_uuid}",
+            web::get().to(get_contracts_grouped_by_customer),
+        )
+        .route(
+            "/handled/{user_uuid}",
+            web::post().to(get_contracts_handled_in_range),
+        )
+        .route(
+            "/commission/{user_uuid}",
+            web::post().to(get_commission_estimate),
+        )
+        .route("/search/{user_uuid}", web::get().to(search_contracts))
+        .route(
+            "/unpaid/{user_uuid}",
+            web::get().to(get_unpaid_first_contracts),
+        )
+        .route("/renewals/team", web::get().to(get_team_renewals))
+        .route("/duplicates", web::get().to(get_duplicate_numbers))
         .route("/{contract_uuid}", web::get().to(get_contract_by_uuid))
         .route(
             "/{contract_uuid}/customer",
             web::get().to(get_customer_uuid),
         )
+        .route("/{contract_uuid}/tasks", web::get().to(get_open_tasks))
+        .route("/{contract_uuid}/pdf", web::get().to(get_contract_pdf))
         .route(
             "/{contract_uuid}/state",
             web::put().to(change_first_payment_state),
         )
+        .route(
+            "/{contract_uuid}/status",
+            web::put().to(change_contract_status),
+        )
         .route("/change/user", web::put().to(change_contract_handler))
         .route("/delete", web::delete().to(delete_contract))
         // CHART API's
@@ -58,11 +95,20 @@ pub fn contract_scope() -> Scope {
             "/chart/production/count/{user_uuid}",
             web::get().to(get_production_count_by_user_uuid),
         )
+        .route("/chart/status/team", web::get().to(get_team_status_summary))
         .route(
             "/chart/portfolio/get-all",
             web::get().to(get_portfolio_chart),
         )
         .route("/chart/portfolio", web::get().to(get_self_portfolio_chart))
+        .route(
+            "/chart/portfolio/export.csv",
+            web::get().to(export_portfolio_chart),
+        )
+        .route(
+            "/chart/portfolio/map/{user_uuid}",
+            web::get().to(get_portfolio_map),
+        )
         .route(
             "/chart/portfolio/{user_uuid}",
             web::get().to(get_portfolio_chart_by_user_uuid),
@@ -79,6 +125,10 @@ pub fn contract_scope() -> Scope {
             "/chart/weekly/{user_uuid}",
             web::post().to(get_weekly_production_chart_by_user_uuid),
         )
+        .route(
+            "/chart/new-vs-renewal/{user_uuid}",
+            web::post().to(get_new_vs_renewal_chart),
+        )
         .route(
             "/chart/monthly/value/get-all",
             web::post().to(get_monthly_production_value_chart),
@@ -103,14 +153,26 @@ pub fn contract_scope() -> Scope {
             "/chart/monthly/production/{user_uuid}",
             web::post().to(get_monthly_production_chart_by_user_uuid),
         )
+        .route(
+            "/chart/monthly/combined",
+            web::post().to(get_self_monthly_combined_chart),
+        )
+        .route(
+            "/chart/monthly/combined/{user_uuid}",
+            web::post().to(get_monthly_combined_chart_by_user_uuid),
+        )
+        .route(
+            "/chart/type-trend/{user_uuid}",
+            web::post().to(get_type_trend_chart_by_user_uuid),
+        )
 }
 
 #[derive(Deserialize, Clone)]
 struct CustomerJson {
     full_name: String,
-    phone_number: String,
+    phone_number: Phone,
     address: String,
-    email: String,
+    email: Email,
 }
 #[derive(Deserialize, Clone)]
 struct CreateContractJson {
@@ -120,18 +182,30 @@ struct CreateContractJson {
     annual_fee: i32,
     payment_frequency: PaymentFrequency,
     payment_method: PaymentMethod,
+    status: Option<ContractStatus>,
+    is_renewal: Option<bool>,
     user_uuid: Uuid,
     created_by: String,
 }
+
+#[derive(Serialize)]
+struct CreateContractResponse {
+    message: String,
+    customer_created: bool,
+}
 async fn create_contract(
     web_data: web::Data<WebData>,
-    _: AuthenticationToken,
+    auth_token: AuthenticationToken,
     data: web::Json<CreateContractJson>,
 ) -> impl Responder {
+    if let Err(e) = data.contract_type.ensure_allowed(&web_data.contract_types) {
+        return ApiError::from(e).error_response();
+    }
+
     let customer = Customer {
         full_name: Some(data.customer.full_name.clone()),
-        phone_number: Some(data.customer.phone_number.clone()),
-        email: Some(data.customer.email.clone()),
+        phone_number: Some(data.customer.phone_number.to_string()),
+        email: Some(data.customer.email.to_string()),
         address: Some(data.customer.address.clone()),
         created_by: Some(data.created_by.clone()),
         ..Default::default()
@@ -142,6 +216,8 @@ async fn create_contract(
         annual_fee: Some(data.annual_fee.clone()),
         payment_frequency: Some(data.payment_frequency.clone()),
         payment_method: Some(data.payment_method.clone()),
+        status: data.status.clone(),
+        is_renewal: data.is_renewal,
         created_by: Some(data.created_by.clone()),
         ..Default::default()
     };
@@ -150,13 +226,69 @@ async fn create_contract(
         &web_data.db,
         &web_data.key,
         &web_data.hmac_secret,
+        auth_token.id as i32,
         data.user_uuid,
         customer,
         contract,
     )
     .await
     {
-        Ok(_) => HttpResponse::Created().json("Szerződés sikeresen létrehozva!"),
+        Ok((_, customer_created)) => HttpResponse::Created().json(CreateContractResponse {
+            message: messages::CONTRACT_CREATED.to_string(),
+            customer_created,
+        }),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize, Clone)]
+struct CreateContractForCustomerJson {
+    contract_number: String,
+    contract_type: ContractType,
+    annual_fee: i32,
+    payment_frequency: PaymentFrequency,
+    payment_method: PaymentMethod,
+    status: Option<ContractStatus>,
+    is_renewal: Option<bool>,
+    user_uuid: Uuid,
+    created_by: String,
+}
+async fn create_contract_for_customer(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    data: web::Json<CreateContractForCustomerJson>,
+    customer_uuid: web::Path<Uuid>,
+) -> impl Responder {
+    // The customer must belong to `user_uuid`, so the caller has to be allowed to act for them
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, data.user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+    if let Err(e) = data.contract_type.ensure_allowed(&web_data.contract_types) {
+        return ApiError::from(e).error_response();
+    }
+
+    let contract = Contract {
+        contract_number: Some(data.contract_number.clone()),
+        contract_type: Some(data.contract_type.clone()),
+        annual_fee: Some(data.annual_fee),
+        payment_frequency: Some(data.payment_frequency.clone()),
+        payment_method: Some(data.payment_method.clone()),
+        status: data.status.clone(),
+        is_renewal: data.is_renewal,
+        created_by: Some(data.created_by.clone()),
+        ..Default::default()
+    };
+
+    match Contract::create_for_customer(
+        &web_data.db,
+        auth_token.id as i32,
+        customer_uuid.into_inner(),
+        data.user_uuid,
+        contract,
+    )
+    .await
+    {
+        Ok(_) => HttpResponse::Created().json(messages::CONTRACT_CREATED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -169,33 +301,238 @@ struct ModifyContractJson {
     annual_fee: i32,
     payment_frequency: PaymentFrequency,
     payment_method: PaymentMethod,
+    status: Option<ContractStatus>,
+    is_renewal: Option<bool>,
 }
 async fn modify_contract(
     web_data: web::Data<WebData>,
     _: AuthenticationToken,
     data: web::Json<ModifyContractJson>,
 ) -> impl Responder {
+    if let Err(e) = data.contract_type.ensure_allowed(&web_data.contract_types) {
+        return ApiError::from(e).error_response();
+    }
+
     let contract = Contract {
         contract_number: Some(data.contract_number.clone()),
         contract_type: Some(data.contract_type.clone()),
         annual_fee: Some(data.annual_fee),
         payment_frequency: Some(data.payment_frequency.clone()),
         payment_method: Some(data.payment_method.clone()),
+        status: data.status.clone(),
+        is_renewal: data.is_renewal,
         ..Default::default()
     };
 
     match Contract::modify(&web_data.db, data.contract_uuid, contract).await {
-        Ok(_) => HttpResponse::Created().json("Sikeresen megváltoztattad a szerződést!"),
+        Ok(_) => HttpResponse::Ok().json(messages::CONTRACT_MODIFIED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
 
-async fn get_contracts_by_user_uuid(
+async fn get_contract_types(
     web_data: web::Data<WebData>,
     _: AuthenticationToken,
+) -> impl Responder {
+    let mut types: Vec<&String> = web_data.contract_types.iter().collect();
+    types.sort();
+    HttpResponse::Ok().json(types)
+}
+
+#[derive(Deserialize)]
+struct ListingScopeQuery {
+    #[serde(default)]
+    scope: ListingScope,
+}
+async fn get_contracts_by_user_uuid(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    query: web::Query<ListingScopeQuery>,
+) -> impl Responder {
+    let user_uuid = user_uuid.into_inner();
+    if query.scope == ListingScope::Team
+        && let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    match Contract::get_all(
+        &web_data.db,
+        &web_data.key,
+        auth_token.real_id as i32,
+        user_uuid,
+        query.scope,
+    )
+    .await
+    {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn get_contracts_grouped_by_customer(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
     user_uuid: web::Path<Uuid>,
 ) -> impl Responder {
-    match Contract::get_all(&web_data.db, &web_data.key, user_uuid.into_inner()).await {
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match Contract::get_grouped_by_customer(
+        &web_data.db,
+        &web_data.key,
+        auth_token.real_id as i32,
+        user_uuid,
+    )
+    .await
+    {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn get_contracts_handled_in_range(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    data: web::Json<ContractChartJson>,
+) -> impl Responder {
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
+    match Contract::get_handled_in_range(
+        &web_data.db,
+        &web_data.key,
+        auth_token.real_id as i32,
+        user_uuid,
+        data.start_date,
+        data.end_date,
+    )
+    .await
+    {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn get_commission_estimate(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    data: web::Json<ContractChartJson>,
+) -> impl Responder {
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
+    match Contract::estimate_commission(&web_data.db, user_uuid, data.start_date, data.end_date)
+        .await
+    {
+        Ok(estimate) => HttpResponse::Ok().json(estimate),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn get_unpaid_first_contracts(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+) -> impl Responder {
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match Contract::get_unpaid_first(
+        &web_data.db,
+        &web_data.key,
+        auth_token.real_id as i32,
+        user_uuid,
+    )
+    .await
+    {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: String,
+}
+async fn search_contracts(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    query: web::Query<SearchQuery>,
+    pagination: web::Query<PaginationQuery>,
+) -> impl Responder {
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match Contract::search(
+        &web_data.db,
+        &web_data.key,
+        auth_token.real_id as i32,
+        user_uuid,
+        &query.q,
+        pagination.into_inner(),
+    )
+    .await
+    {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct RenewalsQuery {
+    within_days: Option<i32>,
+}
+async fn get_team_renewals(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    query: web::Query<RenewalsQuery>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    let within_days = query.within_days.unwrap_or(30).clamp(1, 365);
+
+    match Contract::get_team_renewals(&web_data.db, auth_token.id as i32, within_days).await {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn get_duplicate_numbers(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    match Contract::find_duplicate_numbers(&web_data.db).await {
         Ok(list) => HttpResponse::Ok().json(list),
         Err(e) => ApiError::from(e).error_response(),
     }
@@ -212,6 +549,72 @@ async fn get_contract_by_uuid(
     }
 }
 
+async fn get_open_tasks(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    contract_uuid: web::Path<Uuid>,
+) -> impl Responder {
+    let contract_uuid = contract_uuid.into_inner();
+    let owner_uuid = match Contract::get_owner_uuid(&web_data.db, contract_uuid).await {
+        Ok(uuid) => uuid,
+        Err(e) => return ApiError::from(e).error_response(),
+    };
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, owner_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match Contract::get_open_tasks(
+        &web_data.db,
+        &web_data.key,
+        auth_token.real_id as i32,
+        contract_uuid,
+    )
+    .await
+    {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn get_contract_pdf(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    contract_uuid: web::Path<Uuid>,
+) -> impl Responder {
+    let contract_uuid = contract_uuid.into_inner();
+    let owner_uuid = match Contract::get_owner_uuid(&web_data.db, contract_uuid).await {
+        Ok(uuid) => uuid,
+        Err(e) => return ApiError::from(e).error_response(),
+    };
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, owner_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match Contract::render_pdf(
+        &web_data.db,
+        &web_data.key,
+        auth_token.real_id as i32,
+        contract_uuid,
+    )
+    .await
+    {
+        Ok((contract_number, pdf)) => {
+            let file_name: String = contract_number
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+                .collect();
+            HttpResponse::Ok()
+                .content_type("application/pdf")
+                .insert_header((
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"szerzodes_{file_name}.pdf\""),
+                ))
+                .body(pdf)
+        }
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
 async fn get_customer_uuid(
     web_data: web::Data<WebData>,
     _: AuthenticationToken,
@@ -232,7 +635,28 @@ async fn change_first_payment_state(
     match Contract::change_first_payment_state(&web_data.db, contract_uuid.into_inner(), data.0)
         .await
     {
-        Ok(_) => HttpResponse::Ok().json("Szerződés első díj befizetés módosítva!"),
+        Ok(_) => HttpResponse::Ok().json(messages::CONTRACT_FIRST_PAYMENT_CHANGED),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn change_contract_status(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    contract_uuid: web::Path<Uuid>,
+    data: web::Json<ContractStatus>,
+) -> impl Responder {
+    let contract_uuid = contract_uuid.into_inner();
+    let owner_uuid = match Contract::get_owner_uuid(&web_data.db, contract_uuid).await {
+        Ok(uuid) => uuid,
+        Err(e) => return ApiError::from(e).error_response(),
+    };
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, owner_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match Contract::change_status(&web_data.db, contract_uuid, data.into_inner()).await {
+        Ok(_) => HttpResponse::Ok().json(messages::CONTRACT_STATUS_CHANGED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -258,22 +682,35 @@ async fn change_contract_handler(
     )
     .await
     {
-        Ok(_) => HttpResponse::Created().json("Szerződésért felelős üzletkötő megváltoztatva!"),
+        Ok(_) => HttpResponse::Ok().json(messages::CONTRACT_HANDLER_CHANGED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
 
+#[derive(Deserialize)]
+struct DeleteContractQuery {
+    #[serde(default)]
+    partial: bool,
+}
 async fn delete_contract(
     web_data: web::Data<WebData>,
     auth_token: AuthenticationToken,
+    query: web::Query<DeleteContractQuery>,
     data: web::Json<Vec<Uuid>>,
 ) -> impl Responder {
     if let Err(e) = User::require_role(&web_data.db, UserRole::Agent, auth_token.id as i32).await {
         return ApiError::from(e).error_response();
     }
 
+    if query.partial {
+        return match Contract::delete_partial(&web_data.db, data.0).await {
+            Ok(result) => HttpResponse::Ok().json(result),
+            Err(e) => ApiError::from(e).error_response(),
+        };
+    }
+
     match Contract::delete(&web_data.db, data.0).await {
-        Ok(_) => HttpResponse::Created().json("Szerződés(ek) sikeresen törölve!"),
+        Ok(_) => HttpResponse::Ok().json(messages::CONTRACTS_DELETED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -386,7 +823,7 @@ async fn get_portfolio_chart(
     }
 
     match Contract::get_portfolio_chart(&web_data.db, auth_token.id as i32).await {
-        Ok(chart) => HttpResponse::Ok().json(chart),
+        Ok(chart) => HttpResponse::Ok().json(PortfolioDto::from(&chart)),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -401,7 +838,7 @@ async fn get_self_portfolio_chart(
         .unwrap();
 
     match Contract::get_portfolio_chart_by_user_uuid(&web_data.db, user_uuid).await {
-        Ok(chart) => HttpResponse::Ok().json(chart),
+        Ok(chart) => HttpResponse::Ok().json(PortfolioDto::from(&chart)),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -417,7 +854,80 @@ async fn get_portfolio_chart_by_user_uuid(
     }
 
     match Contract::get_portfolio_chart_by_user_uuid(&web_data.db, user_uuid.into_inner()).await {
-        Ok(chart) => HttpResponse::Ok().json(chart),
+        Ok(chart) => HttpResponse::Ok().json(PortfolioDto::from(&chart)),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn get_team_status_summary(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    match Contract::get_status_summary(&web_data.db, auth_token.id as i32).await {
+        Ok(summary) => HttpResponse::Ok().json(summary),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn get_portfolio_map(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match Contract::get_portfolio_map(&web_data.db, user_uuid).await {
+        Ok(map) => HttpResponse::Ok().json(map),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct PortfolioExportQuery {
+    user_uuid: Option<Uuid>,
+}
+async fn export_portfolio_chart(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    query: web::Query<PortfolioExportQuery>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    let chart = match query.user_uuid {
+        Some(user_uuid) => {
+            if let Err(e) =
+                User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await
+            {
+                return ApiError::from(e).error_response();
+            }
+            Contract::get_portfolio_chart_by_user_uuid(&web_data.db, user_uuid).await
+        }
+        None => Contract::get_portfolio_chart(&web_data.db, auth_token.id as i32).await,
+    };
+
+    match chart.and_then(|chart| Contract::portfolio_csv(&chart)) {
+        Ok(csv) => HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"portfolio.csv\"",
+            ))
+            .body(csv),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -432,6 +942,10 @@ async fn get_weekly_production_chart(
         return ApiError::from(e).error_response();
     }
 
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
     match Contract::get_weekly_production_chart(
         &web_data.db,
         auth_token.id as i32,
@@ -450,6 +964,10 @@ async fn get_self_weekly_production_chart(
     auth_token: AuthenticationToken,
     data: web::Json<ContractChartJson>,
 ) -> impl Responder {
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
     let user_uuid = User::get_uuid_by_id(&web_data.db, auth_token.id as i32)
         .await
         .unwrap()
@@ -479,6 +997,10 @@ async fn get_weekly_production_chart_by_user_uuid(
         return ApiError::from(e).error_response();
     }
 
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
     match Contract::get_weekly_production_chart_by_user_uuid(
         &web_data.db,
         user_uuid.into_inner(),
@@ -492,6 +1014,34 @@ async fn get_weekly_production_chart_by_user_uuid(
     }
 }
 
+async fn get_new_vs_renewal_chart(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    data: web::Json<ContractChartJson>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
+    match Contract::get_new_vs_renewal(
+        &web_data.db,
+        user_uuid.into_inner(),
+        data.start_date,
+        data.end_date,
+    )
+    .await
+    {
+        Ok(chart) => HttpResponse::Ok().json(chart),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
 async fn get_monthly_production_value_chart(
     web_data: web::Data<WebData>,
     auth_token: AuthenticationToken,
@@ -502,6 +1052,10 @@ async fn get_monthly_production_value_chart(
         return ApiError::from(e).error_response();
     }
 
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
     match Contract::get_monthly_production_value_chart(
         &web_data.db,
         auth_token.id as i32,
@@ -520,6 +1074,10 @@ async fn get_self_monthly_production_value_chart(
     auth_token: AuthenticationToken,
     data: web::Json<ContractChartJson>,
 ) -> impl Responder {
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
     let user_uuid = User::get_uuid_by_id(&web_data.db, auth_token.id as i32)
         .await
         .unwrap()
@@ -549,6 +1107,10 @@ async fn get_monthly_production_value_chart_by_user_uuid(
         return ApiError::from(e).error_response();
     }
 
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
     match Contract::get_monthly_production_value_chart_by_user_uuid(
         &web_data.db,
         user_uuid.into_inner(),
@@ -572,6 +1134,10 @@ async fn get_monthly_production_chart(
         return ApiError::from(e).error_response();
     }
 
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
     match Contract::get_monthly_production_chart(
         &web_data.db,
         auth_token.id as i32,
@@ -590,6 +1156,10 @@ async fn get_self_monthly_production_chart(
     auth_token: AuthenticationToken,
     data: web::Json<ContractChartJson>,
 ) -> impl Responder {
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
     let user_uuid = User::get_uuid_by_id(&web_data.db, auth_token.id as i32)
         .await
         .unwrap()
@@ -619,6 +1189,10 @@ async fn get_monthly_production_chart_by_user_uuid(
         return ApiError::from(e).error_response();
     }
 
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
     match Contract::get_monthly_production_chart_by_user_uuid(
         &web_data.db,
         user_uuid.into_inner(),
@@ -631,3 +1205,81 @@ async fn get_monthly_production_chart_by_user_uuid(
         Err(e) => ApiError::from(e).error_response(),
     }
 }
+
+async fn get_self_monthly_combined_chart(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    data: web::Json<ContractChartJson>,
+) -> impl Responder {
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
+    let user_uuid = User::get_uuid_by_id(&web_data.db, auth_token.id as i32)
+        .await
+        .unwrap()
+        .unwrap();
+
+    match Contract::get_monthly_combined(&web_data.db, user_uuid, data.start_date, data.end_date)
+        .await
+    {
+        Ok(chart) => HttpResponse::Ok().json(chart),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn get_monthly_combined_chart_by_user_uuid(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    data: web::Json<ContractChartJson>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
+    match Contract::get_monthly_combined(
+        &web_data.db,
+        user_uuid.into_inner(),
+        data.start_date,
+        data.end_date,
+    )
+    .await
+    {
+        Ok(chart) => HttpResponse::Ok().json(chart),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn get_type_trend_chart_by_user_uuid(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    data: web::Json<ContractChartJson>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
+    match Contract::get_type_trend(
+        &web_data.db,
+        user_uuid.into_inner(),
+        data.start_date,
+        data.end_date,
+    )
+    .await
+    {
+        Ok(chart) => HttpResponse::Ok().json(chart),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
@@ -1,5 +1,8 @@
+use std::collections::HashMap;
+
 use actix_web::{HttpResponse, Responder, ResponseError, Scope, web};
-use serde::Deserialize;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::models::intervention_task::InterventionTask;
@@ -7,11 +10,16 @@ use crate::{
     extractors::authentication_token::AuthenticationToken,
     models::{
         contract::Contract,
-        customer::Customer,
+        customer::{Customer, CustomerFilter, CustomerFlag, DedupStrategy},
         lead::Lead,
-        user::{User, UserRole},
+        user::{ListingScope, ReassignEntity, User, UserRole},
+    },
+    utils::{
+        contact::{Email, Phone},
+        error::ApiError,
+        messages,
+        pagination::PaginationQuery,
     },
-    utils::error::ApiError,
     web_data::WebData,
 };
 
@@ -19,6 +27,7 @@ pub fn customer_scope() -> Scope {
     web::scope("/customer")
         .route("/create", web::post().to(create_customer))
         .route("/modify", web::put().to(modify_customer))
+        .route("/dedup-candidates", web::post().to(get_dedup_candidates))
         .route(
             "/comment/save/{customer_uuid}",
             web::put().to(save_comment_customer),
@@ -27,6 +36,10 @@ pub fn customer_scope() -> Scope {
             "/leads/{customer_uuid}",
             web::get().to(get_leads_by_customer_uuid),
         )
+        .route(
+            "/leads/{customer_uuid}/paginated",
+            web::get().to(get_paginated_leads_by_customer_uuid),
+        )
         .route(
             "/contracts/{customer_uuid}",
             web::get().to(get_contracts_by_customer_uuid),
@@ -36,7 +49,13 @@ pub fn customer_scope() -> Scope {
             web::get().to(get_intervention_tasks),
         )
         .route("/get-all/{user_uuid}", web::get().to(get_customers_by_uuid))
+        .route("/dormant/{user_uuid}", web::get().to(get_dormant_customers))
         .route("/get/{customer_uuid}", web::get().to(get_customer_by_uuid))
+        .route("/{customer_uuid}/flag", web::put().to(set_customer_flag))
+        .route(
+            "/{customer_uuid}/access-log",
+            web::get().to(get_customer_access_log),
+        )
         .route("/change/user", web::put().to(change_customer_handler))
         .route("/delete", web::delete().to(delete_customer))
 }
@@ -44,10 +63,13 @@ pub fn customer_scope() -> Scope {
 #[derive(Deserialize, Clone)]
 struct CreateCustomerJson {
     user_uuid: Uuid,
+    #[serde(default)]
     full_name: String,
-    phone_number: String,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    phone_number: Phone,
     address: String,
-    email: String,
+    email: Email,
     created_by: String,
 }
 async fn create_customer(
@@ -56,9 +78,11 @@ async fn create_customer(
 ) -> impl Responder {
     let customer = Customer {
         full_name: Some(data.full_name.clone()),
-        phone_number: Some(data.phone_number.clone()),
+        first_name: data.first_name.clone(),
+        last_name: data.last_name.clone(),
+        phone_number: Some(data.phone_number.to_string()),
         address: Some(data.address.clone()),
-        email: Some(data.email.clone()),
+        email: Some(data.email.to_string()),
         created_by: Some(data.created_by.clone()),
         ..Default::default()
     };
@@ -72,7 +96,7 @@ async fn create_customer(
     )
     .await
     {
-        Ok(_) => HttpResponse::Created().json("Sikeresen létre lett hozva!"),
+        Ok(_) => HttpResponse::Created().json(messages::CUSTOMER_CREATED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -80,10 +104,13 @@ async fn create_customer(
 #[derive(Deserialize, Clone)]
 struct ModifyCustomerJson {
     customer_uuid: Uuid,
+    #[serde(default)]
     full_name: String,
-    phone_number: String,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    phone_number: Phone,
     address: String,
-    email: String,
+    email: Email,
 }
 async fn modify_customer(
     web_data: web::Data<WebData>,
@@ -91,9 +118,11 @@ async fn modify_customer(
 ) -> impl Responder {
     let customer = Customer {
         full_name: Some(data.full_name.clone()),
-        phone_number: Some(data.phone_number.clone()),
+        first_name: data.first_name.clone(),
+        last_name: data.last_name.clone(),
+        phone_number: Some(data.phone_number.to_string()),
         address: Some(data.address.clone()),
-        email: Some(data.email.clone()),
+        email: Some(data.email.to_string()),
         ..Default::default()
     };
 
@@ -106,7 +135,7 @@ async fn modify_customer(
     )
     .await
     {
-        Ok(_) => HttpResponse::Created().json("Sikeresen módosítottad az ügyfelet!"),
+        Ok(_) => HttpResponse::Ok().json(messages::CUSTOMER_MODIFIED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -117,18 +146,64 @@ async fn save_comment_customer(
     customer_uuid: web::Path<Uuid>,
 ) -> impl Responder {
     match Customer::save_comment(&web_data.db, customer_uuid.into_inner(), data.clone()).await {
-        Ok(_) => HttpResponse::Created().json("Sikeresen elmentetted az ügyfél megjegyzését!"),
+        Ok(_) => HttpResponse::Ok().json(messages::CUSTOMER_COMMENT_SAVED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
 
 async fn get_customers_by_uuid(
     web_data: web::Data<WebData>,
-    _: AuthenticationToken,
+    auth_token: AuthenticationToken,
     user_uuid: web::Path<Uuid>,
+    query: web::Query<CustomerFilter>,
 ) -> impl Responder {
-    match Customer::get_all(&web_data.db, &web_data.key, user_uuid.into_inner()).await {
-        Ok(customers) => HttpResponse::Created().json(customers),
+    let user_uuid = user_uuid.into_inner();
+    if query.scope == ListingScope::Team
+        && let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    match Customer::get_all(
+        &web_data.db,
+        &web_data.key,
+        auth_token.real_id as i32,
+        user_uuid,
+        &query,
+    )
+    .await
+    {
+        Ok(customers) => HttpResponse::Ok().json(customers),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct DormantQuery {
+    inactive_days: Option<i32>,
+}
+async fn get_dormant_customers(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    query: web::Query<DormantQuery>,
+) -> impl Responder {
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    let inactive_days = query.inactive_days.unwrap_or(90).max(1);
+    match Customer::get_dormant(
+        &web_data.db,
+        &web_data.key,
+        auth_token.real_id as i32,
+        user_uuid,
+        inactive_days,
+    )
+    .await
+    {
+        Ok(list) => HttpResponse::Ok().json(list),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -144,6 +219,24 @@ async fn get_leads_by_customer_uuid(
     }
 }
 
+async fn get_paginated_leads_by_customer_uuid(
+    web_data: web::Data<WebData>,
+    _: AuthenticationToken,
+    customer_uuid: web::Path<Uuid>,
+    pagination: web::Query<PaginationQuery>,
+) -> impl Responder {
+    match Lead::get_by_customer_uuid_paginated(
+        &web_data.db,
+        customer_uuid.into_inner(),
+        pagination.into_inner(),
+    )
+    .await
+    {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
 async fn get_contracts_by_customer_uuid(
     web_data: web::Data<WebData>,
     _: AuthenticationToken,
@@ -168,19 +261,120 @@ async fn get_intervention_tasks(
 
 async fn get_customer_by_uuid(
     web_data: web::Data<WebData>,
-    _: AuthenticationToken,
+    auth_token: AuthenticationToken,
+    customer_uuid: web::Path<Uuid>,
+) -> impl Responder {
+    match Customer::get_by_uuid(
+        &web_data.db,
+        &web_data.key,
+        auth_token.real_id as i32,
+        customer_uuid.into_inner(),
+    )
+    .await
+    {
+        Ok(customers) => HttpResponse::Ok().json(customers),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct DedupCandidatesJson {
+    full_name: String,
+    phone_number: Option<String>,
+    email: Option<String>,
+    address: Option<String>,
+    strategy: Option<DedupStrategy>,
+}
+async fn get_dedup_candidates(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    data: web::Json<DedupCandidatesJson>,
+) -> impl Responder {
+    let user_ids = match User::get_authority_ids(&web_data.db, auth_token.id as i32).await {
+        Ok(user_ids) => user_ids,
+        Err(e) => return ApiError::from(e).error_response(),
+    };
+    let data = data.into_inner();
+    let customer = Customer {
+        full_name: Some(data.full_name),
+        phone_number: data.phone_number,
+        email: data.email,
+        address: data.address,
+        ..Default::default()
+    };
+
+    match Customer::find_dedup_candidates(
+        &web_data.db,
+        &web_data.key,
+        &web_data.hmac_secret,
+        &user_ids,
+        &customer,
+        data.strategy.unwrap_or_else(DedupStrategy::from_env),
+    )
+    .await
+    {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn get_customer_access_log(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    customer_uuid: web::Path<Uuid>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    match Customer::get_access_log(&web_data.db, customer_uuid.into_inner()).await {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn set_customer_flag(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
     customer_uuid: web::Path<Uuid>,
+    data: web::Json<Option<CustomerFlag>>,
 ) -> impl Responder {
-    match Customer::get_by_uuid(&web_data.db, &web_data.key, customer_uuid.into_inner()).await {
-        Ok(customers) => HttpResponse::Created().json(customers),
+    let customer_uuid = customer_uuid.into_inner();
+    let owner_uuid = match Customer::get_owner_uuid(&web_data.db, customer_uuid).await {
+        Ok(uuid) => uuid,
+        Err(e) => return ApiError::from(e).error_response(),
+    };
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, owner_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match Customer::set_flag(&web_data.db, customer_uuid, data.0).await {
+        Ok(_) => HttpResponse::Ok().json(messages::CUSTOMER_FLAG_CHANGED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
 
 #[derive(Deserialize)]
 struct ChangeCustomersHandlerJson {
-    user_full_name: String,
+    user_full_name: Option<String>,
+    #[serde(default)]
     customer_uuids: Vec<Uuid>,
+    #[serde(default)]
+    with_related: bool,
+    target_user_uuid: Option<Uuid>,
+    // Set to move everything a user handled in the from..to window instead of the listed customers
+    source_user_uuid: Option<Uuid>,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+    // Defaults to every entity type
+    #[serde(default)]
+    entities: Vec<ReassignEntity>,
+}
+#[derive(Serialize)]
+struct ReassignRecordsResponse {
+    message: String,
+    affected: HashMap<String, u64>,
 }
 async fn change_customer_handler(
     web_data: web::Data<WebData>,
@@ -191,14 +385,60 @@ async fn change_customer_handler(
         return ApiError::from(e).error_response();
     }
 
-    match Customer::change_handler(
-        &web_data.db,
-        data.user_full_name.clone(),
-        data.customer_uuids.clone(),
-    )
-    .await
-    {
-        Ok(_) => HttpResponse::Created().json("Ügyfélt kezelő üzletkötő sikeresen megváltoztatva!"),
+    if let Some(source_user_uuid) = data.source_user_uuid {
+        let Some(target_user_uuid) = data.target_user_uuid else {
+            return ApiError::Validation("Célfelhasználó megadása kötelező!".to_string())
+                .error_response();
+        };
+        if let (Some(from), Some(to)) = (data.from, data.to)
+            && to < from
+        {
+            return ApiError::Validation(
+                "A kezdő dátum nem lehet későbbi a záró dátumnál".to_string(),
+            )
+            .error_response();
+        }
+        let entities = if data.entities.is_empty() {
+            ReassignEntity::ALL.to_vec()
+        } else {
+            data.entities.clone()
+        };
+
+        return match User::reassign_records(
+            &web_data.db,
+            source_user_uuid,
+            target_user_uuid,
+            data.from,
+            data.to,
+            &entities,
+        )
+        .await
+        {
+            Ok(affected) => HttpResponse::Ok().json(ReassignRecordsResponse {
+                message: messages::USER_RECORDS_REASSIGNED.to_string(),
+                affected,
+            }),
+            Err(e) => ApiError::from(e).error_response(),
+        };
+    }
+
+    let result = if data.with_related {
+        let Some(target_user_uuid) = data.target_user_uuid else {
+            return ApiError::Validation("Célfelhasználó megadása kötelező!".to_string())
+                .error_response();
+        };
+        Customer::reassign_with_related(&web_data.db, data.customer_uuids.clone(), target_user_uuid)
+            .await
+    } else {
+        let Some(user_full_name) = data.user_full_name.clone() else {
+            return ApiError::Validation("Célfelhasználó megadása kötelező!".to_string())
+                .error_response();
+        };
+        Customer::change_handler(&web_data.db, user_full_name, data.customer_uuids.clone()).await
+    };
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(messages::CUSTOMER_HANDLER_CHANGED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -213,27 +453,7 @@ async fn delete_customer(
     }
 
     match Customer::delete(&web_data.db, data.0).await {
-        Ok(_) => HttpResponse::Created().json("Ügyfél sikeresen létrehozva!"),
+        Ok(_) => HttpResponse::Ok().json(messages::CUSTOMERS_DELETED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
-
-// #[derive(Deserialize)]
-// struct PaginationQuery {
-//     user_id: i32,
-//     limit: Option<i64>,
-//     offset: Option<i64>,
-// }
-
-// async fn list_contacts(
-//     web_data: web::Data<WebData>,
-//     query: web::Query<PaginationQuery>,
-// ) -> impl Responder {
-//     let limit = query.limit.unwrap_or(20).clamp(1, 100);
-//     let offset = query.offset.unwrap_or(0).max(0);
-
-//     match User::list_contacts_paginated(&db, query.user_id, limit, offset).await {
-//         Ok(list) => HttpResponse::Ok().json(list),
-//         Err(e) => ApiError::from(e).error_response(),
-//     }
-// }
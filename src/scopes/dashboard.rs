@@ -0,0 +1,71 @@
+use actix_web::{HttpResponse, Responder, ResponseError, Scope, web};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    extractors::authentication_token::AuthenticationToken,
+    models::{
+        contract::Contract,
+        user::{User, UserRole},
+    },
+    utils::{error::ApiError, validation::validate_chart_span},
+    web_data::WebData,
+};
+
+pub fn dashboard_scope() -> Scope {
+    web::scope("/dashboard")
+        .route(
+            "/meeting-conversion/{user_uuid}",
+            web::post().to(get_meeting_conversion),
+        )
+        .route("/kpis/{user_uuid}", web::get().to(get_kpis))
+}
+
+#[derive(Deserialize)]
+struct DashboardJson {
+    start_date: NaiveDateTime,
+    end_date: NaiveDateTime,
+}
+async fn get_meeting_conversion(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    data: web::Json<DashboardJson>,
+) -> impl Responder {
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
+    match Contract::get_meeting_conversion(&web_data.db, user_uuid, data.start_date, data.end_date)
+        .await
+    {
+        Ok(chart) => HttpResponse::Ok().json(chart),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn get_kpis(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+) -> impl Responder {
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match User::get_kpis(&web_data.db, user_uuid).await {
+        Ok(kpis) => HttpResponse::Ok().json(kpis),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
@@ -0,0 +1,37 @@
+use actix_web::{HttpResponse, Responder, Scope, web};
+use serde::Serialize;
+
+use crate::web_data::WebData;
+
+pub fn health_scope() -> Scope {
+    web::scope("/ready").route("", web::get().to(ready))
+}
+
+#[derive(Serialize)]
+struct ReadyResponse {
+    database: bool,
+    pool_size: u32,
+    pool_idle: usize,
+    pool_in_use: usize,
+    max_connections: u32,
+}
+async fn ready(web_data: web::Data<WebData>) -> impl Responder {
+    let pool = &web_data.db.pool;
+    let database = sqlx::query("SELECT 1").execute(pool).await.is_ok();
+
+    let pool_size = pool.size();
+    let pool_idle = pool.num_idle();
+    let response = ReadyResponse {
+        database,
+        pool_size,
+        pool_idle,
+        pool_in_use: (pool_size as usize).saturating_sub(pool_idle),
+        max_connections: pool.options().get_max_connections(),
+    };
+
+    if database {
+        HttpResponse::Ok().json(response)
+    } else {
+        HttpResponse::ServiceUnavailable().json(response)
+    }
+}
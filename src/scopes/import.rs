@@ -0,0 +1,122 @@
+use actix_web::{HttpResponse, Responder, ResponseError, Scope, web};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    extractors::authentication_token::AuthenticationToken,
+    models::{
+        contract::{Contract, ContractStatus, ContractType, PaymentFrequency, PaymentMethod},
+        customer::Customer,
+        user::{User, UserRole},
+    },
+    utils::{
+        contact::{Email, Phone},
+        error::ApiError,
+    },
+    web_data::WebData,
+};
+
+const MAX_BOOK_CUSTOMERS: usize = 500;
+
+pub fn import_scope() -> Scope {
+    web::scope("/import").route("/book/{user_uuid}", web::post().to(import_book))
+}
+
+#[derive(Deserialize, Clone)]
+struct BookCustomerJson {
+    full_name: String,
+    phone_number: Phone,
+    address: String,
+    email: Email,
+}
+#[derive(Deserialize, Clone)]
+struct BookContractJson {
+    contract_number: String,
+    contract_type: ContractType,
+    annual_fee: i32,
+    payment_frequency: PaymentFrequency,
+    payment_method: PaymentMethod,
+    status: Option<ContractStatus>,
+    is_renewal: Option<bool>,
+}
+#[derive(Deserialize, Clone)]
+struct BookEntryJson {
+    customer: BookCustomerJson,
+    contracts: Vec<BookContractJson>,
+    created_by: String,
+}
+async fn import_book(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    data: web::Json<Vec<BookEntryJson>>,
+) -> impl Responder {
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    if data.is_empty() || data.len() > MAX_BOOK_CUSTOMERS {
+        return ApiError::Validation(format!(
+            "Egyszerre 1 és {MAX_BOOK_CUSTOMERS} közötti ügyfél importálható!"
+        ))
+        .error_response();
+    }
+
+    let mut book = Vec::with_capacity(data.len());
+    for (index, entry) in data.into_inner().into_iter().enumerate() {
+        let customer = Customer {
+            full_name: Some(entry.customer.full_name),
+            phone_number: Some(entry.customer.phone_number.to_string()),
+            email: Some(entry.customer.email.to_string()),
+            address: Some(entry.customer.address),
+            created_by: Some(entry.created_by.clone()),
+            ..Default::default()
+        };
+
+        let mut contracts = Vec::with_capacity(entry.contracts.len());
+        for contract in entry.contracts {
+            if let Err(e) = contract
+                .contract_type
+                .ensure_allowed(&web_data.contract_types)
+            {
+                return ApiError::Validation(format!(
+                    "{} ({index}. ügyfél)",
+                    ApiError::from(e).message()
+                ))
+                .error_response();
+            }
+
+            contracts.push(Contract {
+                contract_number: Some(contract.contract_number),
+                contract_type: Some(contract.contract_type),
+                annual_fee: Some(contract.annual_fee),
+                payment_frequency: Some(contract.payment_frequency),
+                payment_method: Some(contract.payment_method),
+                status: contract.status,
+                is_renewal: contract.is_renewal,
+                created_by: Some(entry.created_by.clone()),
+                ..Default::default()
+            });
+        }
+        book.push((customer, contracts));
+    }
+
+    match Contract::import_book(
+        &web_data.db,
+        &web_data.key,
+        &web_data.hmac_secret,
+        auth_token.id as i32,
+        user_uuid,
+        book,
+    )
+    .await
+    {
+        Ok(summary) => HttpResponse::Created().json(summary),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
@@ -1,6 +1,6 @@
-use actix_web::{HttpResponse, Responder, ResponseError, Scope, web};
+use actix_web::{HttpResponse, Responder, ResponseError, Scope, http::header, web};
 use chrono::NaiveDateTime;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::models::intervention_task::{InterventionTask, InterventionTaskStatus};
@@ -10,7 +10,11 @@ use crate::{
         customer::Customer,
         user::{User, UserRole},
     },
-    utils::error::ApiError,
+    utils::{
+        contact::{Email, Phone},
+        error::ApiError,
+        messages, validation,
+    },
     web_data::WebData,
 };
 
@@ -28,6 +32,18 @@ pub fn intervention_task_scope() -> Scope {
             "/get-all/{user_uuid}",
             web::get().to(get_intervention_tasks_by_user_uuid),
         )
+        .route(
+            "/export/{user_uuid}.csv",
+            web::get().to(export_intervention_tasks),
+        )
+        .route(
+            "/products/{user_uuid}",
+            web::get().to(get_distinct_products),
+        )
+        .route(
+            "/status/bulk",
+            web::put().to(change_intervention_tasks_status_bulk),
+        )
         .route(
             "/{intervention_task_uuid}",
             web::get().to(get_intervention_task_by_uuid),
@@ -41,14 +57,23 @@ pub fn intervention_task_scope() -> Scope {
             web::put().to(change_intervention_task_handler),
         )
         .route("/delete", web::delete().to(delete_intervention_task))
+        .route(
+            "/contract-links/backfill",
+            web::post().to(backfill_contract_links),
+        )
+        // CHART API's
+        .route(
+            "/chart/balance/{user_uuid}",
+            web::get().to(get_balance_summary_chart),
+        )
 }
 
 #[derive(Deserialize, Clone, Debug)]
 struct CustomerJson {
     full_name: String,
-    phone_number: String,
+    phone_number: Phone,
     address: String,
-    email: String,
+    email: Email,
 }
 #[derive(Deserialize, Clone, Debug)]
 struct InterventionTaskJson {
@@ -65,6 +90,15 @@ struct CreateInterventionTaskJson {
     customer: CustomerJson,
     intervention_task: InterventionTaskJson,
     created_by: String,
+    // Accepts a processing deadline in the past
+    #[serde(default)]
+    force: bool,
+}
+
+#[derive(Serialize)]
+struct CreateInterventionTaskResponse {
+    message: String,
+    customer_created: bool,
 }
 async fn create_intervention_task(
     web_data: web::Data<WebData>,
@@ -72,10 +106,16 @@ async fn create_intervention_task(
     data: web::Json<CreateInterventionTaskJson>,
     customer_uuid: web::Path<Uuid>,
 ) -> impl Responder {
+    if let Err(e) =
+        validation::validate_deadline(data.intervention_task.processing_deadline, data.force)
+    {
+        return e.error_response();
+    }
+
     let customer = Customer {
         full_name: Some(data.customer.full_name.clone()),
-        phone_number: Some(data.customer.phone_number.clone()),
-        email: Some(data.customer.email.clone()),
+        phone_number: Some(data.customer.phone_number.to_string()),
+        email: Some(data.customer.email.to_string()),
         address: Some(data.customer.address.clone()),
         created_by: Some(data.created_by.clone()),
         ..Default::default()
@@ -102,7 +142,10 @@ async fn create_intervention_task(
     )
     .await
     {
-        Ok(_) => HttpResponse::Created().json("Intervenciós feladat sikeresen létrehozva!"),
+        Ok((_, customer_created)) => HttpResponse::Created().json(CreateInterventionTaskResponse {
+            message: messages::INTERVENTION_TASK_CREATED.to_string(),
+            customer_created,
+        }),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -131,24 +174,43 @@ async fn modify_intervention_task(
     )
     .await
     {
-        Ok(_) => {
-            HttpResponse::Created().json("Sikeresen megváltoztattad az intervenciós feladatot!")
-        }
+        Ok(_) => HttpResponse::Ok().json(messages::INTERVENTION_TASK_MODIFIED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
 
 async fn get_intervention_tasks_by_user_uuid(
     web_data: web::Data<WebData>,
-    _: AuthenticationToken,
+    auth_token: AuthenticationToken,
     user_uuid: web::Path<Uuid>,
 ) -> impl Responder {
-    match InterventionTask::get_all(&web_data.db, &web_data.key, user_uuid.into_inner()).await {
+    match InterventionTask::get_all(
+        &web_data.db,
+        &web_data.key,
+        auth_token.real_id as i32,
+        user_uuid.into_inner(),
+    )
+    .await
+    {
         Ok(list) => HttpResponse::Ok().json(list),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
 
+async fn backfill_contract_links(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Leader, auth_token.id as i32).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match InterventionTask::backfill_contract_links(&web_data.db).await {
+        Ok(updated) => HttpResponse::Ok().json(updated),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
 async fn get_intervention_task_by_uuid(
     web_data: web::Data<WebData>,
     _: AuthenticationToken,
@@ -160,6 +222,90 @@ async fn get_intervention_task_by_uuid(
     }
 }
 
+#[derive(Deserialize)]
+struct ChangeStatusBulkJson {
+    intervention_task_uuids: Vec<Uuid>,
+    status: InterventionTaskStatus,
+}
+#[derive(Serialize)]
+struct ChangeStatusBulkResponse {
+    message: String,
+    affected: u64,
+}
+async fn change_intervention_tasks_status_bulk(
+    web_data: web::Data<WebData>,
+    _: AuthenticationToken,
+    data: web::Json<ChangeStatusBulkJson>,
+) -> impl Responder {
+    let data = data.into_inner();
+    match InterventionTask::change_status_bulk(
+        &web_data.db,
+        data.intervention_task_uuids,
+        data.status,
+    )
+    .await
+    {
+        Ok(affected) => HttpResponse::Ok().json(ChangeStatusBulkResponse {
+            message: messages::INTERVENTION_TASKS_STATUS_CHANGED.to_string(),
+            affected,
+        }),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct ProductsQuery {
+    #[serde(default)]
+    prefix: String,
+    limit: Option<i64>,
+}
+async fn get_distinct_products(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    query: web::Query<ProductsQuery>,
+) -> impl Responder {
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    let limit = query.limit.unwrap_or(10).clamp(1, 50);
+    match InterventionTask::distinct_products(&web_data.db, user_uuid, &query.prefix, limit).await {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn export_intervention_tasks(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+) -> impl Responder {
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match InterventionTask::export_csv(
+        &web_data.db,
+        &web_data.key,
+        auth_token.real_id as i32,
+        user_uuid,
+    )
+    .await
+    {
+        Ok(csv) => HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"intervencios_feladatok.csv\"",
+            ))
+            .body(csv),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
 async fn get_customer_uuid(
     web_data: web::Data<WebData>,
     _: AuthenticationToken,
@@ -195,8 +341,7 @@ async fn change_intervention_task_handler(
     )
     .await
     {
-        Ok(_) => HttpResponse::Created()
-            .json("Intervenciós feladat(ok)ért felelős üzletkötő megváltoztatva!"),
+        Ok(_) => HttpResponse::Ok().json(messages::INTERVENTION_TASK_HANDLER_CHANGED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -211,7 +356,23 @@ async fn delete_intervention_task(
     }
 
     match InterventionTask::delete(&web_data.db, data.0).await {
-        Ok(_) => HttpResponse::Created().json("Intervenciós feladat(ok) sikeresen törölve!"),
+        Ok(_) => HttpResponse::Ok().json(messages::INTERVENTION_TASKS_DELETED),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn get_balance_summary_chart(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    match InterventionTask::get_balance_summary(&web_data.db, user_uuid.into_inner()).await {
+        Ok(chart) => HttpResponse::Ok().json(chart),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
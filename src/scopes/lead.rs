@@ -1,5 +1,6 @@
 use actix_web::{HttpResponse, Responder, ResponseError, Scope, web};
-use serde::Deserialize;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
@@ -7,9 +8,14 @@ use crate::{
     models::{
         customer::Customer,
         lead::{Lead, LeadStatus, LeadType},
-        user::{User, UserRole},
+        user::{ListingScope, User, UserRole},
+    },
+    utils::{
+        contact::{Email, Phone},
+        error::ApiError,
+        messages,
+        validation::validate_chart_span,
     },
-    utils::error::ApiError,
     web_data::WebData,
 };
 
@@ -21,18 +27,27 @@ pub fn lead_scope() -> Scope {
             "/get-all/{user_uuid}",
             web::get().to(get_leads_by_user_uuid),
         )
+        .route(
+            "/by-creator/{user_uuid}",
+            web::get().to(get_leads_by_creator),
+        )
         .route("/{lead_uuid}", web::get().to(get_lead_by_uuid))
         .route("/{lead_uuid}/customer", web::get().to(get_customer_uuid))
         .route("/change/user", web::put().to(change_lead_handler))
         .route("/delete", web::delete().to(delete_lead))
+        // CHART API's
+        .route(
+            "/chart/inquiry/{user_uuid}",
+            web::post().to(get_inquiry_breakdown_chart),
+        )
 }
 
 #[derive(Deserialize, Clone)]
 struct CustomerJson {
     full_name: String,
-    phone_number: String,
+    phone_number: Phone,
     address: String,
-    email: String,
+    email: Email,
 }
 #[derive(Deserialize, Clone)]
 struct CreateLeadJson {
@@ -43,14 +58,22 @@ struct CreateLeadJson {
     user_uuid: Uuid,
     created_by: String,
 }
+
+#[derive(Serialize)]
+struct CreateLeadResponse {
+    message: String,
+    customer_created: bool,
+}
+// Lead intake works without signing in, a DoNotContact customer is then always refused
 async fn create_lead(
     web_data: web::Data<WebData>,
+    auth_token: Option<AuthenticationToken>,
     data: web::Json<CreateLeadJson>,
 ) -> impl Responder {
     let customer = Customer {
         full_name: Some(data.customer.full_name.clone()),
-        phone_number: Some(data.customer.phone_number.clone()),
-        email: Some(data.customer.email.clone()),
+        phone_number: Some(data.customer.phone_number.to_string()),
+        email: Some(data.customer.email.to_string()),
         address: Some(data.customer.address.clone()),
         created_by: Some(data.created_by.clone()),
         ..Default::default()
@@ -67,13 +90,17 @@ async fn create_lead(
         &web_data.db,
         &web_data.key,
         &web_data.hmac_secret,
+        auth_token.map(|token| token.id as i32),
         data.user_uuid,
         customer,
         lead,
     )
     .await
     {
-        Ok(_) => HttpResponse::Created().json("Tevékenység sikeresen létrehozva!"),
+        Ok(customer_created) => HttpResponse::Created().json(CreateLeadResponse {
+            message: messages::LEAD_CREATED.to_string(),
+            customer_created,
+        }),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -98,17 +125,63 @@ async fn modify_lead(
     };
 
     match Lead::modify(&web_data.db, data.lead_uuid, lead).await {
-        Ok(_) => HttpResponse::Created().json("Sikeresen megváltoztattad a tevékenységet!"),
+        Ok(_) => HttpResponse::Ok().json(messages::LEAD_MODIFIED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
 
+#[derive(Deserialize)]
+struct ListingScopeQuery {
+    #[serde(default)]
+    scope: ListingScope,
+}
 async fn get_leads_by_user_uuid(
     web_data: web::Data<WebData>,
-    _: AuthenticationToken,
+    auth_token: AuthenticationToken,
     user_uuid: web::Path<Uuid>,
+    query: web::Query<ListingScopeQuery>,
 ) -> impl Responder {
-    match Lead::get_all(&web_data.db, &web_data.key, user_uuid.into_inner()).await {
+    let user_uuid = user_uuid.into_inner();
+    if query.scope == ListingScope::Team
+        && let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    match Lead::get_all(
+        &web_data.db,
+        &web_data.key,
+        auth_token.real_id as i32,
+        user_uuid,
+        query.scope,
+    )
+    .await
+    {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct CreatorQuery {
+    creator: String,
+}
+async fn get_leads_by_creator(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    query: web::Query<CreatorQuery>,
+) -> impl Responder {
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match Lead::get_by_creator(&web_data.db, &web_data.key, user_uuid, &query.creator).await {
         Ok(list) => HttpResponse::Ok().json(list),
         Err(e) => ApiError::from(e).error_response(),
     }
@@ -157,9 +230,7 @@ async fn change_lead_handler(
     )
     .await
     {
-        Ok(_) => {
-            HttpResponse::Created().json("Tevékenység(ek)ért felelős üzletkötő megváltoztatva!")
-        }
+        Ok(_) => HttpResponse::Ok().json(messages::LEAD_HANDLER_CHANGED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -174,7 +245,41 @@ async fn delete_lead(
     }
 
     match Lead::delete(&web_data.db, data.0).await {
-        Ok(_) => HttpResponse::Created().json("Tevékenység(ek) sikeresen törölve!"),
+        Ok(_) => HttpResponse::Ok().json(messages::LEADS_DELETED),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+// LEAD CHART API's
+#[derive(Deserialize)]
+struct LeadChartJson {
+    start_date: NaiveDateTime,
+    end_date: NaiveDateTime,
+}
+async fn get_inquiry_breakdown_chart(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    data: web::Json<LeadChartJson>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
+    match Lead::get_inquiry_breakdown(
+        &web_data.db,
+        user_uuid.into_inner(),
+        data.start_date,
+        data.end_date,
+    )
+    .await
+    {
+        Ok(chart) => HttpResponse::Ok().json(chart),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -0,0 +1,37 @@
+use actix_web::{HttpResponse, Responder, ResponseError, Scope, web};
+use serde::Deserialize;
+
+use crate::{
+    extractors::authentication_token::AuthenticationToken,
+    models::customer::Customer,
+    utils::{contact::Phone, error::ApiError},
+    web_data::WebData,
+};
+
+pub fn lookup_scope() -> Scope {
+    web::scope("/lookup").route("/phone/full", web::post().to(lookup_phone_full))
+}
+
+#[derive(Deserialize)]
+struct PhoneLookupJson {
+    phone_number: Phone,
+}
+async fn lookup_phone_full(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    data: web::Json<PhoneLookupJson>,
+) -> impl Responder {
+    match Customer::lookup_by_phone(
+        &web_data.db,
+        &web_data.key,
+        &web_data.hmac_secret,
+        auth_token.id as i32,
+        auth_token.real_id as i32,
+        data.phone_number.as_str(),
+    )
+    .await
+    {
+        Ok(lookup) => HttpResponse::Ok().json(lookup),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
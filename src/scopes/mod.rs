@@ -6,3 +6,12 @@ pub mod user;
 pub mod user_date;
 pub mod recommendation;
 pub mod recruitment;
+pub mod dashboard;
+pub mod webhook;
+pub mod audit;
+pub mod search;
+pub mod settings;
+pub mod import;
+pub mod stats;
+pub mod lookup;
+pub mod health;
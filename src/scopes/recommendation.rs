@@ -8,13 +8,14 @@ use crate::{
         recommendation::CustomerRecommendation,
         user::{User, UserRole},
     },
-    utils::error::ApiError,
+    utils::{contact::Phone, error::ApiError, messages},
     web_data::WebData,
 };
 
 pub fn recommendation_scope() -> Scope {
     web::scope("/recommendation")
         .route("/create/{user_uuid}", web::post().to(create_recommendation))
+        .route("/check", web::post().to(check_recommendation))
         .route(
             "/modify/{recommendation_uuid}",
             web::put().to(modify_recommendation),
@@ -23,6 +24,14 @@ pub fn recommendation_scope() -> Scope {
             "/get-all/{user_uuid}",
             web::get().to(get_recommendations_by_user_uuid),
         )
+        .route(
+            "/by-referral/{user_uuid}",
+            web::get().to(get_recommendations_by_referral),
+        )
+        .route(
+            "/leaderboard/{user_uuid}",
+            web::get().to(get_referrer_leaderboard),
+        )
         .route(
             "/{recommendation_uuid}",
             web::get().to(get_recommendation_by_uuid),
@@ -34,7 +43,7 @@ pub fn recommendation_scope() -> Scope {
 #[derive(Deserialize, Clone)]
 struct CreateRecommendationJson {
     full_name: String,
-    phone_number: String,
+    phone_number: Phone,
     city: String,
     referral_name: String,
     created_by: String,
@@ -46,7 +55,7 @@ async fn create_recommendation(
 ) -> impl Responder {
     let rec = CustomerRecommendation {
         full_name: Some(data.full_name.clone()),
-        phone_number: Some(data.phone_number.clone()),
+        phone_number: Some(data.phone_number.to_string()),
         city: Some(data.city.clone()),
         referral_name: Some(data.referral_name.clone()),
         created_by: Some(data.created_by.clone()),
@@ -62,7 +71,31 @@ async fn create_recommendation(
     )
     .await
     {
-        Ok(_) => HttpResponse::Created().json("Ajánlás sikeresen létrehozva!"),
+        Ok(_) => HttpResponse::Created().json(messages::RECOMMENDATION_CREATED),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct CheckRecommendationJson {
+    full_name: String,
+    phone_number: Phone,
+}
+async fn check_recommendation(
+    web_data: web::Data<WebData>,
+    _: AuthenticationToken,
+    data: web::Json<CheckRecommendationJson>,
+) -> impl Responder {
+    let data = data.into_inner();
+    match CustomerRecommendation::check_existing(
+        &web_data.db,
+        &web_data.hmac_secret,
+        data.full_name.trim().to_string(),
+        data.phone_number.into(),
+    )
+    .await
+    {
+        Ok(result) => HttpResponse::Ok().json(result),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -70,7 +103,7 @@ async fn create_recommendation(
 #[derive(Deserialize, Clone)]
 struct ModifyRecommendationJson {
     full_name: Option<String>,
-    phone_number: Option<String>,
+    phone_number: Option<Phone>,
     city: Option<String>,
     referral_name: Option<String>,
     created_by: Option<String>,
@@ -83,7 +116,7 @@ async fn modify_recommendation(
 ) -> impl Responder {
     let rec = CustomerRecommendation {
         full_name: data.full_name.clone(),
-        phone_number: data.phone_number.clone(),
+        phone_number: data.phone_number.clone().map(String::from),
         city: data.city.clone(),
         referral_name: data.referral_name.clone(),
         created_by: data.created_by.clone(),
@@ -99,7 +132,7 @@ async fn modify_recommendation(
     )
     .await
     {
-        Ok(_) => HttpResponse::Created().json("Ajánlás sikeresen módosítva!"),
+        Ok(_) => HttpResponse::Ok().json(messages::RECOMMENDATION_MODIFIED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -116,6 +149,56 @@ async fn get_recommendations_by_user_uuid(
     }
 }
 
+#[derive(Deserialize)]
+struct ReferralQuery {
+    referral: String,
+}
+async fn get_recommendations_by_referral(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    query: web::Query<ReferralQuery>,
+) -> impl Responder {
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match CustomerRecommendation::get_by_referral(
+        &web_data.db,
+        &web_data.key,
+        user_uuid,
+        &query.referral,
+    )
+    .await
+    {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct LeaderboardQuery {
+    limit: Option<i64>,
+}
+async fn get_referrer_leaderboard(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    query: web::Query<LeaderboardQuery>,
+) -> impl Responder {
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    let limit = query.limit.unwrap_or(10).clamp(1, 100);
+    match CustomerRecommendation::get_referrer_leaderboard(&web_data.db, user_uuid, limit).await {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
 async fn get_recommendation_by_uuid(
     web_data: web::Data<WebData>,
     _: AuthenticationToken,
@@ -154,7 +237,7 @@ async fn change_recommendation_handler(
     )
     .await
     {
-        Ok(_) => HttpResponse::Created().json("Ajánlás(ok)ért felelős üzletkötő megváltoztatva!"),
+        Ok(_) => HttpResponse::Ok().json(messages::RECOMMENDATION_HANDLER_CHANGED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -169,7 +252,7 @@ async fn delete_recommendations(
     }
 
     match CustomerRecommendation::delete(&web_data.db, data.0).await {
-        Ok(_) => HttpResponse::Created().json("Ajánlás(ok) sikeresen törölve!"),
+        Ok(_) => HttpResponse::Ok().json(messages::RECOMMENDATIONS_DELETED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -6,7 +6,11 @@ use crate::{
     extractors::authentication_token::AuthenticationToken,
     models::recruitment::Recruitment,
     models::user::{User, UserRole},
-    utils::error::ApiError,
+    utils::{
+        contact::{Email, Phone},
+        error::ApiError,
+        messages,
+    },
     web_data::WebData,
 };
 
@@ -25,8 +29,8 @@ pub fn recruitment_scope() -> Scope {
 #[derive(Deserialize, Clone)]
 struct CreateRecruitmentJson {
     full_name: String,
-    email: String,
-    phone_number: String,
+    email: Email,
+    phone_number: Phone,
     description: String,
     created_by: String,
 }
@@ -36,15 +40,15 @@ async fn create_recruitment(
 ) -> impl Responder {
     let r = Recruitment {
         full_name: Some(data.full_name.clone()),
-        email: Some(data.email.clone()),
-        phone_number: Some(data.phone_number.clone()),
+        email: Some(data.email.to_string()),
+        phone_number: Some(data.phone_number.to_string()),
         description: Some(data.description.clone()),
         created_by: Some(data.created_by.clone()),
         ..Default::default()
     };
 
     match Recruitment::create(&web_data.db, &web_data.key, &web_data.hmac_secret, r).await {
-        Ok(_) => HttpResponse::Created().json("Jelentkező sikeresen létrehozva!"),
+        Ok(_) => HttpResponse::Created().json(messages::RECRUITMENT_CREATED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -53,8 +57,8 @@ async fn create_recruitment(
 struct ModifyRecruitmentJson {
     recruitment_uuid: Uuid,
     full_name: Option<String>,
-    email: Option<String>,
-    phone_number: Option<String>,
+    email: Option<Email>,
+    phone_number: Option<Phone>,
     description: Option<String>,
     created_by: Option<String>,
 }
@@ -65,8 +69,8 @@ async fn modify_recruitment(
 ) -> impl Responder {
     let r = Recruitment {
         full_name: data.full_name.clone(),
-        email: data.email.clone(),
-        phone_number: data.phone_number.clone(),
+        email: data.email.clone().map(String::from),
+        phone_number: data.phone_number.clone().map(String::from),
         description: data.description.clone(),
         created_by: data.created_by.clone(),
         ..Default::default()
@@ -81,7 +85,7 @@ async fn modify_recruitment(
     )
     .await
     {
-        Ok(_) => HttpResponse::Created().json("Jelentkező sikeresen módosítva!"),
+        Ok(_) => HttpResponse::Ok().json(messages::RECRUITMENT_MODIFIED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -115,7 +119,7 @@ async fn delete_recruitments(
     }
 
     match Recruitment::delete(&web_data.db, recruitment_uuid.into_inner()).await {
-        Ok(_) => HttpResponse::Created().json("Jelentkező sikeresen törölve!"),
+        Ok(_) => HttpResponse::Ok().json(messages::RECRUITMENTS_DELETED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
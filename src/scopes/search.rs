@@ -0,0 +1,46 @@
+use actix_web::{HttpResponse, Responder, ResponseError, Scope, web};
+use serde::Deserialize;
+
+use crate::{
+    extractors::authentication_token::AuthenticationToken, models::user::User,
+    utils::error::ApiError, web_data::WebData,
+};
+
+const MIN_QUERY_LEN: usize = 2;
+
+pub fn search_scope() -> Scope {
+    web::scope("/search").route("", web::get().to(global_search))
+}
+
+#[derive(Deserialize)]
+struct GlobalSearchQuery {
+    q: String,
+    limit: Option<i64>,
+}
+async fn global_search(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    query: web::Query<GlobalSearchQuery>,
+) -> impl Responder {
+    let q = query.q.trim();
+    if q.chars().count() < MIN_QUERY_LEN {
+        return ApiError::Validation(format!(
+            "A keresett kifejezés legalább {MIN_QUERY_LEN} karakter legyen!"
+        ))
+        .error_response();
+    }
+    let limit = query.limit.unwrap_or(5).clamp(1, 20);
+
+    match User::global_search(
+        &web_data.db,
+        &web_data.hmac_secret,
+        auth_token.id as i32,
+        q,
+        limit,
+    )
+    .await
+    {
+        Ok(results) => HttpResponse::Ok().json(results),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
@@ -0,0 +1,76 @@
+use actix_web::{HttpResponse, Responder, ResponseError, Scope, web};
+use serde::Deserialize;
+
+use crate::{
+    extractors::authentication_token::AuthenticationToken,
+    models::{
+        settings::Setting,
+        user::{User, UserRole},
+    },
+    utils::{error::ApiError, messages},
+    web_data::WebData,
+};
+
+pub fn settings_scope() -> Scope {
+    web::scope("/settings")
+        .route("", web::get().to(get_settings))
+        .route("/refresh", web::post().to(refresh_settings))
+        .route("/{key}", web::put().to(modify_setting))
+}
+
+async fn get_settings(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Leader, auth_token.id as i32).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match Setting::get_all(&web_data.db).await {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+// Picks up rows changed directly in the database, e.g. by another instance
+async fn refresh_settings(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Leader, auth_token.id as i32).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match Setting::refresh(&web_data.db).await {
+        Ok(_) => HttpResponse::Ok().json(messages::SETTINGS_REFRESHED),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct SettingJson {
+    // `null` removes the override
+    value: Option<String>,
+}
+async fn modify_setting(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    key: web::Path<String>,
+    data: web::Json<SettingJson>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Leader, auth_token.id as i32).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match Setting::save(
+        &web_data.db,
+        auth_token.real_id as i32,
+        &key,
+        data.into_inner().value,
+    )
+    .await
+    {
+        Ok(_) => HttpResponse::Ok().json(messages::SETTING_MODIFIED),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
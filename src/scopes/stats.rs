@@ -0,0 +1,45 @@
+use actix_web::{HttpResponse, Responder, ResponseError, Scope, web};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    extractors::authentication_token::AuthenticationToken,
+    models::user::User,
+    utils::{error::ApiError, validation::validate_chart_span},
+    web_data::WebData,
+};
+
+pub fn stats_scope() -> Scope {
+    web::scope("/stats").route(
+        "/activity/{user_uuid}",
+        web::post().to(get_activity_timeline),
+    )
+}
+
+#[derive(Deserialize)]
+struct ActivityJson {
+    start_date: NaiveDateTime,
+    end_date: NaiveDateTime,
+}
+async fn get_activity_timeline(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    data: web::Json<ActivityJson>,
+) -> impl Responder {
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
+    match User::get_activity_timeline(&web_data.db, user_uuid, data.start_date, data.end_date).await
+    {
+        Ok(chart) => HttpResponse::Ok().json(chart),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
@@ -1,14 +1,16 @@
-use actix_web::{HttpResponse, Responder, ResponseError, Scope, web};
+use actix_web::{HttpRequest, HttpResponse, Responder, ResponseError, Scope, http::header, web};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
     extractors::authentication_token::AuthenticationToken,
     models::{
+        notification_prefs::{NotificationChannel, NotificationPrefs},
         user::{User, UserRole},
         user_info::UserInfo,
     },
-    utils::error::ApiError,
+    utils::{error::ApiError, messages},
     web_data::WebData,
 };
 
@@ -16,16 +18,45 @@ pub fn user_scope() -> Scope {
     web::scope("/user")
         .route("/register", web::post().to(create_user))
         .route("/login/username", web::post().to(sign_in_via_username))
+        .route("/login/verify-otp", web::post().to(verify_otp))
+        .route("/refresh", web::post().to(refresh_session))
+        .route("/two-factor", web::put().to(set_two_factor))
         .route("/role", web::get().to(get_user_role))
         .route("/get-all", web::get().to(get_users))
+        .route("/export.csv", web::get().to(export_users))
         .route("/get/{user_uuid}", web::get().to(get_users_by_uuid))
         .route("/sub-users/{min_role}", web::get().to(get_user_sub_users))
+        .route("/assignable", web::get().to(get_assignable_users))
         .route("/managers", web::post().to(get_managers))
         .route("/manager", web::put().to(modify_user_manager))
         .route("/info", web::get().to(get_user_informations_by_id))
         .route("/info", web::put().to(modify_user_info))
+        .route("/{user_uuid}/info", web::get().to(get_user_info_by_uuid))
         .route("/{user_uuid}/info", web::put().to(modify_user_info_by_uuid))
+        .route("/password", web::put().to(change_password))
+        .route("/notifications", web::get().to(get_notification_prefs))
+        .route("/notifications", web::put().to(modify_notification_prefs))
+        .route(
+            "/{user_uuid}/password/reset",
+            web::put().to(reset_user_password),
+        )
+        .route(
+            "/{user_uuid}/password/temporary",
+            web::post().to(generate_temp_password),
+        )
         .route("/delete/{user_uuid}", web::delete().to(delete_user))
+        .route("/created-by/backfill", web::post().to(backfill_created_by))
+        .route("/token-info", web::get().to(get_token_info))
+        .route(
+            "/impersonate/{target_uuid}",
+            web::post().to(impersonate_user),
+        )
+        .route("/sessions", web::get().to(get_sessions))
+        .route(
+            "/sessions/revoke-all",
+            web::post().to(revoke_other_sessions),
+        )
+        .route("/logout", web::post().to(logout))
         .route("/protected", web::get().to(protected_route))
 }
 
@@ -63,7 +94,7 @@ async fn create_user(
     };
 
     match User::create(&web_data.db, new_user).await {
-        Ok(_) => HttpResponse::Created().json("Sikeresen létrehoztad a felhasználót!"),
+        Ok(_) => HttpResponse::Created().json(messages::USER_CREATED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -73,7 +104,15 @@ struct SignInJson {
     username: String,
     password: String,
 }
+fn device_of(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
 async fn sign_in_via_username(
+    req: HttpRequest,
     web_data: web::Data<WebData>,
     data: web::Json<SignInJson>,
 ) -> impl Responder {
@@ -83,12 +122,53 @@ async fn sign_in_via_username(
         ..Default::default()
     };
 
-    match User::sign_in_with_username(&web_data.db, user).await {
+    match User::sign_in_with_username(&web_data.db, user, device_of(&req)).await {
+        Ok(result) => HttpResponse::Ok().json(result),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct VerifyOtpJson {
+    user_uuid: Uuid,
+    code: String,
+}
+async fn verify_otp(
+    req: HttpRequest,
+    web_data: web::Data<WebData>,
+    data: web::Json<VerifyOtpJson>,
+) -> impl Responder {
+    match User::verify_otp(&web_data.db, data.user_uuid, &data.code, device_of(&req)).await {
         Ok(result) => HttpResponse::Ok().json(result),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
 
+#[derive(Deserialize)]
+struct RefreshJson {
+    refresh_token: String,
+}
+async fn refresh_session(
+    web_data: web::Data<WebData>,
+    data: web::Json<RefreshJson>,
+) -> impl Responder {
+    match User::refresh_session(&web_data.db, &data.refresh_token).await {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn set_two_factor(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    data: web::Json<bool>,
+) -> impl Responder {
+    match User::set_two_factor(&web_data.db, auth_token.id as i32, data.0).await {
+        Ok(_) => HttpResponse::Ok().json(messages::USER_TWO_FACTOR_MODIFIED),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
 async fn get_users(
     web_data: web::Data<WebData>,
     auth_token: AuthenticationToken,
@@ -103,6 +183,26 @@ async fn get_users(
     }
 }
 
+async fn export_users(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Leader, auth_token.id as i32).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match User::export_csv(&web_data.db, auth_token.id as i32).await {
+        Ok(csv) => HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"felhasznalok.csv\"",
+            ))
+            .body(csv),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
 async fn get_users_by_uuid(
     web_data: web::Data<WebData>,
     auth_token: AuthenticationToken,
@@ -144,7 +244,7 @@ async fn modify_user_info(
     };
 
     match User::modify_info(&web_data.db, user_uuid, user).await {
-        Ok(_) => HttpResponse::Ok().json("Sikeresen megváltoztattad az adataidat!"),
+        Ok(_) => HttpResponse::Ok().json(messages::USER_SELF_INFO_MODIFIED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -167,7 +267,7 @@ async fn modify_user_info_by_uuid(
     };
 
     match User::modify_info(&web_data.db, user_uuid.into_inner(), user).await {
-        Ok(_) => HttpResponse::Ok().json("Sikeresen megváltoztattad a felhasználó adatait!"),
+        Ok(_) => HttpResponse::Ok().json(messages::USER_INFO_MODIFIED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -192,7 +292,90 @@ async fn modify_user_manager(
     };
 
     match User::modify_manager(&web_data.db, data.user_uuid, user).await {
-        Ok(_) => HttpResponse::Ok().json("Sikeresen megváltoztattad a felhasználó adatait!"),
+        Ok(_) => HttpResponse::Ok().json(messages::USER_MANAGER_MODIFIED),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ResetPasswordJson {
+    new_password: Option<String>,
+}
+#[derive(Serialize)]
+struct ResetPasswordResponse {
+    message: String,
+    temporary_password: Option<String>,
+}
+async fn reset_user_password(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    data: web::Json<ResetPasswordJson>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Leader, auth_token.id as i32).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match User::admin_reset_password(
+        &web_data.db,
+        auth_token.real_id as i32,
+        user_uuid.into_inner(),
+        data.new_password.clone(),
+    )
+    .await
+    {
+        Ok(temporary_password) => HttpResponse::Ok().json(ResetPasswordResponse {
+            message: messages::USER_PASSWORD_RESET.to_string(),
+            temporary_password,
+        }),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn generate_temp_password(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Leader, auth_token.id as i32).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match User::generate_temp_password(
+        &web_data.db,
+        auth_token.real_id as i32,
+        user_uuid.into_inner(),
+    )
+    .await
+    {
+        Ok(temporary_password) => HttpResponse::Ok().json(ResetPasswordResponse {
+            message: messages::USER_TEMPORARY_PASSWORD_GENERATED.to_string(),
+            temporary_password: Some(temporary_password),
+        }),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct ChangePasswordJson {
+    current_password: String,
+    new_password: String,
+}
+async fn change_password(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    data: web::Json<ChangePasswordJson>,
+) -> impl Responder {
+    match User::change_password(
+        &web_data.db,
+        auth_token.id as i32,
+        auth_token.sid.as_deref(),
+        &data.current_password,
+        &data.new_password,
+    )
+    .await
+    {
+        Ok(_) => HttpResponse::Ok().json(messages::USER_PASSWORD_CHANGED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -208,7 +391,21 @@ async fn delete_user(
     println!("Törlendő user UUID: {:?}", user_uuid);
 
     match User::delete(&web_data.db, user_uuid.into_inner()).await {
-        Ok(_) => HttpResponse::Ok().json("Sikeresen kitörölted a felhasználót!"),
+        Ok(_) => HttpResponse::Ok().json(messages::USER_DELETED),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn backfill_created_by(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Leader, auth_token.id as i32).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match User::backfill_created_by(&web_data.db).await {
+        Ok(updated) => HttpResponse::Ok().json(updated),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -223,6 +420,72 @@ async fn get_user_informations_by_id(
     }
 }
 
+async fn get_user_info_by_uuid(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match User::get_info_by_uuid(&web_data.db, user_uuid).await {
+        Ok(user) => HttpResponse::Ok().json(user),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn get_notification_prefs(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+) -> impl Responder {
+    match NotificationPrefs::get(&web_data.db, auth_token.id as i32).await {
+        Ok(prefs) => HttpResponse::Ok().json(prefs),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct NotificationPrefsJson {
+    digest_enabled: Option<bool>,
+    reminder_lead_minutes: Option<i32>,
+    channel: Option<NotificationChannel>,
+}
+async fn modify_notification_prefs(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    data: web::Json<NotificationPrefsJson>,
+) -> impl Responder {
+    let user_id = auth_token.id as i32;
+    let mut prefs = match NotificationPrefs::get(&web_data.db, user_id).await {
+        Ok(prefs) => prefs,
+        Err(e) => return ApiError::from(e).error_response(),
+    };
+
+    // Omitted fields keep their current value
+    let data = data.into_inner();
+    if let Some(digest_enabled) = data.digest_enabled {
+        prefs.digest_enabled = digest_enabled;
+    }
+    if let Some(reminder_lead_minutes) = data.reminder_lead_minutes {
+        prefs.reminder_lead_minutes = reminder_lead_minutes;
+    }
+    if let Some(channel) = data.channel {
+        prefs.channel = channel;
+    }
+
+    match NotificationPrefs::save(&web_data.db, user_id, &prefs).await {
+        Ok(_) => HttpResponse::Ok().json(messages::USER_NOTIFICATIONS_MODIFIED),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
 async fn get_user_role(
     web_data: web::Data<WebData>,
     auth_token: AuthenticationToken,
@@ -233,20 +496,34 @@ async fn get_user_role(
     }
 }
 
+#[derive(Deserialize)]
+struct ManagersQuery {
+    role: Option<UserRole>,
+    #[serde(default)]
+    prefix: String,
+    limit: Option<i64>,
+}
 async fn get_managers(
     web_data: web::Data<WebData>,
     _: AuthenticationToken,
     data: web::Json<Option<Uuid>>,
+    query: web::Query<ManagersQuery>,
 ) -> impl Responder {
     let user_id = match data.0 {
-        Some(user_uuid) => User::get_id_by_uuid(&web_data.db, Some(user_uuid))
-            .await
-            .unwrap()
-            .unwrap(),
+        Some(user_uuid) => match User::get_id_by_uuid(&web_data.db, Some(user_uuid)).await {
+            Ok(Some(user_id)) => user_id,
+            Ok(None) => {
+                return ApiError::NotFound("Felhasználó nem található!".to_string())
+                    .error_response();
+            }
+            Err(e) => return ApiError::from(e).error_response(),
+        },
         None => 0,
     };
 
-    match UserRole::get_managers(&web_data.db, user_id).await {
+    let query = query.into_inner();
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+    match UserRole::get_managers(&web_data.db, user_id, query.role, &query.prefix, limit).await {
         Ok(list) => HttpResponse::Ok().json(list),
         Err(e) => ApiError::from(e).error_response(),
     }
@@ -263,6 +540,146 @@ async fn get_user_sub_users(
     }
 }
 
+#[derive(Deserialize)]
+struct AssignableQuery {
+    min_role: Option<UserRole>,
+}
+async fn get_assignable_users(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    query: web::Query<AssignableQuery>,
+) -> impl Responder {
+    match User::get_assignable(
+        &web_data.db,
+        auth_token.id as i32,
+        query.into_inner().min_role,
+    )
+    .await
+    {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Serialize)]
+struct TokenInfoResponse {
+    user_uuid: Uuid,
+    issued_at: Option<DateTime<Utc>>,
+    expires_at: Option<DateTime<Utc>>,
+    seconds_remaining: i64,
+    impersonated_by: Option<Uuid>,
+}
+async fn get_token_info(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+) -> impl Responder {
+    // The extractor tolerates a short leeway after `exp`, this endpoint doesn't
+    let seconds_remaining = auth_token.exp as i64 - Utc::now().timestamp();
+    if seconds_remaining <= 0 {
+        return ApiError::Unauthorized("A munkamenet lejárt!".to_string()).error_response();
+    }
+
+    let impersonated_by = if auth_token.is_impersonating() {
+        match User::get_uuid_by_id(&web_data.db, auth_token.real_id as i32).await {
+            Ok(actor_uuid) => actor_uuid,
+            Err(e) => return ApiError::from(e).error_response(),
+        }
+    } else {
+        None
+    };
+
+    match User::get_uuid_by_id(&web_data.db, auth_token.id as i32).await {
+        Ok(Some(user_uuid)) => HttpResponse::Ok().json(TokenInfoResponse {
+            user_uuid,
+            issued_at: auth_token
+                .iat
+                .and_then(|iat| DateTime::from_timestamp(iat as i64, 0)),
+            expires_at: DateTime::from_timestamp(auth_token.exp as i64, 0),
+            seconds_remaining,
+            impersonated_by,
+        }),
+        Ok(None) => ApiError::NotFound("Felhasználó nem található!".to_string()).error_response(),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Serialize)]
+struct ImpersonationResponse {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+async fn impersonate_user(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    target_uuid: web::Path<Uuid>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Leader, auth_token.id as i32).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match User::impersonate(&web_data.db, auth_token.id as i32, target_uuid.into_inner()).await {
+        Ok((token, expires_at)) => {
+            HttpResponse::Ok().json(ImpersonationResponse { token, expires_at })
+        }
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+// Sessions belong to the real holder, an impersonated user's devices stay out of reach
+async fn get_sessions(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+) -> impl Responder {
+    match User::get_sessions(
+        &web_data.db,
+        auth_token.real_id as i32,
+        auth_token.sid.as_deref(),
+    )
+    .await
+    {
+        Ok(sessions) => HttpResponse::Ok().json(sessions),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Serialize)]
+struct RevokeSessionsResponse {
+    message: String,
+    revoked: usize,
+}
+async fn revoke_other_sessions(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+) -> impl Responder {
+    match User::revoke_other_sessions(
+        &web_data.db,
+        auth_token.real_id as i32,
+        auth_token.sid.as_deref(),
+    )
+    .await
+    {
+        Ok(revoked) => HttpResponse::Ok().json(RevokeSessionsResponse {
+            message: messages::USER_SESSIONS_REVOKED.to_string(),
+            revoked,
+        }),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+// Impersonation sessions are kept under the real actor
+async fn logout(web_data: web::Data<WebData>, auth_token: AuthenticationToken) -> impl Responder {
+    match User::logout(
+        &web_data.db,
+        auth_token.real_id as i32,
+        auth_token.sid.as_deref(),
+    )
+    .await
+    {
+        Ok(_) => HttpResponse::Ok().json(messages::USER_LOGGED_OUT),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
 #[derive(Serialize)]
 struct ProtectedResponse {
     message: String,
@@ -1,7 +1,7 @@
 use actix_web::{HttpResponse, Responder, ResponseError, Scope, web};
 use anyhow::anyhow;
 use chrono::NaiveDateTime;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
@@ -10,18 +10,22 @@ use crate::{
         user::{User, UserRole},
         user_date::{MeetType, UserMeetDate},
     },
-    utils::error::ApiError,
+    utils::{error::ApiError, messages, validation::validate_chart_span},
     web_data::WebData,
 };
 
 pub fn dates_scope() -> Scope {
     web::scope("/dates")
         .route("/create", web::post().to(create_date))
+        .route("/create-bulk", web::post().to(create_dates_bulk))
         .route("/modify", web::put().to(modify_date))
         .route(
             "/{user_uuid}/{selected_month}",
             web::get().to(get_all_by_dates),
         )
+        .route("/team/range", web::post().to(get_team_range_by_dates))
+        .route("/state/bulk", web::put().to(change_dates_state_bulk))
+        .route("/{user_uuid}/range", web::post().to(get_range_by_dates))
         .route("/{date_uuid}", web::get().to(get_date_by_uuid))
         .route("/{date_uuid}/state", web::put().to(change_date_state))
         .route("/change/user", web::put().to(change_dates_handler))
@@ -57,6 +61,14 @@ pub fn dates_scope() -> Scope {
             "/chart/weekly/{user_uuid}",
             web::post().to(get_dates_weekly_chart_by_user_uuid),
         )
+        .route(
+            "/chart/daily/{user_uuid}",
+            web::post().to(get_daily_counts_chart),
+        )
+        .route(
+            "/chart/location/{user_uuid}",
+            web::get().to(get_location_breakdown_chart),
+        )
         .route(
             "/chart/monthly/get-all",
             web::post().to(get_dates_monthly_chart),
@@ -71,6 +83,11 @@ pub fn dates_scope() -> Scope {
         )
 }
 
+fn parse_meet_date(value: &str) -> Result<NaiveDateTime, chrono::ParseError> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+        .or_else(|_| chrono::DateTime::parse_from_rfc3339(value).map(|dt| dt.naive_utc()))
+}
+
 #[derive(Deserialize, Clone)]
 struct CreateDateJson {
     meet_date: String,
@@ -83,15 +100,10 @@ struct CreateDateJson {
 }
 async fn create_date(
     web_data: web::Data<WebData>,
-    _: AuthenticationToken,
+    auth_token: AuthenticationToken,
     data: web::Json<CreateDateJson>,
 ) -> impl Responder {
-    let parsed_date = chrono::NaiveDateTime::parse_from_str(&data.meet_date, "%Y-%m-%dT%H:%M")
-        .or_else(|_| {
-            chrono::DateTime::parse_from_rfc3339(&data.meet_date).map(|dt| dt.naive_utc())
-        });
-
-    let meet_date = match parsed_date {
+    let meet_date = match parse_meet_date(&data.meet_date) {
         Ok(d) => d,
         Err(e) => return ApiError::from(anyhow!(e)).error_response(),
     };
@@ -111,12 +123,73 @@ async fn create_date(
         &web_data.db,
         &web_data.key,
         &web_data.hmac_secret,
+        auth_token.id as i32,
         data.user_uuid,
         user_date,
     )
     .await
     {
-        Ok(_) => HttpResponse::Created().json("Időpont sikeresen létrehozva!"),
+        Ok(_) => HttpResponse::Created().json(messages::USER_DATE_CREATED),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize, Clone)]
+struct BulkDateItemJson {
+    meet_date: String,
+    full_name: String,
+    phone_number: String,
+    meet_location: String,
+    meet_type: MeetType,
+    created_by: String,
+}
+#[derive(Deserialize, Clone)]
+struct CreateDatesBulkJson {
+    user_uuid: Uuid,
+    meetings: Vec<BulkDateItemJson>,
+}
+async fn create_dates_bulk(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    data: web::Json<CreateDatesBulkJson>,
+) -> impl Responder {
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, data.user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    let mut meetings = Vec::with_capacity(data.meetings.len());
+    for (index, item) in data.meetings.iter().enumerate() {
+        let meet_date = match parse_meet_date(&item.meet_date) {
+            Ok(d) => d,
+            Err(e) => {
+                return ApiError::Validation(format!("Érvénytelen időpont ({index}. sor): {e}"))
+                    .error_response();
+            }
+        };
+
+        meetings.push(UserMeetDate {
+            meet_date: Some(meet_date),
+            full_name: Some(item.full_name.clone()),
+            phone_number: Some(item.phone_number.clone()),
+            meet_location: Some(item.meet_location.clone()),
+            meet_type: Some(item.meet_type.clone()),
+            is_completed: Some(false),
+            created_by: Some(item.created_by.clone()),
+            ..Default::default()
+        });
+    }
+
+    match UserMeetDate::create_many(
+        &web_data.db,
+        &web_data.key,
+        &web_data.hmac_secret,
+        auth_token.id as i32,
+        data.user_uuid,
+        meetings,
+    )
+    .await
+    {
+        Ok(summary) => HttpResponse::Created().json(summary),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -136,12 +209,7 @@ async fn modify_date(
     _: AuthenticationToken,
     data: web::Json<ModifyDateJson>,
 ) -> impl Responder {
-    let parsed_date = chrono::NaiveDateTime::parse_from_str(&data.meet_date, "%Y-%m-%dT%H:%M")
-        .or_else(|_| {
-            chrono::DateTime::parse_from_rfc3339(&data.meet_date).map(|dt| dt.naive_utc())
-        });
-
-    let meet_date = match parsed_date {
+    let meet_date = match parse_meet_date(&data.meet_date) {
         Ok(d) => d,
         Err(e) => return ApiError::from(anyhow!(e)).error_response(),
     };
@@ -164,7 +232,7 @@ async fn modify_date(
     )
     .await
     {
-        Ok(_) => HttpResponse::Created().json("Időpont sikeresen módosítva!"),
+        Ok(_) => HttpResponse::Ok().json(messages::USER_DATE_MODIFIED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -180,6 +248,56 @@ async fn get_all_by_dates(
     }
 }
 
+#[derive(Deserialize)]
+struct DateRangeJson {
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+}
+async fn get_range_by_dates(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    data: web::Json<DateRangeJson>,
+) -> impl Responder {
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match UserMeetDate::get_range(&web_data.db, &web_data.key, user_uuid, data.from, data.to).await
+    {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn get_team_range_by_dates(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    data: web::Json<DateRangeJson>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+    if let Err(e) = validate_chart_span(data.from, data.to) {
+        return e.error_response();
+    }
+
+    match UserMeetDate::get_team_range(
+        &web_data.db,
+        &web_data.key,
+        auth_token.id as i32,
+        data.from,
+        data.to,
+    )
+    .await
+    {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
 async fn get_date_by_uuid(
     web_data: web::Data<WebData>,
     _: AuthenticationToken,
@@ -198,7 +316,33 @@ async fn change_date_state(
     data: web::Json<bool>,
 ) -> impl Responder {
     match UserMeetDate::change_date_state(&web_data.db, date_uuid.into_inner(), data.0).await {
-        Ok(_) => HttpResponse::Ok().json("Időpont státusza megváltoztatva!"),
+        Ok(_) => HttpResponse::Ok().json(messages::USER_DATE_STATE_CHANGED),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct ChangeDatesStateBulkJson {
+    date_uuids: Vec<Uuid>,
+    is_completed: bool,
+}
+#[derive(Serialize)]
+struct ChangeDatesStateBulkResponse {
+    message: String,
+    affected: u64,
+}
+async fn change_dates_state_bulk(
+    web_data: web::Data<WebData>,
+    _: AuthenticationToken,
+    data: web::Json<ChangeDatesStateBulkJson>,
+) -> impl Responder {
+    match UserMeetDate::change_state_bulk(&web_data.db, data.date_uuids.clone(), data.is_completed)
+        .await
+    {
+        Ok(affected) => HttpResponse::Ok().json(ChangeDatesStateBulkResponse {
+            message: messages::USER_DATES_STATE_CHANGED.to_string(),
+            affected,
+        }),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -224,7 +368,7 @@ async fn change_dates_handler(
     )
     .await
     {
-        Ok(_) => HttpResponse::Ok().json("Időpontért felelős üzletkötő megváltoztatva!"),
+        Ok(_) => HttpResponse::Ok().json(messages::USER_DATE_HANDLER_CHANGED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -239,7 +383,7 @@ async fn delete_dates(
     }
 
     match UserMeetDate::delete(&web_data.db, data.0).await {
-        Ok(_) => HttpResponse::Ok().json("Időpont(ok) sikeresen tölölve!"),
+        Ok(_) => HttpResponse::Ok().json(messages::USER_DATES_DELETED),
         Err(e) => ApiError::from(e).error_response(),
     }
 }
@@ -355,6 +499,10 @@ async fn get_dates_weekly_chart(
         return ApiError::from(e).error_response();
     }
 
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
     match UserMeetDate::get_dates_weekly_chart(
         &web_data.db,
         auth_token.id as i32,
@@ -373,6 +521,10 @@ async fn get_self_dates_weekly_chart(
     auth_token: AuthenticationToken,
     data: web::Json<DateChartJson>,
 ) -> impl Responder {
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
     let user_uuid = User::get_uuid_by_id(&web_data.db, auth_token.id as i32)
         .await
         .unwrap()
@@ -402,6 +554,10 @@ async fn get_dates_weekly_chart_by_user_uuid(
         return ApiError::from(e).error_response();
     }
 
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
     match UserMeetDate::get_dates_weekly_chart_by_user_uuid(
         &web_data.db,
         user_uuid.into_inner(),
@@ -415,6 +571,61 @@ async fn get_dates_weekly_chart_by_user_uuid(
     }
 }
 
+async fn get_daily_counts_chart(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    data: web::Json<DateChartJson>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
+    match UserMeetDate::get_daily_counts(
+        &web_data.db,
+        user_uuid.into_inner(),
+        data.start_date,
+        data.end_date,
+    )
+    .await
+    {
+        Ok(chart) => HttpResponse::Ok().json(chart),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct LocationChartQuery {
+    limit: Option<i64>,
+}
+async fn get_location_breakdown_chart(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    user_uuid: web::Path<Uuid>,
+    query: web::Query<LocationChartQuery>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Manager, auth_token.id as i32).await
+    {
+        return ApiError::from(e).error_response();
+    }
+
+    let user_uuid = user_uuid.into_inner();
+    if let Err(e) = User::require_access(&web_data.db, auth_token.id as i32, user_uuid).await {
+        return ApiError::from(e).error_response();
+    }
+
+    let limit = query.limit.unwrap_or(10).clamp(1, 100);
+    match UserMeetDate::get_location_breakdown(&web_data.db, user_uuid, limit).await {
+        Ok(chart) => HttpResponse::Ok().json(chart),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
 async fn get_dates_monthly_chart(
     web_data: web::Data<WebData>,
     auth_token: AuthenticationToken,
@@ -425,6 +636,10 @@ async fn get_dates_monthly_chart(
         return ApiError::from(e).error_response();
     }
 
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
     match UserMeetDate::get_dates_monthly_chart(
         &web_data.db,
         auth_token.id as i32,
@@ -443,6 +658,10 @@ async fn get_self_dates_monthly_chart(
     auth_token: AuthenticationToken,
     data: web::Json<DateChartJson>,
 ) -> impl Responder {
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
     let user_uuid = User::get_uuid_by_id(&web_data.db, auth_token.id as i32)
         .await
         .unwrap()
@@ -472,6 +691,10 @@ async fn get_dates_monthly_chart_by_user_uuid(
         return ApiError::from(e).error_response();
     }
 
+    if let Err(e) = validate_chart_span(data.start_date, data.end_date) {
+        return e.error_response();
+    }
+
     match UserMeetDate::get_dates_monthly_chart_by_user_uuid(
         &web_data.db,
         user_uuid.into_inner(),
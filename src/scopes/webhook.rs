@@ -0,0 +1,77 @@
+use actix_web::{HttpResponse, Responder, ResponseError, Scope, web};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    extractors::authentication_token::AuthenticationToken,
+    models::{
+        user::{User, UserRole},
+        webhook::{Webhook, WebhookEvent},
+    },
+    utils::{error::ApiError, messages},
+    web_data::WebData,
+};
+
+pub fn webhook_scope() -> Scope {
+    web::scope("/webhook")
+        .route("/create", web::post().to(create_webhook))
+        .route("/get-all", web::get().to(get_webhooks))
+        .route("/{webhook_uuid}", web::delete().to(delete_webhook))
+}
+
+#[derive(Deserialize)]
+struct CreateWebhookJson {
+    url: String,
+    event_types: Vec<WebhookEvent>,
+}
+async fn create_webhook(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    data: web::Json<CreateWebhookJson>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Leader, auth_token.id as i32).await {
+        return ApiError::from(e).error_response();
+    }
+
+    let data = data.into_inner();
+    match Webhook::create(
+        &web_data.db,
+        auth_token.id as i32,
+        data.url,
+        data.event_types,
+    )
+    .await
+    {
+        Ok(webhook) => HttpResponse::Created().json(webhook),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn get_webhooks(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Leader, auth_token.id as i32).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match Webhook::get_all(&web_data.db).await {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+async fn delete_webhook(
+    web_data: web::Data<WebData>,
+    auth_token: AuthenticationToken,
+    webhook_uuid: web::Path<Uuid>,
+) -> impl Responder {
+    if let Err(e) = User::require_role(&web_data.db, UserRole::Leader, auth_token.id as i32).await {
+        return ApiError::from(e).error_response();
+    }
+
+    match Webhook::delete(&web_data.db, webhook_uuid.into_inner()).await {
+        Ok(_) => HttpResponse::Ok().json(messages::WEBHOOK_DELETED),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
@@ -1,54 +1,75 @@
 use std::env;
 
-use actix_cors::Cors;
-use actix_web::{App, HttpServer, http, middleware::Logger, web};
-use base64::{Engine as _, engine::general_purpose};
+use actix_web::{App, HttpServer, middleware::Logger, rt, web};
 use chacha20poly1305::Key;
-use env_logger::Env;
 
-use crate::{database::Database, scopes, web_data::WebData};
+use crate::{
+    cli::Task,
+    database::Database,
+    models::{
+        contract::ContractType, hash_rotation, intervention_task::InterventionTask,
+        settings::Setting, user::User,
+    },
+    scopes,
+    utils::{
+        cors::CorsConfig,
+        encrypt::{self, HmacSecret},
+        error::ApiError,
+        logging,
+    },
+    web_data::WebData,
+};
+
+fn encryption_key() -> Key {
+    let key_b64 = env::var("ENCRYPTION_KEY").expect("ENCRYPTION_KEY must be set!");
+    encrypt::decode_key(&key_b64).unwrap_or_else(|e| panic!("{e}"))
+}
+
+fn hmac_secret(name: &str) -> HmacSecret {
+    let secret = env::var(name).unwrap_or_else(|_| panic!("{name} must be set!"));
+    encrypt::decode_hmac_secret(&secret).unwrap_or_else(|e| panic!("{e}"))
+}
 
 pub struct Server;
 impl Server {
     pub async fn run(port: u16) -> std::io::Result<()> {
-        // Initialize logger if -log flag is passed
-        if env::args().any(|arg| arg == "-log") {
-            env_logger::init_from_env(Env::default().default_filter_or("info"));
-        }
+        // Initialize logger from -log / RUST_LOG / LOG_LEVEL and LOG_FORMAT
+        logging::init();
 
-        let key_b64 = env::var("ENCRYPTION_KEY").expect("ENCRYPTION_KEY must be set!");
-        let key_bytes = general_purpose::STANDARD.decode(key_b64).unwrap();
+        let key = encryption_key();
+        let hmac_secret = hmac_secret("HMAC_SECRET");
+        let cors_config = CorsConfig::from_env().unwrap_or_else(|e| panic!("{e}"));
 
         // Initialize shared DB state once at startup
         let db = Database::create_connection()
             .await
             .expect("Failed to initialize database");
-        let key = Key::from_slice(&key_bytes);
-        let hmac_secret = env::var("HMAC_SECRET")
-            .expect("HMAC_SECRET must be set!")
-            .into_bytes();
+        Setting::refresh(&db)
+            .await
+            .expect("Failed to load settings");
+        let contract_types = ContractType::get_allowed(&db)
+            .await
+            .expect("Failed to load contract types");
+
+        let worker_db = db.clone();
+        rt::spawn(async move { worker_db.webhooks.run_worker(worker_db.clone()).await });
         let db_data = web::Data::new(WebData {
             db,
-            key: *key,
+            key,
             hmac_secret,
+            contract_types,
         });
 
         HttpServer::new(move || {
-            let cors = Cors::default()
-                // .allowed_origin("http://localhost")
-                .allow_any_origin()
-                .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
-                .allowed_headers(vec![
-                    http::header::AUTHORIZATION,
-                    http::header::ACCEPT,
-                    http::header::CONTENT_TYPE,
-                ])
-                .max_age(3600);
-
             App::new()
-                .wrap(cors)
+                .wrap(cors_config.build())
                 .wrap(Logger::default())
                 .app_data(db_data.clone())
+                // Malformed bodies (e.g. an invalid phone or email) get the usual error body
+                .app_data(
+                    web::JsonConfig::default()
+                        .error_handler(|err, _| ApiError::Validation(err.to_string()).into()),
+                )
                 .service(scopes::user::user_scope())
                 .service(scopes::customer::customer_scope())
                 .service(scopes::lead::lead_scope())
@@ -57,9 +78,72 @@ impl Server {
                 .service(scopes::intervention_task::intervention_task_scope())
                 .service(scopes::recommendation::recommendation_scope())
                 .service(scopes::recruitment::recruitment_scope())
+                .service(scopes::dashboard::dashboard_scope())
+                .service(scopes::webhook::webhook_scope())
+                .service(scopes::audit::audit_scope())
+                .service(scopes::search::search_scope())
+                .service(scopes::settings::settings_scope())
+                .service(scopes::import::import_scope())
+                .service(scopes::stats::stats_scope())
+                .service(scopes::lookup::lookup_scope())
+                .service(scopes::health::health_scope())
         })
         .bind(("0.0.0.0", port))?
         .run()
         .await
     }
+
+    /// Runs a single maintenance task against the database and exits.
+    pub async fn run_maintenance(task: Task) -> std::io::Result<()> {
+        logging::init();
+
+        let db = Database::create_connection()
+            .await
+            .expect("Failed to initialize database");
+
+        // The settings table comes from a migration, a fresh database doesn't have it yet
+        if task == Task::Migrate {
+            sqlx::migrate!("./migrations")
+                .run(&db.pool)
+                .await
+                .map_err(std::io::Error::other)?;
+            println!("Migrations applied");
+            return Ok(());
+        }
+        Setting::refresh(&db)
+            .await
+            .expect("Failed to load settings");
+
+        match task {
+            Task::Migrate => unreachable!("handled before loading settings"),
+            Task::Rehash => {
+                let key = encryption_key();
+                let old_secret = hmac_secret("HMAC_SECRET_OLD");
+                let new_secret = hmac_secret("HMAC_SECRET");
+
+                let counts = hash_rotation::rehash_all(&db, &key, &old_secret, &new_secret)
+                    .await
+                    .map_err(std::io::Error::other)?;
+                for (table, count) in counts {
+                    println!(
+                        "{table}: {} updated, {} skipped",
+                        count.updated, count.skipped
+                    );
+                }
+            }
+            Task::BackfillCreatedBy => {
+                let updated = User::backfill_created_by(&db)
+                    .await
+                    .map_err(std::io::Error::other)?;
+                println!("{updated} created_by values backfilled");
+            }
+            Task::BackfillContractLinks => {
+                let updated = InterventionTask::backfill_contract_links(&db)
+                    .await
+                    .map_err(std::io::Error::other)?;
+                println!("{updated} intervention tasks linked to their contract");
+            }
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,76 @@
+use std::{
+    collections::HashMap,
+    env,
+    hash::Hash,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+const DEFAULT_ROLE_CACHE_TTL_SECONDS: u64 = 60;
+
+pub fn role_cache_ttl() -> Duration {
+    let seconds = env::var("ROLE_CACHE_TTL_SECONDS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_ROLE_CACHE_TTL_SECONDS);
+
+    Duration::from_secs(seconds)
+}
+
+#[derive(Debug, Clone)]
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    entries: Arc<RwLock<HashMap<K, (V, Instant)>>>,
+}
+
+impl<K: Eq + Hash, V: Clone> TtlCache<K, V> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let entries = self.entries.read().unwrap();
+        entries
+            .get(key)
+            .filter(|(_, inserted_at)| inserted_at.elapsed() < self.ttl)
+            .map(|(value, _)| value.clone())
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        let mut entries = self.entries.write().unwrap();
+        entries.retain(|_, (_, inserted_at)| inserted_at.elapsed() < self.ttl);
+        entries.insert(key, (value, Instant::now()));
+    }
+
+    pub fn invalidate(&self, key: &K) {
+        self.entries.write().unwrap().remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn values_expire_after_their_ttl() {
+        let cache = TtlCache::new(Duration::from_millis(50));
+        cache.insert(1, "Manager");
+        assert_eq!(cache.get(&1), Some("Manager"));
+
+        thread::sleep(Duration::from_millis(80));
+        assert_eq!(cache.get(&1), None);
+
+        let disabled = TtlCache::new(Duration::ZERO);
+        disabled.insert(1, "Manager");
+        assert_eq!(disabled.get(&1), None);
+    }
+}
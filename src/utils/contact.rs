@@ -0,0 +1,159 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use serde::{Deserialize, Serialize};
+
+const MIN_PHONE_DIGITS: usize = 7;
+const MAX_PHONE_DIGITS: usize = 15;
+const MAX_EMAIL_LEN: usize = 254;
+
+/// The form contact values are hashed in, so a phone number or email matches however it
+/// was typed. Anything that is neither is returned as is.
+pub fn canonical(value: &str) -> String {
+    Phone::try_from(value.to_string())
+        .map(String::from)
+        .or_else(|_| Email::try_from(value.to_string()).map(String::from))
+        .unwrap_or_else(|_| value.to_string())
+}
+
+// Separators are dropped and only a leading `+` is kept, hashes made before this need
+// a `rehash` run (see `hash_rotation`) to match again
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Phone(String);
+
+impl Phone {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for Phone {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let value = value.trim();
+        let number = value.strip_prefix('+').unwrap_or(value);
+        let allowed = number
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, ' ' | '-' | '/' | '(' | ')' | '.'));
+        let digits = number.chars().filter(char::is_ascii_digit).count();
+
+        if !allowed || !(MIN_PHONE_DIGITS..=MAX_PHONE_DIGITS).contains(&digits) {
+            return Err(format!("Érvénytelen telefonszám: {value}"));
+        }
+
+        let prefix = if value.starts_with('+') { "+" } else { "" };
+        Ok(Self(
+            prefix
+                .chars()
+                .chain(number.chars().filter(char::is_ascii_digit))
+                .collect(),
+        ))
+    }
+}
+
+impl From<Phone> for String {
+    fn from(phone: Phone) -> Self {
+        phone.0
+    }
+}
+
+impl Display for Phone {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Email(String);
+
+impl Email {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for Email {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let value = value.trim();
+        let valid = value.len() <= MAX_EMAIL_LEN
+            && !value.chars().any(char::is_whitespace)
+            && value.split_once('@').is_some_and(|(local, domain)| {
+                !local.is_empty()
+                    && !domain.contains('@')
+                    && domain
+                        .split_once('.')
+                        .is_some_and(|(name, tld)| !name.is_empty() && !tld.is_empty())
+                    && !domain.ends_with('.')
+            });
+
+        if !valid {
+            return Err(format!("Érvénytelen email cím: {value}"));
+        }
+
+        // Only the domain is case-insensitive, the local part is kept as entered
+        let (local, domain) = value.split_once('@').unwrap_or_default();
+        Ok(Self(format!("{local}@{}", domain.to_lowercase())))
+    }
+}
+
+impl From<Email> for String {
+    fn from(email: Email) -> Self {
+        email.0
+    }
+}
+
+impl Display for Email {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phone(value: &str) -> Result<String, String> {
+        Phone::try_from(value.to_string()).map(String::from)
+    }
+
+    fn email(value: &str) -> Result<String, String> {
+        Email::try_from(value.to_string()).map(String::from)
+    }
+
+    #[test]
+    fn phones_are_reduced_to_their_digits() {
+        assert_eq!(phone(" +36 (30) 123-4567 ").unwrap(), "+36301234567");
+        assert_eq!(phone("06/30.123.4567").unwrap(), "06301234567");
+        assert_eq!(phone("+36301234567"), phone("+36 30 123 4567"));
+        assert_ne!(phone("+36301234567"), phone("36301234567"));
+
+        assert!(phone("123456").is_err());
+        assert!(phone("+1234567890123456").is_err());
+        assert!(phone("+36 30 123 456a").is_err());
+        assert!(phone("36+301234567").is_err());
+    }
+
+    #[test]
+    fn emails_have_their_domain_lowercased() {
+        assert_eq!(
+            email(" Teszt.Elek@Example.COM ").unwrap(),
+            "Teszt.Elek@example.com"
+        );
+
+        assert!(email("teszt@example").is_err());
+        assert!(email("@example.com").is_err());
+        assert!(email("teszt@@example.com").is_err());
+        assert!(email("teszt elek@example.com").is_err());
+    }
+
+    #[test]
+    fn only_contacts_are_canonicalised() {
+        assert_eq!(canonical("+36 30 123 4567"), "+36301234567");
+        assert_eq!(canonical("Teszt@Example.com"), "Teszt@example.com");
+        assert_eq!(canonical("Teszt Elek"), "Teszt Elek");
+    }
+}
@@ -0,0 +1,130 @@
+use std::env;
+
+use actix_cors::Cors;
+use actix_web::http::header::{self, HeaderName};
+use anyhow::{Result, anyhow};
+
+const ALLOWED_METHODS: [&str; 4] = ["GET", "POST", "PUT", "DELETE"];
+const MAX_AGE_SECS: usize = 3600;
+
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    // Empty means any origin
+    pub allowed_origins: Vec<String>,
+    pub allowed_headers: Vec<HeaderName>,
+    pub exposed_headers: Vec<HeaderName>,
+    pub supports_credentials: bool,
+}
+
+/// Comma separated env list, blank entries are skipped.
+fn env_list(name: &str) -> Option<Vec<String>> {
+    env::var(name).ok().map(|value| {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    })
+}
+
+fn parse_headers(name: &str, values: Vec<String>) -> Result<Vec<HeaderName>> {
+    values
+        .into_iter()
+        .map(|value| {
+            HeaderName::try_from(value.as_str())
+                .map_err(|e| anyhow!("Invalid header '{value}' in {name}: {e}"))
+        })
+        .collect()
+}
+
+impl CorsConfig {
+    /// Reads CORS_ALLOWED_ORIGINS, CORS_ALLOWED_HEADERS, CORS_EXPOSED_HEADERS and
+    /// CORS_SUPPORTS_CREDENTIALS, keeping the previous behaviour when none are set.
+    pub fn from_env() -> Result<Self> {
+        let allowed_origins = env_list("CORS_ALLOWED_ORIGINS")
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|origin| origin != "*")
+            .collect();
+        let allowed_headers = match env_list("CORS_ALLOWED_HEADERS") {
+            Some(values) => parse_headers("CORS_ALLOWED_HEADERS", values)?,
+            None => vec![header::AUTHORIZATION, header::ACCEPT, header::CONTENT_TYPE],
+        };
+        let exposed_headers = parse_headers(
+            "CORS_EXPOSED_HEADERS",
+            env_list("CORS_EXPOSED_HEADERS").unwrap_or_default(),
+        )?;
+        let supports_credentials = env::var("CORS_SUPPORTS_CREDENTIALS")
+            .is_ok_and(|value| value.eq_ignore_ascii_case("true") || value == "1");
+
+        let config = Self {
+            allowed_origins,
+            allowed_headers,
+            exposed_headers,
+            supports_credentials,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Browsers refuse credentialed responses for a wildcard origin, so this is a config error
+    pub fn validate(&self) -> Result<()> {
+        if self.supports_credentials && self.allowed_origins.is_empty() {
+            return Err(anyhow!(
+                "CORS_SUPPORTS_CREDENTIALS requires concrete CORS_ALLOWED_ORIGINS, not a wildcard"
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn build(&self) -> Cors {
+        let mut cors = if self.allowed_origins.is_empty() {
+            Cors::default().allow_any_origin()
+        } else {
+            self.allowed_origins
+                .iter()
+                .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+        };
+
+        cors = cors
+            .allowed_methods(ALLOWED_METHODS)
+            .allowed_headers(self.allowed_headers.clone())
+            .max_age(MAX_AGE_SECS);
+        if !self.exposed_headers.is_empty() {
+            cors = cors.expose_headers(self.exposed_headers.clone());
+        }
+        if self.supports_credentials {
+            cors = cors.supports_credentials();
+        }
+        cors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(allowed_origins: &[&str], supports_credentials: bool) -> CorsConfig {
+        CorsConfig {
+            allowed_origins: allowed_origins
+                .iter()
+                .map(|origin| origin.to_string())
+                .collect(),
+            allowed_headers: vec![header::AUTHORIZATION],
+            exposed_headers: Vec::new(),
+            supports_credentials,
+        }
+    }
+
+    #[test]
+    fn credentials_need_a_concrete_origin() {
+        assert!(config(&[], true).validate().is_err());
+        assert!(
+            config(&["https://app.example.com"], true)
+                .validate()
+                .is_ok()
+        );
+        assert!(config(&[], false).validate().is_ok());
+    }
+}
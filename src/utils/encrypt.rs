@@ -1,19 +1,69 @@
+use std::env;
+
+use anyhow::anyhow;
+use base64::{Engine as _, engine::general_purpose};
 use chacha20poly1305::{
     AeadCore, ChaCha20Poly1305, Key, Nonce,
     aead::{Aead, KeyInit, OsRng},
 };
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
+use strum::EnumString;
+
+use crate::utils::{error::ApiError, settings};
 
 type HmacSha256 = Hmac<Sha256>;
 pub type HmacSecret = Vec<u8>;
 
+const DEFAULT_HMAC_SECRET_MIN_LEN: usize = 32;
+const HMAC_SECRET_PLACEHOLDERS: [&str; 5] = [
+    "changeme",
+    "change-me",
+    "secret",
+    "hmac_secret",
+    "your-hmac-secret",
+];
+
 pub fn hash_value(secret: &[u8], value: &str) -> Vec<u8> {
     let mut mac = <HmacSha256 as Mac>::new_from_slice(secret).unwrap();
     mac.update(value.as_bytes());
     mac.finalize().into_bytes().to_vec()
 }
 
+pub fn decode_key(encoded: &str) -> anyhow::Result<Key> {
+    let key_bytes = general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| anyhow!("ENCRYPTION_KEY must be valid base64: {e}"))?;
+
+    if key_bytes.len() != 32 {
+        return Err(anyhow!(
+            "ENCRYPTION_KEY must decode to 32 bytes (got {})",
+            key_bytes.len()
+        ));
+    }
+
+    Ok(*Key::from_slice(&key_bytes))
+}
+
+pub fn decode_hmac_secret(secret: &str) -> anyhow::Result<HmacSecret> {
+    let min_len = env::var("HMAC_SECRET_MIN_LEN")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_HMAC_SECRET_MIN_LEN);
+
+    if HMAC_SECRET_PLACEHOLDERS.contains(&secret.trim().to_lowercase().as_str()) {
+        return Err(anyhow!("HMAC_SECRET must not be a placeholder value"));
+    }
+    if secret.len() < min_len {
+        return Err(anyhow!(
+            "HMAC_SECRET must be at least {min_len} bytes (got {})",
+            secret.len()
+        ));
+    }
+
+    Ok(secret.as_bytes().to_vec())
+}
+
 pub fn encrypt_value(key: &Key, plaintext: &str) -> (Vec<u8>, Vec<u8>) {
     let cipher = ChaCha20Poly1305::new(key);
     let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng); // 96-bit
@@ -29,3 +79,98 @@ pub fn decrypt_value(key: &Key, ciphertext: &[u8], nonce: &[u8]) -> Option<Strin
         Err(_) => None, // decryption failed
     }
 }
+
+/// Decrypts the fields of a single listing row and remembers if any of them failed.
+pub struct RowDecryptor<'a> {
+    key: &'a Key,
+    pub ok: bool,
+}
+
+impl<'a> RowDecryptor<'a> {
+    pub fn new(key: &'a Key) -> Self {
+        Self { key, ok: true }
+    }
+
+    pub fn decrypt(&mut self, ciphertext: &[u8], nonce: &[u8]) -> Option<String> {
+        let value = decrypt_value(self.key, ciphertext, nonce);
+        self.ok &= value.is_some();
+        value
+    }
+}
+
+// DECRYPT_FAILURE_POLICY decides what listings do with rows that can't be decrypted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum DecryptFailurePolicy {
+    /// Leave the row out of the listing
+    Skip,
+    /// Keep the row with blank fields, flagged through `decrypt_ok`
+    #[default]
+    Mask,
+    /// Fail the whole request
+    Error,
+}
+
+impl DecryptFailurePolicy {
+    pub fn from_env() -> Self {
+        settings::get_parsed("DECRYPT_FAILURE_POLICY").unwrap_or_default()
+    }
+
+    pub fn apply<T>(
+        self,
+        mut rows: Vec<T>,
+        decrypt_ok: impl Fn(&T) -> bool,
+    ) -> Result<Vec<T>, ApiError> {
+        let failed = rows.iter().filter(|row| !decrypt_ok(row)).count();
+        if failed == 0 {
+            return Ok(rows);
+        }
+        log::warn!("{failed} sor titkosított mezői nem fejthetők vissza ({self:?})");
+
+        match self {
+            Self::Skip => rows.retain(|row| decrypt_ok(row)),
+            Self::Mask => {}
+            Self::Error => {
+                return Err(ApiError::Internal(
+                    "Sérült titkosított adat a listában!".to_string(),
+                ));
+            }
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_must_be_32_bytes_of_base64() {
+        let key = decode_key(&format!(
+            " {} ",
+            general_purpose::STANDARD.encode([7u8; 32])
+        ))
+        .unwrap();
+        assert_eq!(key, Key::from([7u8; 32]));
+
+        let err = decode_key("nem base64!").unwrap_err();
+        assert!(err.to_string().contains("valid base64"));
+
+        let err = decode_key(&general_purpose::STANDARD.encode([7u8; 16])).unwrap_err();
+        assert!(err.to_string().contains("(got 16)"));
+    }
+
+    #[test]
+    fn placeholder_and_short_hmac_secrets_are_refused() {
+        for placeholder in ["changeme", " ChangeMe ", "SECRET", "\tyour-hmac-secret\n"] {
+            let err = decode_hmac_secret(placeholder).unwrap_err();
+            assert!(err.to_string().contains("placeholder"), "{placeholder:?}");
+        }
+
+        let err = decode_hmac_secret("rövid-titok").unwrap_err();
+        assert!(err.to_string().contains("at least"));
+
+        let secret = "a".repeat(DEFAULT_HMAC_SECRET_MIN_LEN);
+        assert_eq!(decode_hmac_secret(&secret).unwrap(), secret.into_bytes());
+    }
+}
@@ -1,7 +1,13 @@
-use actix_web::{HttpResponse, ResponseError};
+use actix_web::{HttpResponse, ResponseError, http::header};
 use serde::Serialize;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
+use crate::database;
+
+// Seconds clients are asked to wait before retrying a 503
+const RETRY_AFTER_SECS: u32 = 5;
+pub const INTERNAL_ERROR_MESSAGE: &str = "Váratlan hiba történt, kérlek próbáld újra később!";
+
 #[derive(Debug)]
 pub enum ApiError {
     Validation(String),
@@ -9,9 +15,24 @@ pub enum ApiError {
     Unauthorized(String),
     Forbidden(String),
     Conflict(String),
+    Unavailable(String),
     Internal(String),
 }
 
+impl ApiError {
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::Validation(msg)
+            | ApiError::NotFound(msg)
+            | ApiError::Unauthorized(msg)
+            | ApiError::Forbidden(msg)
+            | ApiError::Conflict(msg)
+            | ApiError::Unavailable(msg)
+            | ApiError::Internal(msg) => msg,
+        }
+    }
+}
+
 impl Display for ApiError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
@@ -20,11 +41,14 @@ impl Display for ApiError {
             ApiError::Unauthorized(msg) => write!(f, "unauthorized: {}", msg),
             ApiError::Forbidden(msg) => write!(f, "forbidden: {}", msg),
             ApiError::Conflict(msg) => write!(f, "conflict: {}", msg),
+            ApiError::Unavailable(msg) => write!(f, "service unavailable: {}", msg),
             ApiError::Internal(msg) => write!(f, "internal server error: {}", msg),
         }
     }
 }
 
+impl std::error::Error for ApiError {}
+
 #[derive(Serialize)]
 struct ErrorBody {
     error: String,
@@ -48,6 +72,9 @@ impl ResponseError for ApiError {
             ApiError::Conflict(msg) => {
                 HttpResponse::Conflict().json(ErrorBody { error: msg.clone() })
             }
+            ApiError::Unavailable(msg) => HttpResponse::ServiceUnavailable()
+                .insert_header((header::RETRY_AFTER, RETRY_AFTER_SECS.to_string()))
+                .json(ErrorBody { error: msg.clone() }),
             ApiError::Internal(msg) => {
                 HttpResponse::InternalServerError().json(ErrorBody { error: msg.clone() })
             }
@@ -57,6 +84,93 @@ impl ResponseError for ApiError {
 
 impl From<anyhow::Error> for ApiError {
     fn from(err: anyhow::Error) -> Self {
-        ApiError::Internal(err.to_string())
+        let err = match err.downcast::<ApiError>() {
+            Ok(api_error) => return api_error,
+            Err(err) => err,
+        };
+
+        match err.downcast::<sqlx::Error>() {
+            Ok(sqlx_error) => database::map_pg_error(sqlx_error),
+            Err(err) => classify(err),
+        }
+    }
+}
+
+// Models still return plain `anyhow!` messages for domain errors, their wording decides
+// the status. Anything unrecognised is logged and hidden behind a generic 500.
+fn classify(err: anyhow::Error) -> ApiError {
+    let message = err.to_string();
+    let lowercase = message.to_lowercase();
+    let mentions = |needles: &[&str]| needles.iter().any(|needle| lowercase.contains(needle));
+
+    if mentions(&["nem található", "nem létezik", "nem létező"]) {
+        ApiError::NotFound(message)
+    } else if mentions(&["már létezik", "már szerepel"]) {
+        ApiError::Conflict(message)
+    } else if mentions(&["nincs jogosultságod"]) {
+        ApiError::Forbidden(message)
+    } else if mentions(&["helytelen jelszó"]) {
+        // A failed sign-in, checked before the generic "helytelen" below
+        ApiError::Unauthorized(message)
+    } else if mentions(&["kötelező", "helytelen"]) {
+        ApiError::Validation(message)
+    } else if mentions(&["lejárt"]) {
+        ApiError::Unauthorized(message)
+    } else {
+        log::error!("Unhandled error: {err:#}");
+        ApiError::Internal(INTERNAL_ERROR_MESSAGE.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    fn classified(message: &str) -> ApiError {
+        ApiError::from(anyhow!(message.to_string()))
+    }
+
+    #[test]
+    fn messages_are_classified_by_their_wording() {
+        for message in [
+            "Felhasználó nem található!",
+            "Felhasználó nem létezik",
+            "Nem létező ügyfél",
+        ] {
+            assert!(
+                matches!(classified(message), ApiError::NotFound(_)),
+                "{message}"
+            );
+        }
+        for message in ["A jelölt már szerepel!", "Ez a felhasználónév már létezik."] {
+            assert!(
+                matches!(classified(message), ApiError::Conflict(_)),
+                "{message}"
+            );
+        }
+        assert!(matches!(
+            classified("Ehez a folyamathoz nincs jogosultságod!"),
+            ApiError::Forbidden(_)
+        ));
+        assert!(matches!(
+            classified("Helytelen jelszó!"),
+            ApiError::Unauthorized(_)
+        ));
+        for message in ["Email megadása kötelező!", "Helytelen ellenőrző kód!"] {
+            assert!(
+                matches!(classified(message), ApiError::Validation(_)),
+                "{message}"
+            );
+        }
+        assert!(matches!(
+            classified("Az ellenőrző kód lejárt, jelentkezz be újra!"),
+            ApiError::Unauthorized(_)
+        ));
+        assert!(matches!(
+            classified("connection reset"),
+            ApiError::Internal(msg) if msg == INTERNAL_ERROR_MESSAGE
+        ));
     }
 }
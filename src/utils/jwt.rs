@@ -1,3 +1,5 @@
+use std::env;
+
 use actix_web::web;
 use jsonwebtoken::errors::Error as JwtError;
 use jsonwebtoken::{
@@ -7,9 +9,56 @@ use serde::{Deserialize, Serialize};
 
 use crate::extractors::authentication_token::Claims;
 
-pub async fn generate_jwt_token(id: usize, secret: String) -> String {
-    let exp: usize = (chrono::Utc::now() + chrono::Duration::hours(3)).timestamp() as usize;
-    let claims: Claims = Claims { id, exp };
+pub const IMPERSONATION_EXPIRE_SECONDS: i64 = 15 * 60;
+// Sign-in sessions, and the refresh tokens kept in them, outlive the access tokens
+pub const REFRESH_TOKEN_EXPIRE_SECONDS: i64 = 14 * 24 * 60 * 60;
+const DEFAULT_ACCESS_TOKEN_MINUTES: i64 = 3 * 60;
+
+pub fn access_token_seconds() -> i64 {
+    env::var("ACCESS_TOKEN_MINUTES")
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .filter(|minutes| *minutes > 0)
+        .unwrap_or(DEFAULT_ACCESS_TOKEN_MINUTES)
+        * 60
+}
+
+pub async fn generate_jwt_token(id: usize, sid: Option<String>, secret: String) -> String {
+    encode_claims(id, None, sid, access_token_seconds(), secret)
+}
+
+// `id` is the impersonated user, `act` the real actor behind the token
+pub async fn generate_impersonation_token(
+    id: usize,
+    act: usize,
+    sid: String,
+    secret: String,
+) -> String {
+    encode_claims(
+        id,
+        Some(act),
+        Some(sid),
+        IMPERSONATION_EXPIRE_SECONDS,
+        secret,
+    )
+}
+
+fn encode_claims(
+    id: usize,
+    act: Option<usize>,
+    sid: Option<String>,
+    expire_seconds: i64,
+    secret: String,
+) -> String {
+    let now = chrono::Utc::now();
+    let exp: usize = (now + chrono::Duration::seconds(expire_seconds)).timestamp() as usize;
+    let claims: Claims = Claims {
+        id,
+        exp,
+        iat: Some(now.timestamp() as usize),
+        sid,
+        act,
+    };
     let token = encode(
         &Header::default(),
         &claims,
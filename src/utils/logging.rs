@@ -0,0 +1,84 @@
+use std::env;
+
+use tracing_subscriber::EnvFilter;
+
+const DEFAULT_LOG_LEVEL: &str = "info";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+impl LogFormat {
+    fn from_env() -> Self {
+        Self::parse(env::var("LOG_FORMAT").ok().as_deref())
+    }
+
+    fn parse(value: Option<&str>) -> Self {
+        match value {
+            Some(value) if value.eq_ignore_ascii_case("json") => LogFormat::Json,
+            _ => LogFormat::Pretty,
+        }
+    }
+}
+
+/// Logging is enabled by the legacy `-log` flag or by setting `RUST_LOG`/`LOG_LEVEL`.
+fn is_enabled() -> bool {
+    env::args().any(|arg| arg == "-log")
+        || env::var_os("RUST_LOG").is_some()
+        || env::var_os("LOG_LEVEL").is_some()
+}
+
+/// `RUST_LOG` takes precedence over `LOG_LEVEL`, falling back to "info".
+fn env_filter() -> EnvFilter {
+    let directives = filter_directives(env::var("RUST_LOG").ok(), env::var("LOG_LEVEL").ok());
+
+    EnvFilter::try_new(&directives).unwrap_or_else(|e| {
+        eprintln!("Invalid log filter '{directives}': {e}, falling back to {DEFAULT_LOG_LEVEL}");
+        EnvFilter::new(DEFAULT_LOG_LEVEL)
+    })
+}
+
+fn filter_directives(rust_log: Option<String>, log_level: Option<String>) -> String {
+    rust_log
+        .or(log_level)
+        .unwrap_or_else(|| DEFAULT_LOG_LEVEL.to_string())
+}
+
+pub fn init() {
+    if !is_enabled() {
+        return;
+    }
+
+    let builder = tracing_subscriber::fmt().with_env_filter(env_filter());
+    let result = match LogFormat::from_env() {
+        LogFormat::Json => builder.json().try_init(),
+        LogFormat::Pretty => builder.try_init(),
+    };
+
+    if let Err(e) = result {
+        eprintln!("Failed to initialize logger: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_json_selects_the_json_format() {
+        assert_eq!(LogFormat::parse(Some("JSON")), LogFormat::Json);
+        assert_eq!(LogFormat::parse(Some("json")), LogFormat::Json);
+        assert_eq!(LogFormat::parse(Some("pretty")), LogFormat::Pretty);
+        assert_eq!(LogFormat::parse(None), LogFormat::Pretty);
+    }
+
+    #[test]
+    fn rust_log_wins_over_log_level() {
+        let some = |value: &str| Some(value.to_string());
+        assert_eq!(filter_directives(some("debug"), some("warn")), "debug");
+        assert_eq!(filter_directives(None, some("warn")), "warn");
+        assert_eq!(filter_directives(None, None), DEFAULT_LOG_LEVEL);
+    }
+}
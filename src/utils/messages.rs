@@ -0,0 +1,61 @@
+pub const CONTRACT_CREATED: &str = "Szerződés sikeresen létrehozva!";
+pub const CONTRACT_MODIFIED: &str = "Sikeresen megváltoztattad a szerződést!";
+pub const CONTRACT_FIRST_PAYMENT_CHANGED: &str = "Szerződés első díj befizetés módosítva!";
+pub const CONTRACT_STATUS_CHANGED: &str = "Szerződés státusza megváltoztatva!";
+pub const CONTRACT_HANDLER_CHANGED: &str = "Szerződésért felelős üzletkötő megváltoztatva!";
+pub const CONTRACTS_DELETED: &str = "Szerződés(ek) sikeresen törölve!";
+
+pub const CUSTOMER_CREATED: &str = "Ügyfél sikeresen létrehozva!";
+pub const CUSTOMER_MODIFIED: &str = "Sikeresen módosítottad az ügyfelet!";
+pub const CUSTOMER_COMMENT_SAVED: &str = "Sikeresen elmentetted az ügyfél megjegyzését!";
+pub const CUSTOMER_FLAG_CHANGED: &str = "Ügyfél jelölése sikeresen módosítva!";
+pub const CUSTOMER_HANDLER_CHANGED: &str = "Ügyfelet kezelő üzletkötő sikeresen megváltoztatva!";
+pub const CUSTOMERS_DELETED: &str = "Ügyfél(ek) sikeresen törölve!";
+
+pub const INTERVENTION_TASK_CREATED: &str = "Intervenciós feladat sikeresen létrehozva!";
+pub const INTERVENTION_TASK_MODIFIED: &str = "Sikeresen megváltoztattad az intervenciós feladatot!";
+pub const INTERVENTION_TASK_HANDLER_CHANGED: &str =
+    "Intervenciós feladat(ok)ért felelős üzletkötő megváltoztatva!";
+pub const INTERVENTION_TASKS_STATUS_CHANGED: &str =
+    "Intervenciós feladat(ok) státusza megváltoztatva!";
+pub const INTERVENTION_TASKS_DELETED: &str = "Intervenciós feladat(ok) sikeresen törölve!";
+
+pub const LEAD_CREATED: &str = "Tevékenység sikeresen létrehozva!";
+pub const LEAD_MODIFIED: &str = "Sikeresen megváltoztattad a tevékenységet!";
+pub const LEAD_HANDLER_CHANGED: &str = "Tevékenység(ek)ért felelős üzletkötő megváltoztatva!";
+pub const LEADS_DELETED: &str = "Tevékenység(ek) sikeresen törölve!";
+
+pub const RECOMMENDATION_CREATED: &str = "Ajánlás sikeresen létrehozva!";
+pub const RECOMMENDATION_MODIFIED: &str = "Ajánlás sikeresen módosítva!";
+pub const RECOMMENDATION_HANDLER_CHANGED: &str = "Ajánlás(ok)ért felelős üzletkötő megváltoztatva!";
+pub const RECOMMENDATIONS_DELETED: &str = "Ajánlás(ok) sikeresen törölve!";
+
+pub const RECRUITMENT_CREATED: &str = "Jelentkező sikeresen létrehozva!";
+pub const RECRUITMENT_MODIFIED: &str = "Jelentkező sikeresen módosítva!";
+pub const RECRUITMENTS_DELETED: &str = "Jelentkező(k) sikeresen törölve!";
+
+pub const USER_CREATED: &str = "Sikeresen létrehoztad a felhasználót!";
+pub const USER_SELF_INFO_MODIFIED: &str = "Sikeresen megváltoztattad az adataidat!";
+pub const USER_INFO_MODIFIED: &str = "Sikeresen megváltoztattad a felhasználó adatait!";
+pub const USER_MANAGER_MODIFIED: &str = "Sikeresen megváltoztattad a felhasználó menedzserét!";
+pub const USER_TWO_FACTOR_MODIFIED: &str = "Kétlépcsős azonosítás beállítása módosítva!";
+pub const USER_PASSWORD_RESET: &str = "Sikeresen visszaállítottad a felhasználó jelszavát!";
+pub const USER_PASSWORD_CHANGED: &str = "Sikeresen megváltoztattad a jelszavadat!";
+pub const USER_TEMPORARY_PASSWORD_GENERATED: &str = "Ideiglenes jelszó sikeresen létrehozva!";
+pub const USER_DELETED: &str = "Sikeresen kitörölted a felhasználót!";
+pub const USER_RECORDS_REASSIGNED: &str = "Rekordok sikeresen átadva!";
+pub const USER_SESSIONS_REVOKED: &str = "Többi munkamenet sikeresen kijelentkeztetve!";
+pub const USER_LOGGED_OUT: &str = "Sikeresen kijelentkeztél!";
+pub const USER_NOTIFICATIONS_MODIFIED: &str = "Értesítési beállítások sikeresen módosítva!";
+
+pub const USER_DATE_CREATED: &str = "Időpont sikeresen létrehozva!";
+pub const USER_DATE_MODIFIED: &str = "Időpont sikeresen módosítva!";
+pub const USER_DATE_STATE_CHANGED: &str = "Időpont státusza megváltoztatva!";
+pub const USER_DATES_STATE_CHANGED: &str = "Időpontok státusza megváltoztatva!";
+pub const USER_DATE_HANDLER_CHANGED: &str = "Időpontért felelős üzletkötő megváltoztatva!";
+pub const USER_DATES_DELETED: &str = "Időpont(ok) sikeresen törölve!";
+
+pub const SETTING_MODIFIED: &str = "Beállítás sikeresen módosítva!";
+pub const SETTINGS_REFRESHED: &str = "Beállítások sikeresen újratöltve!";
+
+pub const WEBHOOK_DELETED: &str = "Webhook sikeresen törölve!";
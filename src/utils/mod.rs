@@ -1,5 +1,15 @@
+pub mod cache;
+pub mod contact;
+pub mod cors;
 pub mod encrypt;
 pub mod error;
 pub mod jwt;
+pub mod logging;
+pub mod messages;
+pub mod pagination;
+pub mod pdf;
 pub mod password_hashing;
-// pub mod redis;
+pub mod validation;
+pub mod webhook;
+pub mod redis;
+pub mod settings;
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+pub struct PaginationQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub count: Option<bool>,
+}
+
+impl PaginationQuery {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0).max(0)
+    }
+
+    pub fn with_total(&self) -> bool {
+        self.count.unwrap_or(false)
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    pub total: Option<i64>,
+    pub limit: i64,
+    pub offset: i64,
+    pub has_more: bool,
+}
+
+impl<T> Paginated<T> {
+    /// `items` is expected to be fetched with `LIMIT limit + 1`, the extra row only signals `has_more`.
+    pub fn new(mut items: Vec<T>, total: Option<i64>, limit: i64, offset: i64) -> Self {
+        let has_more = items.len() as i64 > limit;
+        items.truncate(limit as usize);
+
+        Self {
+            items,
+            total,
+            limit,
+            offset,
+            has_more,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(rows: i64) -> Paginated<i64> {
+        Paginated::new((0..rows).collect(), None, 3, 0)
+    }
+
+    #[test]
+    fn the_extra_row_only_sets_has_more() {
+        let full = page(3);
+        assert_eq!((full.items, full.has_more), (vec![0, 1, 2], false));
+
+        let more = page(4);
+        assert_eq!((more.items, more.has_more), (vec![0, 1, 2], true));
+
+        let short = page(2);
+        assert_eq!((short.items, short.has_more), (vec![0, 1], false));
+    }
+}
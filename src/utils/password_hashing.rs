@@ -3,6 +3,8 @@ use argon2::{
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
 
+use crate::utils::error::ApiError;
+
 pub fn hash_password(password: &str) -> String {
     let salt = SaltString::generate(&mut OsRng);
     let argon2 = Argon2::default();
@@ -18,3 +20,21 @@ pub fn verify_password(password: &str, hashed_password: &str) -> bool {
         .verify_password(password.as_bytes(), &parsed_hash)
         .is_ok()
 }
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+pub fn validate_password_policy(password: &str) -> Result<(), ApiError> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(ApiError::Validation(format!(
+            "A jelszónak legalább {MIN_PASSWORD_LENGTH} karakter hosszúnak kell lennie!"
+        )));
+    }
+
+    if !password.chars().any(|c| c.is_alphabetic()) || !password.chars().any(|c| c.is_numeric()) {
+        return Err(ApiError::Validation(
+            "A jelszónak betűt és számot is tartalmaznia kell!".to_string(),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+// Minimal single page PDF writer for short summaries, using the built-in Helvetica
+// fonts so no font files or external renderer are needed
+
+const PAGE_WIDTH: u32 = 595;
+const PAGE_HEIGHT: u32 = 842;
+const MARGIN: u32 = 56;
+const TITLE_SIZE: u32 = 18;
+const FONT_SIZE: u32 = 11;
+const LINE_HEIGHT: u32 = 20;
+const VALUE_OFFSET: u32 = 180;
+
+/// Encodes text as WinAnsi bytes. Characters outside of it are approximated
+/// (ő -> ö, ű -> ü) or replaced with '?'.
+fn encode_text(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            'ő' => 'ö',
+            'Ő' => 'Ö',
+            'ű' => 'ü',
+            'Ű' => 'Ü',
+            _ => c,
+        })
+        .flat_map(|c| {
+            let byte = if (c as u32) < 0x100 && !c.is_control() {
+                c as u8
+            } else {
+                b'?'
+            };
+            match byte {
+                b'(' | b')' | b'\\' => vec![b'\\', byte],
+                _ => vec![byte],
+            }
+        })
+        .collect()
+}
+
+fn text_op(content: &mut Vec<u8>, font: &str, size: u32, x: u32, y: u32, text: &str) {
+    content.extend_from_slice(format!("BT /{font} {size} Tf {x} {y} Td (").as_bytes());
+    content.extend(encode_text(text));
+    content.extend_from_slice(b") Tj ET\n");
+}
+
+/// Renders a title followed by label/value rows on a single A4 page.
+pub fn render_summary(title: &str, fields: &[(&str, String)]) -> Vec<u8> {
+    let mut content = Vec::new();
+    let mut y = PAGE_HEIGHT - MARGIN - TITLE_SIZE;
+    text_op(&mut content, "F2", TITLE_SIZE, MARGIN, y, title);
+    y -= LINE_HEIGHT * 2;
+
+    for (label, value) in fields {
+        text_op(&mut content, "F2", FONT_SIZE, MARGIN, y, label);
+        text_op(
+            &mut content,
+            "F1",
+            FONT_SIZE,
+            MARGIN + VALUE_OFFSET,
+            y,
+            value,
+        );
+        y = y.saturating_sub(LINE_HEIGHT);
+    }
+
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] \
+             /Resources << /Font << /F1 4 0 R /F2 5 0 R >> >> /Contents 6 0 R >>"
+        )
+        .into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+    ];
+    let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+    stream.extend(content);
+    stream.extend_from_slice(b"\nendstream");
+    objects.push(stream);
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+
+    let xref_offset = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .as_bytes(),
+    );
+
+    pdf
+}
@@ -1,33 +1,200 @@
 extern crate redis;
-use redis::Commands;
+use std::collections::HashMap;
+
+use redis::{AsyncCommands, aio::ConnectionManager};
 
 pub struct Redis;
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum OtpStatus {
+    Valid,
+    Invalid,
+    Expired,
+    TooManyAttempts,
+}
+
 impl Redis {
-    pub fn set_token_to_user(
-        con: &mut redis::Connection,
-        user_id: u32,
-        token: &str,
+    pub async fn create_session(
+        con: &mut ConnectionManager,
+        user_id: i32,
+        sid: &str,
+        device: &str,
+        issued_at: i64,
         exp_time: i64,
     ) -> redis::RedisResult<()> {
-        con.set::<_, _, String>(token, format!("user:{user_id}"))?;
-        con.expire::<_, ()>(token, exp_time)?;
+        let session_key = format!("session:{sid}");
+        let sessions_key = format!("sessions:{user_id}");
+        redis::pipe()
+            .atomic()
+            .hset_multiple(
+                &session_key,
+                &[
+                    ("user_id", user_id.to_string()),
+                    ("device", device.to_string()),
+                    ("issued_at", issued_at.to_string()),
+                    ("expires_at", (issued_at + exp_time).to_string()),
+                ],
+            )
+            .expire(&session_key, exp_time)
+            .sadd(&sessions_key, sid)
+            .query_async::<()>(con)
+            .await?;
+
+        // The index lives as long as the longest session, a short one must not cut it down
+        if con.ttl::<_, i64>(&sessions_key).await? < exp_time {
+            con.expire::<_, ()>(&sessions_key, exp_time).await?;
+        }
 
         Ok(())
     }
 
-    pub fn get_user_id_by_token(
-        con: &mut redis::Connection,
+    pub async fn set_refresh_token(
+        con: &mut ConnectionManager,
+        sid: &str,
         token: &str,
-    ) -> redis::RedisResult<i32> {
-        let is_exists = con.exists::<_, bool>(&token)?;
-        if is_exists {
-            let redis_value = con.get::<_, String>(&token)?;
-            let user_id = redis_value[5..].parse::<i32>().unwrap();
+    ) -> redis::RedisResult<()> {
+        con.hset::<_, _, _, ()>(format!("session:{sid}"), "refresh_token", token)
+            .await
+    }
+
+    /// Swaps the session's refresh token only if `token` is the current one, so every
+    /// token works once. Returns the session's user id when the swap happened.
+    pub async fn rotate_refresh_token(
+        con: &mut ConnectionManager,
+        sid: &str,
+        token: &str,
+        new_token: &str,
+    ) -> redis::RedisResult<Option<i32>> {
+        // Compare and swap in one script, two parallel refreshes can't both pass
+        let script = redis::Script::new(
+            r"
+            if redis.call('HGET', KEYS[1], 'refresh_token') ~= ARGV[1] then
+                return false
+            end
+            redis.call('HSET', KEYS[1], 'refresh_token', ARGV[2])
+            return redis.call('HGET', KEYS[1], 'user_id')
+            ",
+        );
+        let user_id = script
+            .key(format!("session:{sid}"))
+            .arg(token)
+            .arg(new_token)
+            .invoke_async::<Option<String>>(con)
+            .await?;
+
+        Ok(user_id.and_then(|user_id| user_id.parse().ok()))
+    }
+
+    pub async fn is_session_active(
+        con: &mut ConnectionManager,
+        sid: &str,
+    ) -> redis::RedisResult<bool> {
+        con.exists(format!("session:{sid}")).await
+    }
+
+    /// Returns the user's live sessions, pruning ids whose session already expired.
+    pub async fn get_sessions(
+        con: &mut ConnectionManager,
+        user_id: i32,
+    ) -> redis::RedisResult<Vec<(String, HashMap<String, String>)>> {
+        let sessions_key = format!("sessions:{user_id}");
+        let sids = con.smembers::<_, Vec<String>>(&sessions_key).await?;
 
-            return Ok(user_id);
+        let mut sessions = Vec::with_capacity(sids.len());
+        for sid in sids {
+            let fields = con
+                .hgetall::<_, HashMap<String, String>>(format!("session:{sid}"))
+                .await?;
+            if fields.is_empty() {
+                con.srem::<_, _, ()>(&sessions_key, &sid).await?;
+                continue;
+            }
+            sessions.push((sid, fields));
         }
-        Ok(-1) // Not exists
+
+        Ok(sessions)
+    }
+
+    pub async fn revoke_session(
+        con: &mut ConnectionManager,
+        user_id: i32,
+        sid: &str,
+    ) -> redis::RedisResult<bool> {
+        let (deleted, _) = redis::pipe()
+            .atomic()
+            .del(format!("session:{sid}"))
+            .srem(format!("sessions:{user_id}"), sid)
+            .query_async::<(usize, usize)>(con)
+            .await?;
+
+        Ok(deleted > 0)
+    }
+
+    pub async fn revoke_sessions(
+        con: &mut ConnectionManager,
+        user_id: i32,
+        keep_sid: Option<&str>,
+    ) -> redis::RedisResult<usize> {
+        let sessions_key = format!("sessions:{user_id}");
+        let sids = con.smembers::<_, Vec<String>>(&sessions_key).await?;
+
+        let mut revoked = 0;
+        for sid in sids.iter().filter(|sid| Some(sid.as_str()) != keep_sid) {
+            revoked += con.del::<_, usize>(format!("session:{sid}")).await?;
+            con.srem::<_, _, ()>(&sessions_key, sid).await?;
+        }
+
+        Ok(revoked)
+    }
+
+    pub async fn set_otp(
+        con: &mut ConnectionManager,
+        user_id: i32,
+        code: &str,
+        exp_time: u64,
+    ) -> redis::RedisResult<()> {
+        redis::pipe()
+            .atomic()
+            .set_ex(format!("otp:{user_id}"), code, exp_time)
+            .set_ex(format!("otp_attempts:{user_id}"), 0, exp_time)
+            .query_async::<()>(con)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn verify_otp(
+        con: &mut ConnectionManager,
+        user_id: i32,
+        code: &str,
+        max_attempts: i64,
+    ) -> redis::RedisResult<OtpStatus> {
+        let otp_key = format!("otp:{user_id}");
+        let attempts_key = format!("otp_attempts:{user_id}");
+
+        let Some(stored_code) = con.get::<_, Option<String>>(&otp_key).await? else {
+            return Ok(OtpStatus::Expired);
+        };
+
+        // INCR is atomic, so parallel guesses can't exceed the limit
+        let attempts = con.incr::<_, _, i64>(&attempts_key, 1).await?;
+        if attempts > max_attempts {
+            con.del::<_, ()>(&[&otp_key, &attempts_key]).await?;
+            return Ok(OtpStatus::TooManyAttempts);
+        }
+
+        if stored_code != code {
+            return Ok(OtpStatus::Invalid);
+        }
+
+        // Only the request that actually deletes the code may use it
+        let deleted = con.del::<_, i64>(&otp_key).await?;
+        if deleted == 0 {
+            return Ok(OtpStatus::Expired);
+        }
+        con.del::<_, ()>(&attempts_key).await?;
+
+        Ok(OtpStatus::Valid)
     }
 }
 
@@ -66,3 +233,69 @@ impl Token {
         token
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{env, time::Duration};
+
+    use super::*;
+
+    const TEST_USER_ID: i32 = i32::MAX - 2;
+
+    async fn connection() -> ConnectionManager {
+        dotenvy::dotenv().ok();
+        let redis_url = env::var("REDIS_URL").expect("REDIS_URL must be set!");
+        redis::Client::open(redis_url)
+            .unwrap()
+            .get_connection_manager()
+            .await
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs REDIS_URL"]
+    async fn otp_is_accepted_once() {
+        let mut con = connection().await;
+        Redis::set_otp(&mut con, TEST_USER_ID, "123456", 60)
+            .await
+            .unwrap();
+
+        let first = Redis::verify_otp(&mut con, TEST_USER_ID, "123456", 5).await;
+        let second = Redis::verify_otp(&mut con, TEST_USER_ID, "123456", 5).await;
+
+        assert_eq!(first.unwrap(), OtpStatus::Valid);
+        assert_eq!(second.unwrap(), OtpStatus::Expired);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs REDIS_URL"]
+    async fn wrong_otp_is_rejected_and_counted() {
+        let mut con = connection().await;
+        Redis::set_otp(&mut con, TEST_USER_ID + 1, "123456", 60)
+            .await
+            .unwrap();
+
+        let wrong = Redis::verify_otp(&mut con, TEST_USER_ID + 1, "654321", 2).await;
+        let again = Redis::verify_otp(&mut con, TEST_USER_ID + 1, "654321", 2).await;
+        // The limit burns the code, even the right one is refused afterwards
+        let locked = Redis::verify_otp(&mut con, TEST_USER_ID + 1, "123456", 2).await;
+
+        assert_eq!(wrong.unwrap(), OtpStatus::Invalid);
+        assert_eq!(again.unwrap(), OtpStatus::Invalid);
+        assert_eq!(locked.unwrap(), OtpStatus::TooManyAttempts);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs REDIS_URL"]
+    async fn expired_otp_is_rejected() {
+        let mut con = connection().await;
+        Redis::set_otp(&mut con, TEST_USER_ID + 2, "123456", 1)
+            .await
+            .unwrap();
+        actix_web::rt::time::sleep(Duration::from_millis(1500)).await;
+
+        let status = Redis::verify_otp(&mut con, TEST_USER_ID + 2, "123456", 5).await;
+
+        assert_eq!(status.unwrap(), OtpStatus::Expired);
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    collections::HashMap,
+    env,
+    str::FromStr,
+    sync::{LazyLock, RwLock},
+};
+
+use crate::utils::{encrypt::DecryptFailurePolicy, error::ApiError};
+
+// How a setting is parsed by the code that reads it, overrides are checked the same way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingKind {
+    PositiveNumber,
+    PositiveCount,
+    DeadlinePolicy,
+    DecryptFailurePolicy,
+    DedupStrategy,
+    Text,
+}
+
+// Settings operators may override at runtime, named after the env variable they replace
+pub const TUNABLE_SETTINGS: [(&str, SettingKind); 6] = [
+    ("MAX_CHART_SPAN_DAYS", SettingKind::PositiveNumber),
+    ("MAX_DECRYPT_ROWS", SettingKind::PositiveCount),
+    ("PAST_DEADLINE_POLICY", SettingKind::DeadlinePolicy),
+    ("DECRYPT_FAILURE_POLICY", SettingKind::DecryptFailurePolicy),
+    ("CUSTOMER_DEDUP_STRATEGY", SettingKind::DedupStrategy),
+    ("CREATED_BY_FALLBACK", SettingKind::Text),
+];
+
+// Loaded from the `settings` table at startup and on every refresh
+static OVERRIDES: LazyLock<RwLock<HashMap<String, String>>> = LazyLock::new(Default::default);
+
+/// The stored override if there is one, otherwise the environment variable.
+pub fn get(key: &str) -> Option<String> {
+    if let Some(value) = OVERRIDES.read().unwrap().get(key) {
+        return Some(value.clone());
+    }
+    env::var(key).ok()
+}
+
+pub fn get_parsed<T: FromStr>(key: &str) -> Option<T> {
+    get(key).and_then(|value| value.parse().ok())
+}
+
+pub fn replace_overrides(values: HashMap<String, String>) {
+    *OVERRIDES.write().unwrap() = values;
+}
+
+pub fn ensure_tunable(key: &str) -> Result<SettingKind, ApiError> {
+    TUNABLE_SETTINGS
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, kind)| *kind)
+        .ok_or_else(|| ApiError::NotFound(format!("Ismeretlen beállítás: {key}")))
+}
+
+pub fn validate(key: &str, value: &str) -> Result<(), ApiError> {
+    let one_of = |options: &[&str]| {
+        options
+            .iter()
+            .any(|option| value.eq_ignore_ascii_case(option))
+    };
+    let (valid, expected) = match ensure_tunable(key)? {
+        SettingKind::PositiveNumber => (
+            value.parse::<i64>().is_ok_and(|number| number > 0),
+            "pozitív egész szám",
+        ),
+        SettingKind::PositiveCount => (
+            value.parse::<usize>().is_ok_and(|count| count > 0),
+            "pozitív egész szám",
+        ),
+        SettingKind::DeadlinePolicy => (one_of(&["reject", "warn"]), "reject vagy warn"),
+        SettingKind::DecryptFailurePolicy => (
+            DecryptFailurePolicy::from_str(value).is_ok(),
+            "skip, mask vagy error",
+        ),
+        SettingKind::DedupStrategy => (one_of(&["strict", "fuzzy"]), "strict vagy fuzzy"),
+        SettingKind::Text => (!value.is_empty(), "nem üres szöveg"),
+    };
+
+    if !valid {
+        return Err(ApiError::Validation(format!(
+            "A(z) {key} beállítás értéke érvénytelen, elvárt: {expected}!"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_checked_against_their_consumer() {
+        assert!(validate("MAX_CHART_SPAN_DAYS", "30").is_ok());
+        assert!(validate("MAX_CHART_SPAN_DAYS", "0").is_err());
+        assert!(validate("MAX_CHART_SPAN_DAYS", "-5").is_err());
+        assert!(validate("MAX_DECRYPT_ROWS", "500").is_ok());
+        assert!(validate("MAX_DECRYPT_ROWS", "0").is_err());
+        assert!(validate("MAX_DECRYPT_ROWS", "1.5").is_err());
+        assert!(validate("PAST_DEADLINE_POLICY", "Warn").is_ok());
+        assert!(validate("PAST_DEADLINE_POLICY", "ignore").is_err());
+        assert!(validate("DECRYPT_FAILURE_POLICY", "skip").is_ok());
+        assert!(validate("DECRYPT_FAILURE_POLICY", "drop").is_err());
+        assert!(validate("CUSTOMER_DEDUP_STRATEGY", "FUZZY").is_ok());
+        assert!(validate("CUSTOMER_DEDUP_STRATEGY", "loose").is_err());
+        assert!(validate("CREATED_BY_FALLBACK", "Import").is_ok());
+        assert!(matches!(
+            validate("UNKNOWN_SETTING", "1"),
+            Err(ApiError::NotFound(_))
+        ));
+    }
+}
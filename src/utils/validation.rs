@@ -0,0 +1,168 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::anyhow;
+use chrono::{NaiveDateTime, Utc};
+
+use crate::utils::{error::ApiError, settings};
+
+const DEFAULT_MAX_CHART_SPAN_DAYS: i64 = 366;
+
+pub fn max_chart_span_days() -> i64 {
+    settings::get_parsed("MAX_CHART_SPAN_DAYS").unwrap_or(DEFAULT_MAX_CHART_SPAN_DAYS)
+}
+
+pub fn validate_chart_span(
+    start_date: NaiveDateTime,
+    end_date: NaiveDateTime,
+) -> Result<(), ApiError> {
+    if end_date < start_date {
+        return Err(ApiError::Validation(
+            "A kezdő dátum nem lehet későbbi a záró dátumnál".to_string(),
+        ));
+    }
+
+    if (end_date - start_date).num_days() > max_chart_span_days() {
+        return Err(ApiError::Validation(
+            "A lekérdezett időszak túl hosszú".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+// PAST_DEADLINE_POLICY=warn only logs backdated deadlines instead of rejecting them
+pub fn reject_past_deadlines() -> bool {
+    !settings::get("PAST_DEADLINE_POLICY").is_some_and(|value| value.eq_ignore_ascii_case("warn"))
+}
+
+pub fn validate_deadline(deadline: NaiveDateTime, force: bool) -> Result<(), ApiError> {
+    if force || deadline >= Utc::now().naive_utc() {
+        return Ok(());
+    }
+
+    if reject_past_deadlines() {
+        return Err(ApiError::Validation(
+            "A feldolgozási határidő nem lehet a múltban".to_string(),
+        ));
+    }
+    log::warn!("Múltbeli feldolgozási határidő rögzítve: {deadline}");
+
+    Ok(())
+}
+
+pub fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+// Lowercases and strips Hungarian accents so free-text values compare loosely
+pub fn fold_accents(value: &str) -> String {
+    value
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' => 'a',
+            'é' => 'e',
+            'í' => 'i',
+            'ó' | 'ö' | 'ő' => 'o',
+            'ú' | 'ü' | 'ű' => 'u',
+            c => c,
+        })
+        .collect()
+}
+
+pub fn created_by_fallback() -> Option<String> {
+    settings::get("CREATED_BY_FALLBACK").filter(|value| !value.trim().is_empty())
+}
+
+const DEFAULT_MAX_DECRYPT_ROWS: usize = 1000;
+
+pub fn max_decrypt_rows() -> usize {
+    settings::get_parsed("MAX_DECRYPT_ROWS").unwrap_or(DEFAULT_MAX_DECRYPT_ROWS)
+}
+
+/// Row cap for the query behind an unpaginated listing, one more than it may decrypt so
+/// `ensure_decrypt_limit` can tell an oversized result from one that fits exactly.
+pub fn decrypt_fetch_limit() -> i64 {
+    max_decrypt_rows() as i64 + 1
+}
+
+// Decrypting is CPU heavy, so unpaginated listings refuse oversized result sets. The query
+// is capped with `decrypt_fetch_limit`, so an oversized one is never loaded in full.
+pub fn ensure_decrypt_limit(rows: usize) -> Result<(), ApiError> {
+    if rows > max_decrypt_rows() {
+        return Err(ApiError::Validation(
+            "Túl sok találat, kérlek használj lapozást".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+// Enum columns are stored as text, a value that no longer maps to a variant fails
+// the request instead of panicking the worker
+pub fn parse_stored<T>(column: &str, value: &str) -> anyhow::Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|e| anyhow!("Érvénytelen tárolt érték ({column}: '{value}'): {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate};
+
+    use super::*;
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn chart_spans_are_bounded_and_ordered() {
+        let start = day(2025, 1, 1);
+        let limit = Duration::days(DEFAULT_MAX_CHART_SPAN_DAYS);
+
+        assert!(validate_chart_span(start, start).is_ok());
+        assert!(validate_chart_span(start, start + limit).is_ok());
+        assert!(matches!(
+            validate_chart_span(start, start + limit + Duration::days(1)),
+            Err(ApiError::Validation(_))
+        ));
+        assert!(matches!(
+            validate_chart_span(start, day(2024, 12, 31)),
+            Err(ApiError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn past_deadlines_need_force() {
+        let past = Utc::now().naive_utc() - Duration::days(1);
+        let future = Utc::now().naive_utc() + Duration::days(1);
+
+        assert!(matches!(
+            validate_deadline(past, false),
+            Err(ApiError::Validation(_))
+        ));
+        assert!(validate_deadline(past, true).is_ok());
+        assert!(validate_deadline(future, false).is_ok());
+    }
+
+    #[test]
+    fn listings_over_the_decrypt_cap_are_refused() {
+        assert_eq!(decrypt_fetch_limit(), DEFAULT_MAX_DECRYPT_ROWS as i64 + 1);
+        assert!(ensure_decrypt_limit(DEFAULT_MAX_DECRYPT_ROWS).is_ok());
+        assert!(matches!(
+            ensure_decrypt_limit(decrypt_fetch_limit() as usize),
+            Err(ApiError::Validation(_))
+        ));
+    }
+}
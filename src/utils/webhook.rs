@@ -0,0 +1,292 @@
+use std::time::Duration;
+
+use actix_web::rt;
+use base64::{Engine as _, engine::general_purpose};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{
+    database::Database,
+    models::webhook::{WebhookDelivery, WebhookEvent},
+    utils::encrypt,
+};
+
+const MAX_DELIVERY_ATTEMPTS: i32 = 5;
+const DELIVERY_TIMEOUT_SECONDS: u64 = 10;
+const DELIVERY_BATCH_SIZE: i64 = 20;
+// Long enough for a whole batch to time out before another worker may retry it
+const DELIVERY_LEASE_SECONDS: f64 = (DELIVERY_TIMEOUT_SECONDS * 30) as f64;
+const POLL_INTERVAL_SECONDS: u64 = 5;
+
+#[derive(Serialize)]
+struct WebhookPayload<T: Serialize> {
+    event: WebhookEvent,
+    occurred_at: DateTime<Utc>,
+    data: T,
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookDispatcher {
+    client: reqwest::Client,
+}
+
+impl WebhookDispatcher {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(DELIVERY_TIMEOUT_SECONDS))
+            .build()
+            .expect("Failed to build webhook HTTP client");
+
+        Self { client }
+    }
+
+    // Only queues the deliveries, a slow subscriber never holds up the request and a
+    // restart doesn't lose them
+    pub async fn dispatch<T: Serialize>(&self, db: &Database, event: WebhookEvent, data: T) {
+        let body = match serde_json::to_string(&WebhookPayload {
+            event: event.clone(),
+            occurred_at: Utc::now(),
+            data,
+        }) {
+            Ok(body) => body,
+            Err(e) => {
+                log::warn!("Failed to serialize {event} webhook payload: {e}");
+                return;
+            }
+        };
+
+        match WebhookDelivery::enqueue(db, &event, &body).await {
+            Ok(0) => {}
+            // Deliver right away instead of waiting for the next poll
+            Ok(_) => {
+                let (dispatcher, db) = (self.clone(), db.clone());
+                rt::spawn(async move {
+                    if let Err(e) = dispatcher.drain(&db).await {
+                        log::warn!("Failed to deliver {event} webhooks: {e}");
+                    }
+                });
+            }
+            Err(e) => log::warn!("Failed to queue {event} webhook deliveries: {e}"),
+        }
+    }
+
+    /// Delivers queued webhooks until the server stops.
+    pub async fn run_worker(&self, db: Database) {
+        loop {
+            match self.drain(&db).await {
+                // A full batch means more may already be due
+                Ok(attempted) if attempted as i64 == DELIVERY_BATCH_SIZE => continue,
+                Ok(_) => {}
+                Err(e) => log::warn!("Failed to load queued webhook deliveries: {e}"),
+            }
+
+            // Fresh events are delivered by `dispatch`, the worker picks up retries
+            // and whatever a restart left behind
+            rt::time::sleep(Duration::from_secs(POLL_INTERVAL_SECONDS)).await;
+        }
+    }
+
+    /// Attempts every due delivery once and returns how many were attempted.
+    pub async fn drain(&self, db: &Database) -> anyhow::Result<usize> {
+        let deliveries =
+            WebhookDelivery::claim_due(db, DELIVERY_BATCH_SIZE, DELIVERY_LEASE_SECONDS).await?;
+
+        for delivery in &deliveries {
+            let recorded = match self.send(delivery).await {
+                Ok(_) => WebhookDelivery::mark_delivered(db, delivery.id).await,
+                Err(e) if delivery.attempts < MAX_DELIVERY_ATTEMPTS => {
+                    log::warn!(
+                        "Webhook delivery to {} failed (attempt {}): {e}",
+                        delivery.url,
+                        delivery.attempts
+                    );
+                    let retry_in = 2u64.pow(delivery.attempts as u32) as f64;
+                    WebhookDelivery::mark_failed(db, delivery.id, &e.to_string(), Some(retry_in))
+                        .await
+                }
+                Err(e) => {
+                    log::warn!("Giving up webhook delivery to {}: {e}", delivery.url);
+                    WebhookDelivery::mark_failed(db, delivery.id, &e.to_string(), None).await
+                }
+            };
+            if let Err(e) = recorded {
+                log::warn!("Failed to record webhook delivery {}: {e}", delivery.id);
+            }
+        }
+
+        Ok(deliveries.len())
+    }
+
+    async fn send(&self, delivery: &WebhookDelivery) -> reqwest::Result<()> {
+        let timestamp = Utc::now().timestamp();
+        let signature = sign_payload(&delivery.secret, timestamp, &delivery.body);
+
+        self.client
+            .post(&delivery.url)
+            .header("Content-Type", "application/json")
+            .header("X-Webhook-Event", &delivery.event)
+            .header("X-Webhook-Timestamp", timestamp.to_string())
+            .header("X-Webhook-Signature", signature)
+            .body(delivery.body.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+    }
+}
+
+impl Default for WebhookDispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The timestamp is signed along with the body, so a captured request can't be replayed later
+pub fn sign_payload(secret: &str, timestamp: i64, body: &str) -> String {
+    let signature = encrypt::hash_value(secret.as_bytes(), &format!("{timestamp}.{body}"));
+    format!("sha256={}", general_purpose::STANDARD.encode(signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use actix_web::{App, HttpRequest, HttpResponse, HttpServer, web};
+    use serde_json::json;
+
+    use super::*;
+    use crate::utils::redis::Token;
+
+    type Received = Mutex<Vec<(String, String, String)>>;
+
+    async fn receive(
+        request: HttpRequest,
+        body: String,
+        received: web::Data<Received>,
+    ) -> HttpResponse {
+        let header = |name: &str| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string()
+        };
+        received.lock().unwrap().push((
+            header("X-Webhook-Timestamp"),
+            header("X-Webhook-Signature"),
+            body,
+        ));
+        HttpResponse::Ok().finish()
+    }
+
+    async fn insert_webhook(db: &Database, url: String, secret: &str) -> i32 {
+        sqlx::query_scalar!(
+            "INSERT INTO webhooks(url, secret, event_types) VALUES($1, $2, ARRAY['LeadStatusChanged'])
+             RETURNING id",
+            url,
+            secret
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap()
+    }
+
+    #[test]
+    fn signature_covers_the_timestamp() {
+        let body = r#"{"event":"ContractDeleted"}"#;
+
+        assert_eq!(
+            sign_payload("secret", 1_700_000_000, body),
+            sign_payload("secret", 1_700_000_000, body)
+        );
+        assert_ne!(
+            sign_payload("secret", 1_700_000_000, body),
+            sign_payload("secret", 1_700_000_001, body)
+        );
+        assert!(sign_payload("secret", 1_700_000_000, body).starts_with("sha256="));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs DATABASE_URL and REDIS_URL"]
+    async fn queued_deliveries_are_signed_and_retried() {
+        dotenvy::dotenv().ok();
+        let db = Database::create_connection().await.unwrap();
+        let received = web::Data::new(Received::default());
+        let server = {
+            let received = received.clone();
+            HttpServer::new(move || {
+                App::new()
+                    .app_data(received.clone())
+                    .route("/ok", web::post().to(receive))
+                    .route(
+                        "/fail",
+                        web::post().to(|| async { HttpResponse::InternalServerError().finish() }),
+                    )
+            })
+        }
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let base_url = format!("http://{}", server.addrs()[0]);
+        rt::spawn(server.run());
+
+        let secret = Token::generate_token();
+        let ok_id = insert_webhook(&db, format!("{base_url}/ok"), &secret).await;
+        let fail_id = insert_webhook(&db, format!("{base_url}/fail"), &secret).await;
+
+        // Only this test subscribes to lead status changes, no other test starts a drain that
+        // could claim these deliveries
+        let marker = Token::generate_token();
+        db.webhooks
+            .dispatch(
+                &db,
+                WebhookEvent::LeadStatusChanged,
+                json!({ "marker": marker }),
+            )
+            .await;
+
+        // Delivery runs in the background, wait until both attempts are recorded
+        let mut states = Vec::new();
+        for _ in 0..50 {
+            states = sqlx::query!(
+                r#"SELECT webhook_id, attempts, delivered_at IS NOT NULL AS "delivered!",
+                          last_error IS NOT NULL AS "errored!", next_attempt_at > NOW() AS "rescheduled!"
+                   FROM webhook_deliveries
+                   WHERE webhook_id = ANY($1) AND body LIKE '%' || $2 || '%'
+                   ORDER BY webhook_id"#,
+                &[ok_id, fail_id],
+                marker
+            )
+            .fetch_all(&db.pool)
+            .await
+            .unwrap();
+            if states.len() == 2 && states[0].delivered && states[1].errored {
+                break;
+            }
+            rt::time::sleep(Duration::from_millis(100)).await;
+        }
+        sqlx::query!("DELETE FROM webhooks WHERE id = ANY($1)", &[ok_id, fail_id])
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let received = received.lock().unwrap();
+        let received: Vec<_> = received
+            .iter()
+            .filter(|(_, _, body)| body.contains(&marker))
+            .collect();
+        assert_eq!(received.len(), 1);
+        let (timestamp, signature, body) = received[0];
+        assert_eq!(
+            signature,
+            &sign_payload(&secret, timestamp.parse().unwrap(), body)
+        );
+        assert!(body.contains("LeadStatusChanged"));
+
+        assert_eq!(states.len(), 2);
+        assert!(states[0].delivered);
+        assert!(!states[1].delivered && states[1].errored && states[1].rescheduled);
+        assert_eq!(states[1].attempts, 1);
+    }
+}
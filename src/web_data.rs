@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use chacha20poly1305::Key;
 
 use crate::{database::Database, utils::encrypt::HmacSecret};
@@ -6,4 +8,6 @@ pub struct WebData {
     pub db: Database,
     pub key: Key,
     pub hmac_secret: HmacSecret,
+    // Allowed `contract_types` rows, loaded once at startup
+    pub contract_types: HashSet<String>,
 }